@@ -0,0 +1,57 @@
+CREATE TABLE members (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX members_name_user_id_idx ON members (name, user_id);
+
+CREATE TRIGGER set_members_updated_at
+    BEFORE UPDATE ON members
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
+
+CREATE TYPE split_mode AS ENUM ('equal', 'percentage', 'exact');
+
+-- A oneoff transaction has at most one split, which records who paid and how the amount is shared
+CREATE TABLE transaction_splits (
+    oneoff_transaction_id INTEGER PRIMARY KEY REFERENCES oneoff_transactions (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    paid_by_member_id INTEGER NOT NULL REFERENCES members (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    mode split_mode NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER set_transaction_splits_updated_at
+    BEFORE UPDATE ON transaction_splits
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
+
+-- `value` is the share in percent or cents depending on the split mode, and NULL for equal splits.
+-- Share amounts of equal and percentage splits are derived from the transaction amount when reading.
+CREATE TABLE transaction_split_shares (
+    oneoff_transaction_id INTEGER NOT NULL REFERENCES transaction_splits (oneoff_transaction_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    member_id INTEGER NOT NULL REFERENCES members (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    value INTEGER CHECK (value >= 0),
+    PRIMARY KEY (oneoff_transaction_id, member_id)
+);
+
+CREATE TABLE settlements (
+    id SERIAL PRIMARY KEY,
+    date DATE NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    from_member_id INTEGER NOT NULL REFERENCES members (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    to_member_id INTEGER NOT NULL REFERENCES members (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    CONSTRAINT settlements_members_check CHECK (from_member_id <> to_member_id)
+);
+
+CREATE TRIGGER set_settlements_updated_at
+    BEFORE UPDATE ON settlements
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
@@ -0,0 +1,22 @@
+CREATE TYPE api_token_scope AS ENUM ('read_only', 'transactions_only');
+
+CREATE TABLE api_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE NO ACTION ON UPDATE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    -- Public part of the token used to look it up, the secret part is only stored as hash
+    prefix VARCHAR(255) NOT NULL UNIQUE,
+    hash VARCHAR(255) NOT NULL,
+    scopes api_token_scope[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX api_tokens_user_id_idx ON api_tokens (user_id);
+
+CREATE TRIGGER set_api_tokens_updated_at
+    BEFORE UPDATE ON api_tokens
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
@@ -0,0 +1,28 @@
+-- Deleting a user removes all rows owned by them
+ALTER TABLE categories
+    DROP CONSTRAINT categories_user_id_fkey,
+    ADD CONSTRAINT categories_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE shops
+    DROP CONSTRAINT shops_user_id_fkey,
+    ADD CONSTRAINT shops_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE oneoff_transactions
+    DROP CONSTRAINT oneoff_transactions_user_id_fkey,
+    ADD CONSTRAINT oneoff_transactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE recurring_transactions
+    DROP CONSTRAINT recurring_transactions_user_id_fkey,
+    ADD CONSTRAINT recurring_transactions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE members
+    DROP CONSTRAINT members_user_id_fkey,
+    ADD CONSTRAINT members_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE settlements
+    DROP CONSTRAINT settlements_user_id_fkey,
+    ADD CONSTRAINT settlements_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE api_tokens
+    DROP CONSTRAINT api_tokens_user_id_fkey,
+    ADD CONSTRAINT api_tokens_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE;
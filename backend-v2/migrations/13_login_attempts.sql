@@ -0,0 +1,11 @@
+CREATE TABLE login_attempts (
+    id BIGSERIAL PRIMARY KEY,
+    -- Not a foreign key, attempts for unknown usernames are recorded as well
+    username VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(255),
+    succeeded BOOLEAN NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX login_attempts_username_idx ON login_attempts (username, created_at);
+CREATE INDEX login_attempts_ip_address_idx ON login_attempts (ip_address, created_at);
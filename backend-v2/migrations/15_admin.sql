@@ -0,0 +1,4 @@
+ALTER TABLE users
+    ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT false,
+    -- Disabled users can't log in and their sessions and API tokens stop working
+    ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT false;
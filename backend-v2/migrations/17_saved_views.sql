@@ -0,0 +1,16 @@
+-- Named sets of oneoff transaction list parameters. `params` holds the parameters in the camelCase format of the API.
+CREATE TABLE saved_views (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    params JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX saved_views_name_user_id_idx ON saved_views (name, user_id);
+
+CREATE TRIGGER set_saved_views_updated_at
+    BEFORE UPDATE ON saved_views
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
@@ -0,0 +1,24 @@
+-- Preferences of a user. Users without a row use the defaults of the columns.
+CREATE TYPE weekday AS ENUM ('monday', 'tuesday', 'wednesday', 'thursday', 'friday', 'saturday', 'sunday');
+CREATE TYPE currency_display AS ENUM ('symbol', 'code', 'name');
+CREATE TYPE date_format AS ENUM ('YYYY-MM-DD', 'DD.MM.YYYY', 'DD/MM/YYYY', 'MM/DD/YYYY');
+CREATE TYPE list_ordering AS ENUM ('Asc', 'Desc');
+CREATE TYPE list_order_key AS ENUM ('Time', 'Amount', 'Category', 'Shop');
+
+CREATE TABLE user_settings (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    -- IANA name, NULL uses the timezone of the server configuration
+    timezone TEXT,
+    first_day_of_week weekday NOT NULL DEFAULT 'monday',
+    currency_display currency_display NOT NULL DEFAULT 'symbol',
+    date_format date_format NOT NULL DEFAULT 'YYYY-MM-DD',
+    ordering list_ordering NOT NULL DEFAULT 'Asc',
+    order_key list_order_key NOT NULL DEFAULT 'Time',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER set_user_settings_updated_at
+    BEFORE UPDATE ON user_settings
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
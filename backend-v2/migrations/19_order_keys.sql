@@ -0,0 +1,7 @@
+-- Order keys that can be chosen as the default of transaction lists. `Frequency` and `IntervalTo` only apply to
+-- recurring transactions and are rejected as defaults, but kept in sync with the `OrderKey` enum of the backend.
+ALTER TYPE list_order_key ADD VALUE 'Description';
+ALTER TYPE list_order_key ADD VALUE 'CreatedAt';
+ALTER TYPE list_order_key ADD VALUE 'UpdatedAt';
+ALTER TYPE list_order_key ADD VALUE 'Frequency';
+ALTER TYPE list_order_key ADD VALUE 'IntervalTo';
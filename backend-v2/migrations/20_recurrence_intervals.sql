@@ -0,0 +1,31 @@
+-- Weekly, bi-weekly, quarterly and every-N-months recurrences. Weekly recurrences store the dates of their first and
+-- last possible occurrence, all others the first days of their months like before.
+-- Enum values added by `ALTER TYPE ... ADD VALUE` can't be used in the same transaction, so the type is replaced.
+ALTER TABLE recurring_transactions
+    DROP CONSTRAINT recurring_transactions_interval_from_check,
+    DROP CONSTRAINT recurring_transactions_interval_to_check,
+    DROP CONSTRAINT recurring_transactions_interval_from_month_check,
+    DROP CONSTRAINT recurring_transactions_interval_to_month_check;
+
+ALTER TYPE recurrence_frequency RENAME TO recurrence_frequency_old;
+CREATE TYPE recurrence_frequency AS ENUM ('weekly', 'biweekly', 'monthly', 'quarterly', 'every_n_months', 'yearly');
+ALTER TABLE recurring_transactions
+    ALTER COLUMN frequency TYPE recurrence_frequency USING frequency::text::recurrence_frequency;
+DROP TYPE recurrence_frequency_old;
+
+-- Number of months between two occurrences of `every_n_months` recurrences
+ALTER TABLE recurring_transactions ADD COLUMN interval_months INTEGER;
+
+ALTER TABLE recurring_transactions
+    ADD CONSTRAINT recurring_transactions_interval_from_check
+        CHECK (EXTRACT(DAY FROM interval_from) = 1 OR frequency IN ('weekly', 'biweekly')),
+    ADD CONSTRAINT recurring_transactions_interval_to_check
+        CHECK (EXTRACT(DAY FROM interval_to) = 1 OR frequency IN ('weekly', 'biweekly')),
+    ADD CONSTRAINT recurring_transactions_interval_from_month_check
+        CHECK (EXTRACT(MONTH FROM interval_from) = 1 OR frequency <> 'yearly'),
+    ADD CONSTRAINT recurring_transactions_interval_to_month_check
+        CHECK (EXTRACT(MONTH FROM interval_to) = 1 OR frequency <> 'yearly'),
+    ADD CONSTRAINT recurring_transactions_interval_months_check
+        CHECK ((interval_months IS NOT NULL) = (frequency = 'every_n_months')),
+    ADD CONSTRAINT recurring_transactions_interval_months_range_check
+        CHECK (interval_months BETWEEN 2 AND 120);
@@ -0,0 +1,21 @@
+-- Day of the month that month based and yearly recurrences fall on, clamped to the last day of shorter months.
+-- Yearly recurrences additionally store their month. Without them, occurrences fall on the first day of the month
+-- or year like before.
+ALTER TABLE recurring_transactions
+    ADD COLUMN day_of_month SMALLINT,
+    ADD COLUMN month_of_year SMALLINT;
+
+ALTER TABLE recurring_transactions
+    ADD CONSTRAINT recurring_transactions_day_of_month_range_check
+        CHECK (day_of_month BETWEEN 1 AND 31),
+    ADD CONSTRAINT recurring_transactions_month_of_year_range_check
+        CHECK (month_of_year BETWEEN 1 AND 12),
+    ADD CONSTRAINT recurring_transactions_day_of_month_check
+        CHECK (day_of_month IS NULL OR frequency NOT IN ('weekly', 'biweekly')),
+    ADD CONSTRAINT recurring_transactions_month_of_year_check
+        CHECK (
+            CASE frequency
+                WHEN 'yearly' THEN (month_of_year IS NULL) = (day_of_month IS NULL)
+                ELSE month_of_year IS NULL
+            END
+        );
@@ -0,0 +1,31 @@
+-- Changes of the amount of a recurring transaction. A revision applies from the first day of its month on, before the
+-- first revision the `amount` of the transaction applies.
+CREATE TABLE recurring_transaction_amounts (
+    id SERIAL PRIMARY KEY,
+    recurring_transaction_id INTEGER NOT NULL REFERENCES recurring_transactions (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    effective_from DATE NOT NULL CONSTRAINT recurring_transaction_amounts_effective_from_check
+        CHECK (EXTRACT(DAY FROM effective_from) = 1),
+    amount INTEGER NOT NULL CONSTRAINT recurring_transaction_amounts_amount_check CHECK (amount > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX recurring_transaction_amounts_effective_from_idx
+    ON recurring_transaction_amounts (recurring_transaction_id, effective_from);
+
+-- Amount of a recurring transaction that applies on a day, `base_amount` is the `amount` of the transaction
+CREATE FUNCTION recurring_transaction_amount(transaction_id INTEGER, base_amount INTEGER, day DATE)
+    RETURNS INTEGER
+    LANGUAGE sql
+    STABLE
+AS $$
+    SELECT COALESCE(
+        (
+            SELECT amount
+            FROM recurring_transaction_amounts
+            WHERE recurring_transaction_id = transaction_id AND effective_from <= day
+            ORDER BY effective_from DESC
+            LIMIT 1
+        ),
+        base_amount
+    )
+$$;
@@ -0,0 +1,49 @@
+-- Exceptions of the occurrences of a recurring transaction in a month, e.g. a paused subscription or a higher bill.
+-- Skipped occurrences don't take place, all others can override the amount and the description.
+CREATE TABLE recurring_transaction_exceptions (
+    id SERIAL PRIMARY KEY,
+    recurring_transaction_id INTEGER NOT NULL REFERENCES recurring_transactions (id) ON DELETE CASCADE ON UPDATE CASCADE,
+    month DATE NOT NULL CONSTRAINT recurring_transaction_exceptions_month_check
+        CHECK (EXTRACT(DAY FROM month) = 1),
+    skip BOOLEAN NOT NULL,
+    amount INTEGER CONSTRAINT recurring_transaction_exceptions_amount_check CHECK (amount > 0),
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    CONSTRAINT recurring_transaction_exceptions_override_check
+        CHECK (skip = (amount IS NULL AND description IS NULL))
+);
+
+CREATE UNIQUE INDEX recurring_transaction_exceptions_month_idx
+    ON recurring_transaction_exceptions (recurring_transaction_id, month);
+
+CREATE TRIGGER set_recurring_transaction_exceptions_updated_at
+    BEFORE UPDATE ON recurring_transaction_exceptions
+    FOR EACH ROW
+    EXECUTE PROCEDURE set_current_timestamp_updated_at();
+
+-- Amount of the occurrence of a recurring transaction in the period of a day, i.e. its year for yearly transactions
+-- and its month for all others. `base_amount` is the amount that applies on the day, skipped occurrences amount to 0.
+CREATE FUNCTION recurring_transaction_period_amount(
+    transaction_id INTEGER,
+    transaction_frequency recurrence_frequency,
+    base_amount INTEGER,
+    day DATE
+)
+    RETURNS INTEGER
+    LANGUAGE sql
+    STABLE
+AS $$
+    SELECT COALESCE(
+        (
+            SELECT CASE WHEN e.skip THEN 0 ELSE COALESCE(e.amount, base_amount) END
+            FROM recurring_transaction_exceptions e
+            WHERE e.recurring_transaction_id = transaction_id
+                AND date_trunc(CASE WHEN transaction_frequency = 'yearly' THEN 'year' ELSE 'month' END, e.month)
+                    = date_trunc(CASE WHEN transaction_frequency = 'yearly' THEN 'year' ELSE 'month' END, day)
+            ORDER BY e.month
+            LIMIT 1
+        ),
+        base_amount
+    )
+$$;
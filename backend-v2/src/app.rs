@@ -1,10 +1,12 @@
-use std::{env, fs, time::Duration};
+use std::{env, fs, net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Context;
-use axum::{Router, http::StatusCode};
+use axum::{Router, http::StatusCode, middleware};
 use axum_login::{
-    AuthManagerLayerBuilder, login_required,
-    tower_sessions::{Expiry, MemoryStore, SessionManagerLayer},
+    AuthManagerLayerBuilder,
+    tower_sessions::{
+        ExpiredDeletion, Expiry, MemoryStore, SessionManagerLayer, SessionStore, cookie::SameSite,
+    },
 };
 use sqlx::{Pool, Postgres, postgres::PgPoolOptions};
 use tokio::{net::TcpListener, signal};
@@ -15,19 +17,35 @@ use tower_http::{
 };
 
 use crate::{
-    app::resources::{
-        categories_shops::{Category, Shop},
-        oneoff_transactions::OneoffTransaction,
-        recurring_transactions::RecurringTransaction,
+    app::{
+        config::{Config, SessionStoreKind},
+        resources::{
+            categories_shops::{Category, Shop},
+            members::Member,
+            oneoff_transactions::OneoffTransaction,
+            recurring_transactions::{self, RecurringTransaction},
+            saved_views::SavedView,
+            settlements::Settlement,
+        },
+        session_store::PostgresStore,
     },
-    build_routes,
-    users::Backend,
+    build_routes, errors,
+    users::{self, Backend},
 };
+mod admin;
 mod api;
 mod auth;
+mod config;
+mod openapi;
 mod resources;
+mod session_store;
+mod settings;
+mod splits;
 mod transactions;
 
+/// How often expired sessions and old login attempts are removed from the database
+const MAINTENANCE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
 pub struct App {
     state: AppState,
 }
@@ -35,6 +53,7 @@ pub struct App {
 #[derive(Clone)]
 struct AppState {
     database: Pool<Postgres>,
+    config: Arc<Config>,
 }
 
 /// Read a value from an env var, or if `{name}_FILE` is set, read the file contents instead.
@@ -71,6 +90,7 @@ fn build_database_url() -> anyhow::Result<String> {
 
 impl App {
     pub async fn new() -> anyhow::Result<App> {
+        let config = Config::from_env()?;
         let database_url = build_database_url()?;
         let pool = PgPoolOptions::new()
             .max_connections(5)
@@ -79,47 +99,168 @@ impl App {
 
         sqlx::migrate!("./migrations").run(&pool).await?;
 
+        if !config.admin_usernames.is_empty() {
+            for username in users::promote_admins(&pool, &config.admin_usernames).await? {
+                log::warn!("Can't grant admin rights to unknown user '{username}'");
+            }
+        }
+
         Ok(Self {
-            state: AppState { database: pool },
+            state: AppState {
+                database: pool,
+                config: Arc::new(config),
+            },
         })
     }
 
     #[cfg(test)]
     fn from_pool(database: Pool<Postgres>) -> Self {
+        Self::from_pool_with_config(database, Config::default())
+    }
+
+    #[cfg(test)]
+    fn from_pool_with_config(database: Pool<Postgres>, config: Config) -> Self {
         Self {
-            state: AppState { database },
+            state: AppState {
+                database,
+                config: Arc::new(config),
+            },
         }
     }
 
+    /// Methods and paths of all API routes. axum can't list the routes of a router, so keep this in sync with
+    /// [`Self::router_with_store`]. The OpenAPI tests check that each of them is served and documented.
+    #[cfg(test)]
+    const ROUTES: &[(&str, &str)] = &[
+        ("get", "/api/admin/login-attempts"),
+        ("get", "/api/admin/users"),
+        ("get", "/api/admin/users/{id}"),
+        ("patch", "/api/admin/users/{id}"),
+        ("post", "/api/admin/users/{id}/password"),
+        ("delete", "/api/auth/account"),
+        ("get", "/api/auth/invites"),
+        ("post", "/api/auth/invites"),
+        ("delete", "/api/auth/invites/{id}"),
+        ("post", "/api/auth/login"),
+        ("post", "/api/auth/login/totp"),
+        ("get", "/api/auth/logout"),
+        ("get", "/api/auth/oidc/callback"),
+        ("get", "/api/auth/oidc/link"),
+        ("get", "/api/auth/oidc/login"),
+        ("post", "/api/auth/password"),
+        ("get", "/api/auth/refresh"),
+        ("post", "/api/auth/register"),
+        ("get", "/api/auth/registration"),
+        ("get", "/api/auth/tokens"),
+        ("post", "/api/auth/tokens"),
+        ("delete", "/api/auth/tokens/{id}"),
+        ("delete", "/api/auth/totp"),
+        ("post", "/api/auth/totp"),
+        ("post", "/api/auth/totp/confirm"),
+        ("get", "/api/auth/whoami"),
+        ("get", "/api/balances"),
+        ("get", "/api/balances/settlements"),
+        ("post", "/api/balances/settlements"),
+        ("delete", "/api/balances/settlements/{id}"),
+        ("get", "/api/balances/settlements/{id}"),
+        ("patch", "/api/balances/settlements/{id}"),
+        ("get", "/api/categories"),
+        ("post", "/api/categories"),
+        ("delete", "/api/categories/{id}"),
+        ("get", "/api/categories/{id}"),
+        ("patch", "/api/categories/{id}"),
+        ("get", "/api/members"),
+        ("post", "/api/members"),
+        ("delete", "/api/members/{id}"),
+        ("get", "/api/members/{id}"),
+        ("patch", "/api/members/{id}"),
+        ("get", "/api/openapi.json"),
+        ("get", "/api/settings"),
+        ("patch", "/api/settings"),
+        ("get", "/api/shops"),
+        ("post", "/api/shops"),
+        ("delete", "/api/shops/{id}"),
+        ("get", "/api/shops/{id}"),
+        ("patch", "/api/shops/{id}"),
+        ("get", "/api/transactions/oneoff"),
+        ("post", "/api/transactions/oneoff"),
+        ("delete", "/api/transactions/oneoff/{id}"),
+        ("get", "/api/transactions/oneoff/{id}"),
+        ("patch", "/api/transactions/oneoff/{id}"),
+        ("delete", "/api/transactions/oneoff/{id}/split"),
+        ("get", "/api/transactions/oneoff/{id}/split"),
+        ("put", "/api/transactions/oneoff/{id}/split"),
+        ("get", "/api/transactions/recurring"),
+        ("post", "/api/transactions/recurring"),
+        ("delete", "/api/transactions/recurring/{id}"),
+        ("get", "/api/transactions/recurring/{id}"),
+        ("patch", "/api/transactions/recurring/{id}"),
+        ("post", "/api/transactions/recurring/{id}/amounts"),
+        (
+            "delete",
+            "/api/transactions/recurring/{id}/exceptions/{month}",
+        ),
+        ("put", "/api/transactions/recurring/{id}/exceptions/{month}"),
+        ("get", "/api/transactions/recurring/{id}/occurrences"),
+        ("get", "/api/views"),
+        ("post", "/api/views"),
+        ("delete", "/api/views/{id}"),
+        ("get", "/api/views/{id}"),
+        ("patch", "/api/views/{id}"),
+    ];
+
     fn router(&self) -> Router {
-        let session_store = MemoryStore::default();
+        match self.state.config.session_store {
+            SessionStoreKind::Memory => self.router_with_store(MemoryStore::default()),
+            SessionStoreKind::Postgres => {
+                self.router_with_store(PostgresStore::new(self.state.database.clone()))
+            }
+        }
+    }
+
+    fn router_with_store<S: SessionStore + Clone>(&self, session_store: S) -> Router {
         let session_layer = SessionManagerLayer::new(session_store)
             .with_http_only(true)
             .with_secure(true)
             .with_path("/api")
-            .with_expiry(Expiry::OnInactivity(auth::SESSION_MAX_AGE));
+            // Strict would drop the cookie when the identity provider redirects back to the OpenID Connect callback
+            .with_same_site(SameSite::Lax)
+            .with_expiry(Expiry::OnInactivity(self.state.config.session_max_age));
 
-        let auth_layer =
-            AuthManagerLayerBuilder::new(Backend::new(self.state.database.clone()), session_layer)
-                .build();
+        let backend = Backend::new(self.state.database.clone())
+            .with_hash_params(self.state.config.hash_params.clone());
+        let auth_layer = AuthManagerLayerBuilder::new(backend, session_layer).build();
 
         Router::new()
             .nest(
                 "/api",
                 Router::new()
+                    .merge(openapi::router())
                     .nest("/categories", build_routes!(Category))
                     .nest("/shops", build_routes!(Shop))
-                    .nest("/transactions/oneoff", build_routes!(OneoffTransaction))
+                    .nest("/members", build_routes!(Member))
+                    .nest("/views", build_routes!(SavedView))
+                    .nest("/settings", settings::router())
+                    .nest(
+                        "/transactions/oneoff",
+                        build_routes!(OneoffTransaction).merge(splits::transaction_router()),
+                    )
                     .nest(
                         "/transactions/recurring",
-                        build_routes!(RecurringTransaction),
+                        build_routes!(RecurringTransaction).merge(recurring_transactions::router()),
+                    )
+                    .nest(
+                        "/balances",
+                        splits::balances_router().nest("/settlements", build_routes!(Settlement)),
                     ),
             )
-            .with_state(self.state.clone())
-            .route_layer(login_required!(Backend))
-            // The login_required macro above doesn't affect the auth routes, this module manually manages their routes
+            // No login_required layer here: every handler takes the `AuthUser` extractor, which accepts both sessions
+            // and API tokens and rejects unauthenticated requests
             .nest("/api/auth", auth::router())
+            .nest("/api/admin", admin::router())
+            .with_state(self.state.clone())
             .route_layer(auth_layer)
+            .layer(middleware::map_response(errors::json_error_fallback))
             .fallback_service(ServeDir::new("static").fallback(ServeFile::new("static/index.html")))
             .layer((
                 TraceLayer::new_for_http(),
@@ -134,15 +275,42 @@ impl App {
         // This listens to IPv4 and IPv6 if not v6only
         let addr = "[::]:8080";
         let listener = TcpListener::bind(addr).await?;
+
+        tokio::spawn(run_maintenance(self.state.clone()));
+
         log::info!("App listening on {addr}");
-        axum::serve(listener, self.router())
-            .with_graceful_shutdown(shutdown_signal())
-            .await?;
+        axum::serve(
+            listener,
+            self.router()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
 
         Ok(())
     }
 }
 
+/// Periodically delete data that is no longer needed
+async fn run_maintenance(state: AppState) {
+    let mut interval = tokio::time::interval(MAINTENANCE_PERIOD);
+    loop {
+        interval.tick().await;
+
+        if state.config.session_store == SessionStoreKind::Postgres
+            && let Err(err) = PostgresStore::new(state.database.clone())
+                .delete_expired()
+                .await
+        {
+            log::error!("Failed to delete expired sessions: {err}");
+        }
+
+        if let Err(err) = auth::rate_limit::delete_old_attempts(&state.database).await {
+            log::error!("Failed to delete old login attempts: {err}");
+        }
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
@@ -0,0 +1,477 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    app::{
+        AppState,
+        api::{AdminUser, ValidJson, ValidQuery, pagination::Pagination},
+    },
+    errors::ServerError,
+    users::{Backend, PasswordReset, User},
+};
+
+/// Account details of a user together with how much data they store
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOverview {
+    pub id: i32,
+    pub username: String,
+    pub is_admin: bool,
+    pub disabled: bool,
+    pub created_at: DateTime<Utc>,
+    /// Number of rows owned by the user, e.g. transactions, categories and members
+    pub row_count: i64,
+    /// Approximate size of these rows in bytes
+    pub storage_bytes: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UserUpdate {
+    #[garde(skip)]
+    pub is_admin: Option<bool>,
+    #[garde(skip)]
+    pub disabled: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginAttempt {
+    pub id: i64,
+    pub username: String,
+    pub ip_address: Option<String>,
+    pub succeeded: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserOverview {
+    /// Fetch all users, or only the user with the given id
+    pub async fn fetch(
+        database: &PgPool,
+        id: Option<i32>,
+    ) -> Result<Vec<UserOverview>, sqlx::Error> {
+        sqlx::query_as!(
+            UserOverview,
+            r#"
+            WITH owned (user_id, size) AS (
+                SELECT user_id, pg_column_size(t.*) FROM categories t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM shops t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM members t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM oneoff_transactions t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM recurring_transactions t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM settlements t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM saved_views t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM api_tokens t
+                UNION ALL SELECT user_id, pg_column_size(t.*) FROM user_settings t
+                UNION ALL
+                SELECT o.user_id, pg_column_size(s.*)
+                FROM transaction_splits s
+                JOIN oneoff_transactions o ON o.id = s.oneoff_transaction_id
+                UNION ALL
+                SELECT o.user_id, pg_column_size(s.*)
+                FROM transaction_split_shares s
+                JOIN oneoff_transactions o ON o.id = s.oneoff_transaction_id
+                UNION ALL
+                SELECT r.user_id, pg_column_size(a.*)
+                FROM recurring_transaction_amounts a
+                JOIN recurring_transactions r ON r.id = a.recurring_transaction_id
+                UNION ALL
+                SELECT r.user_id, pg_column_size(e.*)
+                FROM recurring_transaction_exceptions e
+                JOIN recurring_transactions r ON r.id = e.recurring_transaction_id
+            )
+            SELECT
+                u.id, u.username, u.is_admin, u.disabled, u.created_at,
+                COUNT(o.user_id) as "row_count!",
+                COALESCE(SUM(o.size), 0)::bigint as "storage_bytes!"
+            FROM users u
+            LEFT JOIN owned o ON o.user_id = u.id
+            WHERE $1::integer IS NULL OR u.id = $1
+            GROUP BY u.id
+            ORDER BY u.id
+            "#,
+            id
+        )
+        .fetch_all(database)
+        .await
+    }
+
+    /// Apply the update and return whether the user exists
+    pub async fn update(
+        database: &PgPool,
+        id: i32,
+        params: UserUpdate,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET is_admin = COALESCE($2, is_admin), disabled = COALESCE($3, disabled)
+            WHERE id = $1
+            "#,
+            id,
+            params.is_admin,
+            params.disabled
+        )
+        .execute(database)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+}
+
+impl LoginAttempt {
+    /// Fetch the most recent login attempts first
+    pub async fn fetch(
+        database: &PgPool,
+        pagination: Pagination,
+    ) -> Result<Vec<LoginAttempt>, sqlx::Error> {
+        sqlx::query_as!(
+            LoginAttempt,
+            r#"
+            SELECT id, username, ip_address, succeeded, created_at
+            FROM login_attempts
+            ORDER BY created_at DESC, id DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            pagination.limit.0 as i64,
+            pagination.offset.0 as i64
+        )
+        .fetch_all(database)
+        .await
+    }
+}
+
+/// All routes require a user with admin rights
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/{id}", get(get_user).patch(update_user))
+        .route("/users/{id}/password", post(reset_password))
+        .route("/login-attempts", get(list_login_attempts))
+}
+
+/// Admins can't lock themselves out by disabling their own account or revoking their own admin rights
+fn ensure_not_self(admin: &User, id: i32) -> Result<(), ServerError> {
+    if admin.id == id {
+        return Err(ServerError::Generic(
+            StatusCode::FORBIDDEN,
+            Some("Admins can't modify their own account here".to_owned()),
+        ));
+    }
+    Ok(())
+}
+
+async fn fetch_one(database: &PgPool, id: i32) -> Result<UserOverview, ServerError> {
+    UserOverview::fetch(database, Some(id))
+        .await?
+        .pop()
+        .ok_or(ServerError::Generic(StatusCode::NOT_FOUND, None))
+}
+
+async fn list_users(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+) -> Result<impl IntoResponse, ServerError> {
+    let users = UserOverview::fetch(&state.database, None).await?;
+
+    Ok(Json(json!({ "status": "success", "data": users })))
+}
+
+async fn get_user(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = fetch_one(&state.database, id as i32).await?;
+
+    Ok(Json(json!({ "status": "success", "data": user })))
+}
+
+async fn update_user(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<u32>,
+    ValidJson(params): ValidJson<UserUpdate>,
+) -> Result<impl IntoResponse, ServerError> {
+    let id = id as i32;
+    ensure_not_self(&admin, id)?;
+
+    if !UserOverview::update(&state.database, id, params).await? {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    }
+    let user = fetch_one(&state.database, id).await?;
+
+    Ok(Json(json!({ "status": "success", "data": user })))
+}
+
+/// Set a new password. All sessions of the user are invalidated, as the hash is used as `session_auth_hash`.
+async fn reset_password(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(id): Path<u32>,
+    ValidJson(params): ValidJson<PasswordReset>,
+) -> Result<impl IntoResponse, ServerError> {
+    let id = id as i32;
+    // Admins have to change their own password with the current one
+    ensure_not_self(&admin, id)?;
+
+    match Backend::new(state.database.clone())
+        .with_hash_params(state.config.hash_params.clone())
+        .set_password(id, &params.new_password)
+        .await?
+    {
+        Some(_) => Ok(Json(json!({ "status": "success" }))),
+        None => Err(ServerError::Generic(StatusCode::NOT_FOUND, None)),
+    }
+}
+
+async fn list_login_attempts(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+    ValidQuery(pagination): ValidQuery<Pagination>,
+) -> Result<impl IntoResponse, ServerError> {
+    let attempts = LoginAttempt::fetch(&state.database, pagination).await?;
+
+    Ok(Json(json!({ "status": "success", "data": attempts })))
+}
+
+#[cfg(test)]
+mod tests {
+    use argon2::Params;
+    use axum::{
+        body::Body,
+        http::{Method, Request},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        app::{
+            App,
+            auth::{
+                tests::session_cookie,
+                tokens::{ApiToken, ApiTokenCreate},
+            },
+        },
+        users::{Backend, promote_admins},
+    };
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    async fn create_token(pool: &PgPool, id: i32) -> anyhow::Result<String> {
+        let user = get_user_by_id(pool, id).await;
+        let params = ApiTokenCreate {
+            name: "admin".to_string(),
+            scopes: vec![],
+            expires_at: None,
+        };
+        Ok(ApiToken::create(pool, &user, params, &Params::default())
+            .await?
+            .1)
+    }
+
+    /// Log in the user with a session and return its cookie
+    async fn login(app: &Router, pool: &PgPool, id: i32) -> anyhow::Result<String> {
+        let user = get_user_by_id(pool, id).await;
+        Backend::new(pool.clone())
+            .set_password(id, "password")
+            .await?;
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/auth/login")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({ "username": user.username, "password": "password" }).to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        session_cookie(&response)
+    }
+
+    fn token_request(method: Method, uri: &str, token: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .expect("Failed to build request")
+    }
+
+    fn request(method: Method, uri: &str, cookie: &str, body: Option<Value>) -> Request<Body> {
+        let builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Cookie", cookie);
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .expect("Failed to build request")
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base")))]
+    async fn test_admin_routes_require_admin(pool: PgPool) -> anyhow::Result<()> {
+        let app = App::from_pool(pool.clone()).router();
+        let cookie = login(&app, &pool, 1).await?;
+
+        let response = app
+            .clone()
+            .oneshot(request(Method::GET, "/api/admin/users", &cookie, None))
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let unknown = promote_admins(&pool, &["alice_admin".into(), "nobody".into()]).await?;
+        assert_eq!(unknown, vec!["nobody".to_string()]);
+
+        let response = app
+            .oneshot(request(Method::GET, "/api/admin/users", &cookie, None))
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value =
+            serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+        assert_eq!(body["data"][0]["username"], "alice_admin");
+        assert_eq!(body["data"][0]["isAdmin"], true);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base")))]
+    async fn test_admin_routes_reject_tokens(pool: PgPool) -> anyhow::Result<()> {
+        let app = App::from_pool(pool.clone()).router();
+        promote_admins(&pool, &["alice_admin".into()]).await?;
+        let token = create_token(&pool, 1).await?;
+
+        for (method, uri) in [
+            (Method::GET, "/api/admin/users"),
+            (Method::GET, "/api/admin/login-attempts"),
+            (Method::PATCH, "/api/admin/users/2"),
+            (Method::POST, "/api/admin/users/2/password"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(token_request(method, uri, &token))
+                .await?;
+            assert!(
+                [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN].contains(&response.status()),
+                "{uri} must not accept API tokens"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base")))]
+    async fn test_storage_usage(pool: PgPool) -> anyhow::Result<()> {
+        let users = UserOverview::fetch(&pool, None).await?;
+        assert_eq!(users.len(), 2);
+        // Categories and shops of the base fixture
+        assert_eq!(users[0].row_count, 28);
+        assert_eq!(users[1].row_count, 9);
+        assert!(users[0].storage_bytes > users[1].storage_bytes);
+
+        // Tokens, settings and the rows of recurring transactions count as well
+        create_token(&pool, 2).await?;
+        sqlx::query!("INSERT INTO user_settings (user_id) VALUES (2)")
+            .execute(&pool)
+            .await?;
+        let recurring_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO recurring_transactions (frequency, interval_from, user_id, is_expense, amount, category_id)
+            SELECT 'monthly', '2024-01-01', 2, true, 1000, id FROM categories WHERE user_id = 2 LIMIT 1
+            RETURNING id
+            "#
+        )
+        .fetch_one(&pool)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO recurring_transaction_amounts (recurring_transaction_id, effective_from, amount) VALUES ($1, '2024-06-01', 1200)",
+            recurring_id
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO recurring_transaction_exceptions (recurring_transaction_id, month, skip) VALUES ($1, '2024-03-01', true)",
+            recurring_id
+        )
+        .execute(&pool)
+        .await?;
+
+        let user = UserOverview::fetch(&pool, Some(2)).await?.remove(0);
+        assert_eq!(user.row_count, 9 + 5);
+        assert!(user.storage_bytes > users[1].storage_bytes);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base")))]
+    async fn test_disable_user(pool: PgPool) -> anyhow::Result<()> {
+        let app = App::from_pool(pool.clone()).router();
+        promote_admins(&pool, &["alice_admin".into()]).await?;
+        let admin_cookie = login(&app, &pool, 1).await?;
+        let user_token = create_token(&pool, 2).await?;
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                Method::PATCH,
+                "/api/admin/users/1",
+                &admin_cookie,
+                Some(json!({ "disabled": true })),
+            ))
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                Method::PATCH,
+                "/api/admin/users/2",
+                &admin_cookie,
+                Some(json!({ "disabled": true })),
+            ))
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Tokens of disabled users stop working
+        let response = app
+            .clone()
+            .oneshot(token_request(Method::GET, "/api/categories", &user_token))
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .oneshot(request(
+                Method::PATCH,
+                "/api/admin/users/3",
+                &admin_cookie,
+                Some(json!({ "disabled": true })),
+            ))
+            .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        Ok(())
+    }
+}
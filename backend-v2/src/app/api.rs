@@ -1,20 +1,29 @@
 use axum::{
     Json,
     extract::{
-        FromRequest, FromRequestParts, OptionalFromRequest, Query, Request,
+        FromRequest, FromRequestParts, OptionalFromRequest, OriginalUri, Query, Request,
         rejection::{JsonRejection, QueryRejection},
     },
-    http::{StatusCode, request::Parts},
+    http::{StatusCode, Uri, header::AUTHORIZATION, request::Parts},
 };
 use garde::Validate;
 use serde::de::DeserializeOwned;
 
-use crate::{app::auth::AuthSession, errors::ServerError, users::User};
+use crate::{
+    app::{
+        api::pagination::{PAGINATION_FIELDS, Pagination},
+        auth::{AuthSession, tokens::ApiToken},
+    },
+    errors::ServerError,
+    users::User,
+};
 
 pub mod column_filter;
 pub mod pagination;
 pub mod tri_state_field;
 
+/// The authenticated user, either from the session or from an `Authorization: Bearer` API token.
+/// Requests outside of the scopes of the token are rejected.
 pub struct AuthUser(pub User);
 
 impl<S> FromRequestParts<S> for AuthUser
@@ -34,6 +43,37 @@ where
             }
         };
 
+        if let Some(header) = parts.headers.get(AUTHORIZATION) {
+            let token = header
+                .to_str()
+                .ok()
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .ok_or(ServerError::Generic(StatusCode::UNAUTHORIZED, None))?;
+
+            let Some((user, scopes)) =
+                ApiToken::authenticate(&session.backend.database, token.trim()).await?
+            else {
+                return Err(ServerError::Generic(StatusCode::UNAUTHORIZED, None));
+            };
+
+            // Nested routers strip their prefix from `parts.uri`, so use the original URI for the scope check
+            let path = match parts.extensions.get::<OriginalUri>() {
+                Some(OriginalUri(uri)) => uri.path(),
+                None => parts.uri.path(),
+            };
+            if !scopes
+                .iter()
+                .all(|scope| scope.permits(&parts.method, path))
+            {
+                return Err(ServerError::Generic(
+                    StatusCode::FORBIDDEN,
+                    Some("Request is outside of the scopes of this API token".to_owned()),
+                ));
+            }
+
+            return Ok(AuthUser(user));
+        }
+
         // If session.user is None, no user is logged in
         let Some(user) = session.user else {
             return Err(ServerError::Generic(StatusCode::UNAUTHORIZED, None));
@@ -43,6 +83,42 @@ where
     }
 }
 
+/// A user with admin rights, logged in with a session. Other users are rejected with 403. Unlike [`AuthUser`], API
+/// tokens aren't accepted, so a leaked token can't be used to manage users.
+pub struct AdminUser(pub User);
+
+impl<S> FromRequestParts<S> for AdminUser
+where
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(AUTHORIZATION) {
+            return Err(ServerError::Generic(
+                StatusCode::FORBIDDEN,
+                Some("API tokens can't be used for admin routes".to_owned()),
+            ));
+        }
+
+        let session = match AuthSession::from_request_parts(parts, state).await {
+            Ok(session) => session,
+            Err(err) => {
+                log::error!("{}", err.1);
+                return Err(ServerError::Generic(err.0, None));
+            }
+        };
+        let Some(user) = session.user else {
+            return Err(ServerError::Generic(StatusCode::UNAUTHORIZED, None));
+        };
+        if !user.is_admin {
+            return Err(ServerError::Generic(StatusCode::FORBIDDEN, None));
+        }
+
+        Ok(AdminUser(user))
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ValidJson<T>(pub T);
 
@@ -100,3 +176,98 @@ where
         Ok(ValidQuery(value))
     }
 }
+
+/// Query of list endpoints, split into the [`Pagination`] and the filters of the resource. The filters don't see the
+/// pagination parameters, so they can still reject unknown fields.
+#[derive(Debug, Clone, Default)]
+pub struct ListQuery<T>(pub Pagination, pub T);
+
+impl<T, S> FromRequestParts<S> for ListQuery<T>
+where
+    T: DeserializeOwned + Validate<Context = ()>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let (pagination, filters): (Vec<&str>, Vec<&str>) = parts
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .partition(|pair| {
+                let key = pair.split('=').next().unwrap_or_default();
+                PAGINATION_FIELDS.contains(&key)
+            });
+
+        let pagination: Pagination = parse_query(&pagination)?;
+        pagination.validate()?;
+        let filters: T = parse_query(&filters)?;
+        filters.validate()?;
+
+        Ok(ListQuery(pagination, filters))
+    }
+}
+
+fn parse_query<T: DeserializeOwned>(pairs: &[&str]) -> Result<T, ServerError> {
+    // The pairs come from a valid URI, so they form a valid URI again
+    let uri: Uri = format!("/?{}", pairs.join("&"))
+        .parse()
+        .map_err(|_| ServerError::Generic(StatusCode::BAD_REQUEST, None))?;
+    let Query(value) = Query::try_from_uri(&uri)?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::app::api::pagination::{Limit, Offset};
+
+    #[derive(Debug, Default, Deserialize, Validate)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    struct Filters {
+        #[garde(skip)]
+        is_expense: Option<bool>,
+    }
+
+    async fn extract(query: &str) -> Result<ListQuery<Filters>, ServerError> {
+        let (mut parts, _) = Request::builder()
+            .uri(format!("/api/transactions/oneoff?{query}"))
+            .body(())
+            .expect("Failed to build request")
+            .into_parts();
+        ListQuery::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_list_query() -> anyhow::Result<()> {
+        let ListQuery(pagination, filters) = extract("limit=2&isExpense=true&offset=4").await?;
+        assert_eq!(pagination.limit.0, 2);
+        assert_eq!(pagination.offset.0, 4);
+        assert_eq!(filters.is_expense, Some(true));
+
+        let ListQuery(pagination, filters) = extract("").await?;
+        assert_eq!(pagination.limit.0, Limit::default().0);
+        assert_eq!(pagination.offset.0, Offset::default().0);
+        assert_eq!(filters.is_expense, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_query_rejected() {
+        extract("limit=1001")
+            .await
+            .expect_err("Limit above the maximum should cause an error");
+        extract("limit=-1")
+            .await
+            .expect_err("Negative limit should cause an error");
+        extract("isExpense=true&unknown=1")
+            .await
+            .expect_err("Unknown filter should cause an error");
+    }
+}
@@ -1,14 +1,30 @@
+use std::ops::Deref;
+
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::{DateTime, NaiveDate, Utc};
 use garde::Validate;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use utoipa::{
+    IntoParams, PartialSchema, ToSchema,
+    openapi::{KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type},
+};
+
+use crate::app::resources::recurring_transactions::recurrence::RecurrenceFrequency;
+
+/// Maximum number of rows in a single page
+pub const MAX_LIMIT: i32 = 1000;
+
+/// Query parameters of [`Pagination`]
+pub const PAGINATION_FIELDS: [&str; 2] = ["limit", "offset"];
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, Validate)]
 #[serde(transparent)]
 #[garde(transparent)]
-pub struct Limit(#[garde(range(min = 0))] pub i32);
+pub struct Limit(#[garde(range(min = 0, max = MAX_LIMIT))] pub i32);
 
 impl Default for Limit {
     fn default() -> Self {
-        Limit(1000)
+        Limit(MAX_LIMIT)
     }
 }
 
@@ -17,16 +33,32 @@ impl Default for Limit {
 #[garde(transparent)]
 pub struct Offset(#[garde(range(min = 0))] pub i32);
 
-#[derive(Clone, Copy, Debug, Default, Deserialize, Validate)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Pagination {
     #[serde(default)]
     #[garde(dive)]
+    #[param(schema_with = limit_schema)]
     pub limit: Limit,
     #[serde(default)]
     #[garde(dive)]
+    #[param(value_type = i32, minimum = 0, default = 0)]
     pub offset: Offset,
 }
 
+fn limit_schema() -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::Integer)
+        .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int32)))
+        .minimum(Some(0))
+        .maximum(Some(MAX_LIMIT))
+        .default(Some(MAX_LIMIT.into()))
+        .description(Some(
+            "`0` returns no rows and is rejected together with `cursor`",
+        ))
+        .into()
+}
+
 impl Pagination {
     #[cfg(test)]
     pub fn new(limit: Limit, offset: Offset) -> Self {
@@ -34,6 +66,118 @@ impl Pagination {
     }
 }
 
+/// Value of a sort key of the last row of a page. Sort keys are never NULL, nullable columns are sorted by an
+/// `IS NULL` key followed by the `COALESCE`d column.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortValue {
+    Bool(bool),
+    Int(i32),
+    Date(NaiveDate),
+    Timestamp(DateTime<Utc>),
+    Text(String),
+    /// Compared in the declaration order of the enum instead of by name
+    Frequency(RecurrenceFrequency),
+}
+
+/// Position after the last row of a page for keyset pagination. Clients only see it as an opaque string.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct RawCursor {
+    /// The ordering the cursor was created for, cursors can't be used with other orderings
+    order: String,
+    values: Vec<SortValue>,
+    id: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor(RawCursor);
+
+impl Cursor {
+    pub fn new(order: String, values: Vec<SortValue>, id: i32) -> Self {
+        Cursor(RawCursor { order, values, id })
+    }
+
+    pub fn order(&self) -> &str {
+        &self.0.order
+    }
+
+    pub fn values(&self) -> &[SortValue] {
+        &self.0.values
+    }
+
+    pub fn id(&self) -> i32 {
+        self.0.id
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let json = serde_json::to_vec(&self.0).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&BASE64_URL_SAFE_NO_PAD.encode(json))
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        BASE64_URL_SAFE_NO_PAD
+            .decode(value)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .map(Cursor)
+            .ok_or_else(|| de::Error::custom("invalid cursor"))
+    }
+}
+
+impl PartialSchema for Cursor {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some(
+                "Opaque position in a list, only valid together with the ordering it was returned for",
+            ))
+            .into()
+    }
+}
+
+impl ToSchema for Cursor {}
+
+/// Metadata of the whole result set of a list request, returned next to `data`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageMeta {
+    /// Number of rows matching the filters, regardless of pagination
+    pub total: i64,
+    /// Sum of the amounts of all matching incomes in cents
+    pub income: i64,
+    /// Sum of the amounts of all matching expenses in cents
+    pub expenses: i64,
+    /// `income` minus `expenses`
+    pub net: i64,
+    /// Pass as `cursor` to get the next page, `null` on the last page
+    pub next_cursor: Option<Cursor>,
+}
+
+/// A page of a list together with the metadata of the whole result set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub meta: PageMeta,
+}
+
+impl<T> Deref for Page<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use garde::Validate;
@@ -55,7 +199,37 @@ mod tests {
         Pagination::new(Limit(100), Offset(-2))
             .validate()
             .expect_err("Negative offset should cause an error");
+        Pagination::new(Limit(MAX_LIMIT + 1), Offset(0))
+            .validate()
+            .expect_err("Limit above the maximum should cause an error");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cursor_round_trip() -> anyhow::Result<()> {
+        let cursor = Cursor::new(
+            "Shop:ASC".to_owned(),
+            vec![SortValue::Bool(false), SortValue::Text("Bakery".to_owned())],
+            12,
+        );
+
+        let encoded = serde_json::to_value(&cursor)?;
+        let encoded = encoded.as_str().expect("Cursor is not a string");
+        assert!(!encoded.contains("Bakery"));
+
+        let decoded: Cursor = serde_json::from_value(encoded.into())?;
+        assert_eq!(decoded, cursor);
 
         Ok(())
     }
+
+    #[test]
+    fn test_invalid_cursor() {
+        serde_json::from_str::<Cursor>(r#""not a cursor""#)
+            .expect_err("Invalid base64 should cause an error");
+        // Valid base64 of `{}`
+        serde_json::from_str::<Cursor>(r#""e30""#)
+            .expect_err("Missing cursor fields should cause an error");
+    }
 }
@@ -2,6 +2,8 @@ use garde::{Validate, rules::length::graphemes::Graphemes};
 use serde::{Deserialize, Serialize};
 
 // https://github.com/serde-rs/serde/issues/1042#issuecomment-1656337230
+/// Fields of this type need `#[serde(default)]` and are documented as `#[schema(value_type = Option<T>)]` in the
+/// OpenAPI spec, which makes them optional and nullable.
 #[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
 pub enum TriState<T> {
     /// key-value pair doesn't exist in the json/query string
@@ -52,7 +54,6 @@ mod tests {
     use axum::{extract::Query, http::Uri};
     use garde::{Validate, rules::length::graphemes::Graphemes};
     use serde::Deserialize;
-    use std::assert_matches;
 
     use crate::app::api::tri_state_field::TriState;
 
@@ -105,7 +106,7 @@ mod tests {
         assert_eq!(value, String::new());
 
         let result = parse_query("http://localhost?field=42");
-        assert_matches!(result.0.field, TriState::Defined(Some(_)));
+        assert!(matches!(result.0.field, TriState::Defined(Some(_))));
         let TriState::Defined(Some(value)) = result.0.field else {
             panic!();
         };
@@ -1,69 +1,297 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
 use axum::{
     Json, Router,
+    extract::State,
     http::StatusCode,
-    response::IntoResponse,
-    routing::{get, post},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
 };
 use axum_login::{
-    login_required,
-    tower_sessions::{Session, cookie::time::Duration},
+    AuthnBackend, login_required,
+    tower_sessions::{Expiry, Session},
 };
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use utoipa::ToSchema;
 
 use crate::{
-    app::api::ValidJson,
+    app::{
+        AppState,
+        api::ValidJson,
+        auth::rate_limit::ClientIp,
+        config::{Config, RegistrationMode},
+        settings::UserSettings,
+    },
     errors::ServerError,
-    users::{Backend, LoginCredentials, RegisterCredentials},
+    users::{
+        AccountDeletion, Backend, LoginCredentials, PasswordChange, RegisterCredentials, User,
+    },
 };
 
+pub mod invites;
+pub mod oidc;
+pub mod rate_limit;
+pub mod tokens;
+pub mod totp;
+
 pub type AuthSession = axum_login::AuthSession<Backend>;
 
-pub const SESSION_MAX_AGE: Duration = Duration::minutes(2);
+/// Session key storing the [`SessionMode`]
+const SESSION_MODE_KEY: &str = "mode";
+/// Session key storing the [`PendingLogin`] of a user that still has to provide a second factor
+const PENDING_LOGIN_KEY: &str = "pending_login";
+/// Number of invalid second factors after which the pending login is discarded
+const MAX_SECOND_FACTOR_ATTEMPTS: u32 = 5;
+
+/// Sessions created with the remember-me flag expire after a longer period of inactivity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionMode {
+    #[default]
+    Default,
+    RememberMe,
+}
+
+impl SessionMode {
+    fn expiry(self, config: &Config) -> Expiry {
+        match self {
+            SessionMode::Default => Expiry::OnInactivity(config.session_max_age),
+            SessionMode::RememberMe => Expiry::OnInactivity(config.remember_me_max_age),
+        }
+    }
+
+    async fn get(session: &Session) -> Result<Self, ServerError> {
+        Ok(session
+            .get::<SessionMode>(SESSION_MODE_KEY)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn apply(self, session: &Session, config: &Config) -> Result<(), ServerError> {
+        session.insert(SESSION_MODE_KEY, self).await?;
+        session.set_expiry(Some(self.expiry(config)));
+        Ok(())
+    }
+}
+
+/// The session of the logged in user
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    username: String,
+    /// Unix timestamp after which the session expires without activity
+    session_timeout: i64,
+    session_mode: SessionMode,
+}
+
+/// The [`SessionInfo`] together with the settings of the user
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct WhoAmI {
+    #[serde(flatten)]
+    session: SessionInfo,
+    settings: UserSettings,
+}
+
+/// Response of a login whose second factor is still missing, see `/login/totp`
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondFactorRequired {
+    second_factor_required: bool,
+}
+
+/// How new accounts can be created
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RegistrationInfo {
+    mode: RegistrationMode,
+    /// Whether single sign-on with OpenID Connect is configured
+    oidc: bool,
+}
+
+/// A login whose password was correct, but that still needs a second factor to complete
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct PendingLogin {
+    user_id: i32,
+    mode: SessionMode,
+    failed_attempts: u32,
+}
+
+/// Generate a random hex string from the given number of bytes
+fn random_hex(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buffer);
+    buffer.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// User of the session. Unlike [`AuthUser`](crate::app::api::AuthUser), API tokens aren't accepted, so a leaked token
+/// can't be used to change the credentials of the account.
+fn session_user(auth_session: &AuthSession) -> Result<&User, ServerError> {
+    auth_session
+        .user
+        .as_ref()
+        .ok_or(ServerError::Generic(StatusCode::UNAUTHORIZED, None))
+}
 
-pub fn router() -> Router {
+/// Validate that an optional expiry date lies in the future
+fn validate_expiry(value: &Option<DateTime<Utc>>, _context: &()) -> garde::Result {
+    match value {
+        Some(expires_at) if *expires_at <= Utc::now() => {
+            Err(garde::Error::new("expiry date must be in the future"))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn router() -> Router<AppState> {
     Router::new()
         .route("/logout", get(logout))
         .route("/whoami", get(whoami))
         // '/refresh' does the same as '/whoami', but is still there for backwards compatibility
         .route("/refresh", get(whoami))
+        .route("/password", post(change_password))
+        .route("/account", delete(delete_account))
+        .nest("/tokens", tokens::router())
+        .nest("/totp", totp::router())
+        .nest("/invites", invites::router())
         .route_layer(login_required!(Backend))
+        .route("/registration", get(registration_info))
         .route("/login", post(login))
+        .route("/login/totp", post(login_second_factor))
         .route("/register", post(register))
+        .nest("/oidc", oidc::router())
 }
 
-fn collect_session_info(username: &str, session: &Session) -> impl IntoResponse + use<> {
-    let timeout = session.expiry_date().to_utc().unix_timestamp();
-    Json(json!(
-        {
-            "status": "success",
-            "data": {
-                "username": username,
-                "sessionTimeout": timeout
-            }
-        }
-    ))
+fn session_info(username: &str, session: &Session, mode: SessionMode) -> SessionInfo {
+    SessionInfo {
+        username: username.to_owned(),
+        session_timeout: session.expiry_date().to_utc().unix_timestamp(),
+        session_mode: mode,
+    }
+}
+
+fn collect_session_info(
+    username: &str,
+    session: &Session,
+    mode: SessionMode,
+) -> impl IntoResponse + use<> {
+    Json(json!({
+        "status": "success",
+        "data": session_info(username, session, mode)
+    }))
 }
 
 pub async fn login(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
     mut auth_session: AuthSession,
     session: Session,
     ValidJson(credentials): ValidJson<LoginCredentials>,
-) -> Result<impl IntoResponse, ServerError> {
+) -> Result<Response, ServerError> {
+    let username = credentials.username.clone();
+    rate_limit::check(
+        &state.database,
+        &state.config,
+        &username,
+        ip_address.as_deref(),
+    )
+    .await?;
+
     auth_session.logout().await?;
     session.cycle_id().await?;
 
+    let mode = if credentials.remember_me {
+        SessionMode::RememberMe
+    } else {
+        SessionMode::Default
+    };
+
     let Some(user) = auth_session.authenticate(credentials).await? else {
+        rate_limit::record(&state.database, &username, ip_address.as_deref(), false).await?;
         return Err(StatusCode::FORBIDDEN.into());
     };
 
+    // With two-factor authentication, the session is only logged in after `login_second_factor`, which also records
+    // the attempt
+    if totp::is_enabled(&state.database, user.id).await? {
+        let pending = PendingLogin {
+            user_id: user.id,
+            mode,
+            failed_attempts: 0,
+        };
+        session.insert(PENDING_LOGIN_KEY, pending).await?;
+        let data = SecondFactorRequired {
+            second_factor_required: true,
+        };
+        return Ok(Json(json!({ "status": "success", "data": data })).into_response());
+    }
+
+    rate_limit::record(&state.database, &username, ip_address.as_deref(), true).await?;
     auth_session.login(&user).await?;
+    mode.apply(&session, &state.config).await?;
     Ok((
         StatusCode::OK,
-        collect_session_info(&user.username, &session),
-    ))
+        collect_session_info(&user.username, &session, mode),
+    )
+        .into_response())
+}
+
+pub async fn login_second_factor(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
+    mut auth_session: AuthSession,
+    session: Session,
+    ValidJson(factor): ValidJson<totp::SecondFactor>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(mut pending) = session.get::<PendingLogin>(PENDING_LOGIN_KEY).await? else {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    };
+    let Some(user) = auth_session.backend.get_user(&pending.user_id).await? else {
+        session.remove::<PendingLogin>(PENDING_LOGIN_KEY).await?;
+        return Err(StatusCode::UNAUTHORIZED.into());
+    };
+
+    rate_limit::check(
+        &state.database,
+        &state.config,
+        &user.username,
+        ip_address.as_deref(),
+    )
+    .await?;
+
+    if !totp::verify(&state.database, &user, factor).await? {
+        rate_limit::record(
+            &state.database,
+            &user.username,
+            ip_address.as_deref(),
+            false,
+        )
+        .await?;
+        pending.failed_attempts += 1;
+        if pending.failed_attempts >= MAX_SECOND_FACTOR_ATTEMPTS {
+            session.remove::<PendingLogin>(PENDING_LOGIN_KEY).await?;
+        } else {
+            session.insert(PENDING_LOGIN_KEY, pending).await?;
+        }
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    rate_limit::record(&state.database, &user.username, ip_address.as_deref(), true).await?;
+    session.remove::<PendingLogin>(PENDING_LOGIN_KEY).await?;
+    auth_session.login(&user).await?;
+    pending.mode.apply(&session, &state.config).await?;
+    Ok(collect_session_info(&user.username, &session, pending.mode))
+}
+
+pub async fn registration_info(State(state): State<AppState>) -> impl IntoResponse {
+    let info = RegistrationInfo {
+        mode: state.config.registration_mode,
+        oidc: state.config.oidc.is_some(),
+    };
+    Json(json!({ "status": "success", "data": info }))
 }
 
 pub async fn register(
+    State(state): State<AppState>,
     mut auth_session: AuthSession,
     session: Session,
     ValidJson(credentials): ValidJson<RegisterCredentials>,
@@ -71,14 +299,41 @@ pub async fn register(
     auth_session.logout().await?;
     session.cycle_id().await?;
 
-    let Some(user) = auth_session.backend.create_user(credentials).await? else {
+    let user = match state.config.registration_mode {
+        RegistrationMode::Open => auth_session.backend.create_user(credentials).await?,
+        RegistrationMode::Invite => {
+            let Some(code) = credentials.invite_code.clone() else {
+                return Err(ServerError::Generic(
+                    StatusCode::FORBIDDEN,
+                    Some("An invite code is required".to_owned()),
+                ));
+            };
+            invites::create_user_with_invite(
+                &state.database,
+                credentials,
+                &code,
+                &state.config.hash_params,
+            )
+            .await?
+        }
+        RegistrationMode::Closed => {
+            return Err(ServerError::Generic(
+                StatusCode::FORBIDDEN,
+                Some("Registration is disabled".to_owned()),
+            ));
+        }
+    };
+
+    let Some(user) = user else {
         return Err(StatusCode::FORBIDDEN.into());
     };
 
     auth_session.login(&user).await?;
+    let mode = SessionMode::Default;
+    mode.apply(&session, &state.config).await?;
     Ok((
         StatusCode::CREATED,
-        collect_session_info(&user.username, &session),
+        collect_session_info(&user.username, &session, mode),
     ))
 }
 
@@ -88,7 +343,45 @@ pub async fn logout(mut auth_session: AuthSession) -> Result<impl IntoResponse,
     Ok(Json(json!({"status": "success"})))
 }
 
+pub async fn change_password(
+    State(state): State<AppState>,
+    mut auth_session: AuthSession,
+    session: Session,
+    ValidJson(params): ValidJson<PasswordChange>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(user) = auth_session.user.clone() else {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    };
+
+    let Some(user) = auth_session.backend.change_password(&user, params).await? else {
+        return Err(StatusCode::FORBIDDEN.into());
+    };
+
+    // The new hash invalidates all sessions, so log in again to keep the current one
+    let mode = SessionMode::get(&session).await?;
+    auth_session.login(&user).await?;
+    mode.apply(&session, &state.config).await?;
+    Ok(collect_session_info(&user.username, &session, mode))
+}
+
+pub async fn delete_account(
+    mut auth_session: AuthSession,
+    ValidJson(params): ValidJson<AccountDeletion>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(user) = auth_session.user.clone() else {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    };
+
+    if !auth_session.backend.delete_user(&user, params).await? {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    auth_session.logout().await?;
+    Ok(Json(json!({"status": "success"})))
+}
+
 pub async fn whoami(
+    State(state): State<AppState>,
     auth_session: AuthSession,
     session: Session,
 ) -> Result<impl IntoResponse, ServerError> {
@@ -96,21 +389,26 @@ pub async fn whoami(
         return Err(StatusCode::UNAUTHORIZED.into());
     };
 
-    session.set_expiry(Some(axum_login::tower_sessions::Expiry::OnInactivity(
-        SESSION_MAX_AGE,
-    )));
-    Ok(collect_session_info(&user.username, &session))
+    let mode = SessionMode::get(&session).await?;
+    session.set_expiry(Some(mode.expiry(&state.config)));
+    let whoami = WhoAmI {
+        session: session_info(&user.username, &session, mode),
+        settings: UserSettings::get(&state.database, &user).await?,
+    };
+    Ok(Json(json!({ "status": "success", "data": whoami })))
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use anyhow::{Context, Ok, Result};
+    use argon2::Params;
     use axum::{
         Router,
         body::Body,
-        http::{Request, StatusCode},
+        http::{Request, Response, StatusCode},
     };
 
+    use axum_login::AuthnBackend;
     use http_body_util::BodyExt;
     use regex::Regex;
     use serde_json::{Value, json};
@@ -118,10 +416,30 @@ mod tests {
     use tower::ServiceExt;
 
     use crate::{
-        app::App,
+        app::{
+            App,
+            auth::{
+                invites::{Invite, InviteCreate},
+                tokens::{ApiToken, ApiTokenCreate, ApiTokenScope},
+                totp,
+            },
+            config::{Config, RegistrationMode},
+        },
         users::{Backend, RegisterCredentials},
     };
 
+    pub(crate) fn session_cookie(response: &Response<Body>) -> Result<String> {
+        let session_header = response
+            .headers()
+            .get("set-cookie")
+            .context("No session cookie set")?;
+        let cookie = &Regex::new(r"^(id=[^;]+)")?
+            .captures(session_header.to_str()?)
+            .context("Regex error")?[1];
+
+        Ok(String::from(cookie))
+    }
+
     async fn count_users(db: &Pool<Postgres>) -> Result<i64> {
         sqlx::query_scalar!("SELECT COUNT(*) FROM users")
             .fetch_one(db)
@@ -234,6 +552,7 @@ mod tests {
             .create_user(RegisterCredentials {
                 username: "username".into(),
                 password: "test".into(),
+                invite_code: None,
             })
             .await?;
 
@@ -314,6 +633,60 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_whoami_contains_settings(db: Pool<Postgres>) -> anyhow::Result<()> {
+        let app = App::from_pool(db).router();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/auth/register")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"username": "username1", "password": "password"}).to_string(),
+                    ))?,
+            )
+            .await?;
+        let cookie = session_cookie(&response)?;
+
+        let whoami = || async {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::get("/api/auth/whoami")
+                        .header("Cookie", &cookie)
+                        .body(Body::empty())?,
+                )
+                .await?;
+            let body = response.into_body().collect().await?.to_bytes();
+            anyhow::Ok(serde_json::from_slice::<Value>(&body)?)
+        };
+
+        let settings = &whoami().await?["data"]["settings"];
+        assert_eq!(settings["timezone"], Value::Null);
+        assert_eq!(settings["firstDayOfWeek"], "monday");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::patch("/api/settings")
+                    .header("Cookie", &cookie)
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"timezone": "Europe/Berlin", "currencyDisplay": "code"}).to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let settings = &whoami().await?["data"]["settings"];
+        assert_eq!(settings["timezone"], "Europe/Berlin");
+        assert_eq!(settings["currencyDisplay"], "code");
+        assert_eq!(settings["dateFormat"], "YYYY-MM-DD");
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn test_session_id_changes_when_login_again(db: Pool<Postgres>) -> anyhow::Result<()> {
         let backend = Backend::new(db.clone());
@@ -321,12 +694,14 @@ mod tests {
             .create_user(RegisterCredentials {
                 username: "username1".to_string(),
                 password: "password".to_string(),
+                invite_code: None,
             })
             .await?;
         backend
             .create_user(RegisterCredentials {
                 username: "username2".to_string(),
                 password: "password".to_string(),
+                invite_code: None,
             })
             .await?;
 
@@ -385,4 +760,489 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn test_api_token_authentication_and_scopes(db: Pool<Postgres>) -> anyhow::Result<()> {
+        let user = Backend::new(db.clone())
+            .create_user(RegisterCredentials {
+                username: "username".into(),
+                password: "password".into(),
+                invite_code: None,
+            })
+            .await?
+            .expect("Failed to create user");
+
+        let (_, token) = ApiToken::create(
+            &db,
+            &user,
+            ApiTokenCreate {
+                name: "script".into(),
+                scopes: vec![ApiTokenScope::ReadOnly, ApiTokenScope::TransactionsOnly],
+                expires_at: None,
+            },
+            &Params::default(),
+        )
+        .await?;
+
+        let app = App::from_pool(db.clone()).router();
+        let request = |method: &str, uri: &str, token: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Authorization", format!("Bearer {token}"))
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/api/transactions/oneoff", &token)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/api/categories", &token)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(request("POST", "/api/transactions/oneoff", &token)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "GET",
+                "/api/transactions/oneoff",
+                "kb_invalid_token",
+            )?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Tokens can't be used to manage tokens
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/api/auth/tokens", &token)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Not even unscoped tokens can enroll a second factor
+        let (_, unscoped_token) = ApiToken::create(
+            &db,
+            &user,
+            ApiTokenCreate {
+                name: "unscoped".into(),
+                scopes: vec![],
+                expires_at: None,
+            },
+            &Params::default(),
+        )
+        .await?;
+        for (uri, body) in [
+            ("/api/auth/totp", json!({"password": "password"})),
+            ("/api/auth/totp/confirm", json!({"code": "123456"})),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::post(uri)
+                        .header("Authorization", format!("Bearer {unscoped_token}"))
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_remember_me_login(db: Pool<Postgres>) -> anyhow::Result<()> {
+        Backend::new(db.clone())
+            .create_user(RegisterCredentials {
+                username: "username".into(),
+                password: "password".into(),
+                invite_code: None,
+            })
+            .await?;
+
+        let app = App::from_pool(db).router();
+
+        async fn login(app: Router, remember_me: bool) -> anyhow::Result<(String, Value)> {
+            let response = app
+                .oneshot(
+                    Request::post("/api/auth/login")
+                        .header("content-type", "application/json")
+                        .body(Body::from(
+                            json!({
+                                "username": "username",
+                                "password": "password",
+                                "rememberMe": remember_me
+                            })
+                            .to_string(),
+                        ))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let session_header = response
+                .headers()
+                .get("set-cookie")
+                .expect("No session cookie on login");
+            let cookie = Regex::new(r"^(id=[^;]+)")?
+                .captures(session_header.to_str()?)
+                .expect("Regex error")[1]
+                .to_string();
+            let body = serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+
+            Ok((cookie, body))
+        }
+
+        let now = chrono::Utc::now().timestamp();
+
+        let (_, body) = login(app.clone(), false).await?;
+        assert_eq!(body["data"]["sessionMode"], "default");
+        assert!(body["data"]["sessionTimeout"].as_i64().unwrap() < now + 60 * 60);
+
+        let (cookie, body) = login(app.clone(), true).await?;
+        assert_eq!(body["data"]["sessionMode"], "rememberMe");
+        assert!(body["data"]["sessionTimeout"].as_i64().unwrap() > now + 29 * 24 * 60 * 60);
+
+        // The mode is kept when the session is refreshed
+        let response = app
+            .oneshot(
+                Request::get("/api/auth/whoami")
+                    .header("Cookie", cookie)
+                    .body(Body::empty())?,
+            )
+            .await?;
+        let body: Value =
+            serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+        assert_eq!(body["data"]["sessionMode"], "rememberMe");
+        assert!(body["data"]["sessionTimeout"].as_i64().unwrap() > now + 29 * 24 * 60 * 60);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_change_password_invalidates_other_sessions(
+        db: Pool<Postgres>,
+    ) -> anyhow::Result<()> {
+        Backend::new(db.clone())
+            .create_user(RegisterCredentials {
+                username: "username".into(),
+                password: "password".into(),
+                invite_code: None,
+            })
+            .await?;
+
+        let app = App::from_pool(db).router();
+
+        let login = || async {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::post("/api/auth/login")
+                        .header("content-type", "application/json")
+                        .body(Body::from(
+                            json!({"username": "username", "password": "password"}).to_string(),
+                        ))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            session_cookie(&response)
+        };
+        let whoami = |cookie: String| async {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::get("/api/auth/whoami")
+                        .header("Cookie", cookie)
+                        .body(Body::empty())?,
+                )
+                .await?;
+            Ok(response.status())
+        };
+
+        let current = login().await?;
+        let other = login().await?;
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/auth/password")
+                    .header("content-type", "application/json")
+                    .header("Cookie", current.clone())
+                    .body(Body::from(
+                        json!({"currentPassword": "wrong", "newPassword": "newpassword"})
+                            .to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/auth/password")
+                    .header("content-type", "application/json")
+                    .header("Cookie", current)
+                    .body(Body::from(
+                        json!({"currentPassword": "password", "newPassword": "newpassword"})
+                            .to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let current = session_cookie(&response)?;
+
+        assert_eq!(whoami(current).await?, StatusCode::OK);
+        assert_eq!(whoami(other).await?, StatusCode::UNAUTHORIZED);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_delete_account(db: Pool<Postgres>) -> anyhow::Result<()> {
+        let app = App::from_pool(db.clone()).router();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/auth/register")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"username": "username", "password": "password"}).to_string(),
+                    ))?,
+            )
+            .await?;
+        let cookie = session_cookie(&response)?;
+
+        let delete = |password: &str| {
+            Request::delete("/api/auth/account")
+                .header("content-type", "application/json")
+                .header("Cookie", cookie.clone())
+                .body(Body::from(json!({ "password": password }).to_string()))
+        };
+
+        let response = app.clone().oneshot(delete("wrong")?).await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(count_users(&db).await?, 1);
+
+        let response = app.oneshot(delete("password")?).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(count_users(&db).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_login_with_second_factor(db: Pool<Postgres>) -> anyhow::Result<()> {
+        let user = Backend::new(db.clone())
+            .create_user(RegisterCredentials {
+                username: "username".into(),
+                password: "password".into(),
+                invite_code: None,
+            })
+            .await?
+            .expect("Failed to create user");
+
+        let enrollment = totp::enroll(&db, &user).await?;
+        let generator = totp_rs::TOTP::from_url_unchecked(&enrollment.otpauth_uri)?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        totp::confirm(&db, &user, &generator.generate(now), &Params::default())
+            .await?
+            .expect("Failed to confirm enrollment");
+
+        let app = App::from_pool(db).router();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post("/api/auth/login")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"username": "username", "password": "password"}).to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = session_cookie(&response)?;
+        let body: Value =
+            serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+        assert_eq!(body["data"]["secondFactorRequired"], true);
+
+        let whoami = || async {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::get("/api/auth/whoami")
+                        .header("Cookie", cookie.clone())
+                        .body(Body::empty())?,
+                )
+                .await?;
+            Ok(response.status())
+        };
+        let second_factor = |code: String| {
+            Request::post("/api/auth/login/totp")
+                .header("content-type", "application/json")
+                .header("Cookie", cookie.clone())
+                .body(Body::from(json!({ "code": code }).to_string()))
+        };
+
+        assert_eq!(whoami().await?, StatusCode::UNAUTHORIZED);
+
+        // The code used for the enrollment can't be used again
+        let response = app
+            .clone()
+            .oneshot(second_factor(generator.generate(now))?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app
+            .clone()
+            .oneshot(second_factor(generator.generate(now + 30))?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value =
+            serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+        assert_eq!(body["data"]["username"], "username");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_login_rate_limited(db: Pool<Postgres>) -> anyhow::Result<()> {
+        Backend::new(db.clone())
+            .create_user(RegisterCredentials {
+                username: "username".into(),
+                password: "password".into(),
+                invite_code: None,
+            })
+            .await?;
+
+        let app = App::from_pool(db).router();
+        let login = |password: &str| {
+            Request::post("/api/auth/login")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"username": "username", "password": password}).to_string(),
+                ))
+        };
+
+        for _ in 0..3 {
+            let response = app.clone().oneshot(login("wrong")?).await?;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+
+        // Even the correct password is rejected until the backoff has passed
+        let response = app.oneshot(login("password")?).await?;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_login_forwarded_for(db: Pool<Postgres>) -> anyhow::Result<()> {
+        let app = App::from_pool_with_config(
+            db.clone(),
+            Config {
+                trust_forwarded_for: true,
+                ..Default::default()
+            },
+        )
+        .router();
+
+        // The client sends a different leftmost address each time, the proxy appends the real one
+        for spoofed in ["203.0.113.1", "203.0.113.2"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::post("/api/auth/login")
+                        .header("content-type", "application/json")
+                        .header("x-forwarded-for", format!("{spoofed}, 192.0.2.7"))
+                        .body(Body::from(
+                            json!({"username": "username", "password": "wrong"}).to_string(),
+                        ))?,
+                )
+                .await?;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+
+        let addresses: Vec<Option<String>> =
+            sqlx::query_scalar!("SELECT ip_address FROM login_attempts ORDER BY id")
+                .fetch_all(&db)
+                .await?;
+        assert_eq!(
+            addresses,
+            vec![Some("192.0.2.7".to_string()), Some("192.0.2.7".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base")))]
+    async fn test_registration_modes(db: Pool<Postgres>) -> anyhow::Result<()> {
+        let register = |username: &str, invite_code: Option<&str>| {
+            Request::post("/api/auth/register")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "username": username,
+                        "password": "password",
+                        "inviteCode": invite_code
+                    })
+                    .to_string(),
+                ))
+        };
+
+        let closed = App::from_pool_with_config(
+            db.clone(),
+            Config {
+                registration_mode: RegistrationMode::Closed,
+                ..Default::default()
+            },
+        )
+        .router();
+        let response = closed.oneshot(register("carol", None)?).await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let invite_only = App::from_pool_with_config(
+            db.clone(),
+            Config {
+                registration_mode: RegistrationMode::Invite,
+                ..Default::default()
+            },
+        )
+        .router();
+        let response = invite_only
+            .clone()
+            .oneshot(register("carol", None)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let inviter = Backend::new(db.clone())
+            .get_user(&1)
+            .await?
+            .expect("User not found");
+        let invite = Invite::create(&db, &inviter, InviteCreate::default()).await?;
+        let response = invite_only
+            .clone()
+            .oneshot(register("carol", Some(&invite.code))?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = invite_only
+            .oneshot(register("dave", Some(&invite.code))?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(count_users(&db).await?, 3);
+
+        Ok(())
+    }
 }
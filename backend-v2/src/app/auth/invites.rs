@@ -0,0 +1,266 @@
+use argon2::Params;
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use chrono::{DateTime, Utc};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    app::{
+        AppState,
+        api::{AdminUser, ValidJson},
+        auth::{random_hex, validate_expiry},
+    },
+    errors::ServerError,
+    users::{RegisterCredentials, User, insert_user},
+};
+
+const CODE_BYTES: usize = 12;
+
+/// A single-use code that allows registering while registration is invite-only
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Invite {
+    pub id: i32,
+    pub code: String,
+    /// Username of the admin that created this invite
+    pub created_by: String,
+    /// Username of the user that registered with this invite
+    pub used_by: Option<String>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InviteCreate {
+    #[garde(custom(validate_expiry))]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Invite {
+    pub async fn create(
+        database: &PgPool,
+        user: &User,
+        params: InviteCreate,
+    ) -> Result<Invite, sqlx::Error> {
+        sqlx::query_as!(
+            Invite,
+            r#"
+            INSERT INTO invites (code, created_by, expires_at) VALUES ($1, $2, $3)
+            RETURNING id, code, $4::varchar as "created_by!", NULL::varchar as "used_by?", used_at, expires_at,
+                created_at
+            "#,
+            random_hex(CODE_BYTES),
+            user.id,
+            params.expires_at,
+            user.username
+        )
+        .fetch_one(database)
+        .await
+    }
+
+    /// Invites of all admins, so that each of them can manage the invites of the others
+    pub async fn fetch(database: &PgPool) -> Result<Vec<Invite>, sqlx::Error> {
+        sqlx::query_as!(
+            Invite,
+            r#"
+            SELECT i.id, i.code, c.username as created_by, u.username as "used_by?", i.used_at, i.expires_at,
+                i.created_at
+            FROM invites i
+            INNER JOIN users c ON i.created_by = c.id
+            LEFT JOIN users u ON i.used_by = u.id
+            ORDER BY i.created_at, i.id
+            "#
+        )
+        .fetch_all(database)
+        .await
+    }
+
+    /// Revoke an unused invite, no matter which admin created it
+    pub async fn remove(database: &PgPool, id: i32) -> Result<u64, sqlx::Error> {
+        sqlx::query!("DELETE FROM invites WHERE id = $1 AND used_at IS NULL", id)
+            .execute(database)
+            .await
+            .map(|result| result.rows_affected())
+    }
+}
+
+/// Create a new user and use up the invite in one transaction. Return None if the username is already taken, in which
+/// case the invite stays valid.
+pub async fn create_user_with_invite(
+    database: &PgPool,
+    credentials: RegisterCredentials,
+    code: &str,
+    hash_params: &Params,
+) -> Result<Option<User>, ServerError> {
+    let mut transaction = database.begin().await?;
+
+    let invite_id = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM invites
+        WHERE code = $1 AND used_at IS NULL AND (expires_at IS NULL OR expires_at > now())
+        FOR UPDATE
+        "#,
+        code
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let Some(invite_id) = invite_id else {
+        return Err(ServerError::Generic(
+            StatusCode::FORBIDDEN,
+            Some("Invalid invite code".to_owned()),
+        ));
+    };
+
+    let Some(user) = insert_user(&mut *transaction, credentials, hash_params).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        "UPDATE invites SET used_by = $2, used_at = now() WHERE id = $1",
+        invite_id,
+        user.id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(Some(user))
+}
+
+/// Only admins can invite new users
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_invites).post(create_invite))
+        .route("/{id}", delete(revoke_invite))
+}
+
+async fn create_invite(
+    State(state): State<AppState>,
+    AdminUser(user): AdminUser,
+    params: Option<ValidJson<InviteCreate>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = params.map(|ValidJson(params)| params).unwrap_or_default();
+    let invite = Invite::create(&state.database, &user, params).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "status": "success", "data": invite })),
+    ))
+}
+
+async fn list_invites(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+) -> Result<impl IntoResponse, ServerError> {
+    let invites = Invite::fetch(&state.database).await?;
+
+    Ok(Json(json!({ "status": "success", "data": invites })))
+}
+
+async fn revoke_invite(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, ServerError> {
+    match Invite::remove(&state.database, id as i32).await? {
+        0 => Err(ServerError::Generic(StatusCode::NOT_FOUND, None)),
+        _ => Ok(Json(json!({ "status": "success" }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    fn credentials(username: &str) -> RegisterCredentials {
+        RegisterCredentials {
+            username: username.to_string(),
+            password: "password".to_string(),
+            invite_code: None,
+        }
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_invite_single_use(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+        let invite = Invite::create(&pool, &user, InviteCreate::default()).await?;
+
+        // A taken username doesn't use up the invite
+        let result = create_user_with_invite(
+            &pool,
+            credentials("alice_admin"),
+            &invite.code,
+            &Params::default(),
+        )
+        .await?;
+        assert!(result.is_none());
+
+        let new_user = create_user_with_invite(
+            &pool,
+            credentials("carol"),
+            &invite.code,
+            &Params::default(),
+        )
+        .await?
+        .expect("Failed to create user");
+        assert_eq!(new_user.username, "carol");
+
+        create_user_with_invite(&pool, credentials("dave"), &invite.code, &Params::default())
+            .await
+            .expect_err("Invite was used twice");
+
+        // Invites of other admins are listed as well
+        let other_admin = get_user_by_id(&pool, 2).await;
+        Invite::create(&pool, &other_admin, InviteCreate::default()).await?;
+
+        let invites = Invite::fetch(&pool).await?;
+        assert_eq!(invites.len(), 2);
+        assert_eq!(invites[1].created_by, other_admin.username);
+        assert_eq!(invites[0].created_by, user.username);
+        assert_eq!(invites[0].used_by.as_deref(), Some("carol"));
+
+        // Used invites can't be revoked
+        assert_eq!(Invite::remove(&pool, invite.id).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_revoked_invite_rejected(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+        let invite = Invite::create(&pool, &user, InviteCreate::default()).await?;
+
+        assert_eq!(Invite::remove(&pool, invite.id).await?, 1);
+        assert_eq!(Invite::remove(&pool, invite.id).await?, 0);
+
+        create_user_with_invite(
+            &pool,
+            credentials("carol"),
+            &invite.code,
+            &Params::default(),
+        )
+        .await
+        .expect_err("Revoked invite was accepted");
+
+        Ok(())
+    }
+}
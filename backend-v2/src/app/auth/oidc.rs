@@ -0,0 +1,725 @@
+use argon2::Params;
+use axum::{
+    Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::Redirect,
+    routing::get,
+};
+use axum_login::tower_sessions::Session;
+use garde::Validate;
+use openidconnect::{
+    AuthorizationCode, CsrfToken, EndpointMaybeSet, EndpointNotSet, EndpointSet, Nonce,
+    PkceCodeChallenge, PkceCodeVerifier, Scope, TokenResponse,
+    core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata},
+    reqwest,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::IntoParams;
+
+use crate::{
+    app::{
+        AppState,
+        api::ValidQuery,
+        auth::{
+            AuthSession, PENDING_LOGIN_KEY, PendingLogin, SessionMode, random_hex,
+            rate_limit::{self, ClientIp},
+            totp,
+        },
+        config::{OidcConfig, RegistrationMode},
+    },
+    errors::ServerError,
+    users::{RegisterCredentials, User, insert_user},
+};
+
+/// Session key storing the [`PendingOidcLogin`] while the user is at the identity provider
+const OIDC_LOGIN_KEY: &str = "oidc_login";
+/// Where the browser is sent after a successful login
+const LOGIN_REDIRECT: &str = "/";
+/// Where the browser is sent if the login has to be completed with `/login/totp`
+const SECOND_FACTOR_REDIRECT: &str = "/?secondFactorRequired=true";
+
+type DiscoveredClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// State of an authorization request that is checked in the callback
+#[derive(Debug, Deserialize, Serialize)]
+struct PendingOidcLogin {
+    csrf_token: CsrfToken,
+    nonce: Nonce,
+    pkce_verifier: PkceCodeVerifier,
+    mode: SessionMode,
+    /// Link the identity to this user instead of logging in
+    link_user_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Validate, IntoParams)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct OidcLoginParams {
+    #[serde(default)]
+    #[garde(skip)]
+    pub remember_me: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// An account at the identity provider, identified by issuer and subject
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    pub issuer: String,
+    pub subject: String,
+    /// Username used for auto-provisioned accounts
+    pub preferred_username: Option<String>,
+}
+
+/// Return the user linked to the identity, including disabled users
+pub async fn find_user(
+    database: &PgPool,
+    identity: &Identity,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT u.* FROM users u
+        JOIN user_identities i ON i.user_id = u.id
+        WHERE i.issuer = $1 AND i.subject = $2
+        "#,
+        identity.issuer,
+        identity.subject
+    )
+    .fetch_optional(database)
+    .await
+}
+
+/// Link the identity to a user. Return false if it is already linked to another user.
+pub async fn link_identity(
+    database: &PgPool,
+    user: &User,
+    identity: &Identity,
+) -> Result<bool, sqlx::Error> {
+    let linked_user_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO user_identities (user_id, issuer, subject) VALUES ($1, $2, $3)
+        ON CONFLICT (issuer, subject) DO UPDATE SET issuer = EXCLUDED.issuer
+        RETURNING user_id
+        "#,
+        user.id,
+        identity.issuer,
+        identity.subject
+    )
+    .fetch_one(database)
+    .await?;
+
+    Ok(linked_user_id == user.id)
+}
+
+/// Create a user for the identity. The account gets a random password, so it can only log in through the provider
+/// until the password is reset. Return None if the username is already taken.
+pub async fn provision_user(
+    database: &PgPool,
+    identity: &Identity,
+    hash_params: &Params,
+) -> Result<Option<User>, ServerError> {
+    let credentials = RegisterCredentials {
+        username: identity
+            .preferred_username
+            .clone()
+            .unwrap_or_else(|| identity.subject.clone()),
+        password: random_hex(32),
+        invite_code: None,
+    };
+
+    let mut transaction = database.begin().await?;
+    let Some(user) = insert_user(&mut *transaction, credentials, hash_params).await? else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        "INSERT INTO user_identities (user_id, issuer, subject) VALUES ($1, $2, $3)",
+        user.id,
+        identity.issuer,
+        identity.subject
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(Some(user))
+}
+
+/// `/login` and `/callback` are public, `/link` requires a session
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/login", get(login))
+        .route("/link", get(link))
+        .route("/callback", get(callback))
+}
+
+fn oidc_config(state: &AppState) -> Result<&OidcConfig, ServerError> {
+    state.config.oidc.as_ref().ok_or(ServerError::Generic(
+        StatusCode::NOT_FOUND,
+        Some("OpenID Connect is not configured".to_owned()),
+    ))
+}
+
+fn provider_error(err: impl std::fmt::Display) -> ServerError {
+    log::error!("OpenID Connect provider error: {err}");
+    ServerError::Generic(StatusCode::BAD_GATEWAY, None)
+}
+
+fn http_client() -> Result<reqwest::Client, ServerError> {
+    reqwest::ClientBuilder::new()
+        // Following redirects opens the client up to SSRF vulnerabilities
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(provider_error)
+}
+
+/// Fetch the provider metadata. This is done per request, so changes of the provider keys are picked up.
+async fn discover(
+    config: &OidcConfig,
+    http_client: &reqwest::Client,
+) -> Result<DiscoveredClient, ServerError> {
+    let metadata = CoreProviderMetadata::discover_async(config.issuer_url.clone(), http_client)
+        .await
+        .map_err(provider_error)?;
+
+    Ok(CoreClient::from_provider_metadata(
+        metadata,
+        config.client_id.clone(),
+        config.client_secret.clone(),
+    )
+    .set_redirect_uri(config.redirect_url.clone()))
+}
+
+/// Store the state of a new authorization request in the session and redirect to the provider
+async fn start(
+    state: &AppState,
+    session: &Session,
+    mode: SessionMode,
+    link_user_id: Option<i32>,
+) -> Result<Redirect, ServerError> {
+    let config = oidc_config(state)?;
+    let client = discover(config, &http_client()?).await?;
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (url, csrf_token, nonce) = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .add_scope(Scope::new("profile".to_owned()))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    let pending = PendingOidcLogin {
+        csrf_token,
+        nonce,
+        pkce_verifier,
+        mode,
+        link_user_id,
+    };
+    session.insert(OIDC_LOGIN_KEY, pending).await?;
+
+    Ok(Redirect::to(url.as_str()))
+}
+
+async fn login(
+    State(state): State<AppState>,
+    session: Session,
+    ValidQuery(params): ValidQuery<OidcLoginParams>,
+) -> Result<Redirect, ServerError> {
+    let mode = if params.remember_me {
+        SessionMode::RememberMe
+    } else {
+        SessionMode::Default
+    };
+
+    start(&state, &session, mode, None).await
+}
+
+/// Link an identity to the logged in user, so they can log in through the provider afterwards
+async fn link(
+    State(state): State<AppState>,
+    auth_session: AuthSession,
+    session: Session,
+) -> Result<Redirect, ServerError> {
+    let Some(user) = auth_session.user else {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    };
+
+    let mode = SessionMode::get(&session).await?;
+    start(&state, &session, mode, Some(user.id)).await
+}
+
+/// Exchange the authorization code and verify the ID token
+async fn verify_callback(
+    config: &OidcConfig,
+    pending: PendingOidcLogin,
+    params: CallbackParams,
+) -> Result<Identity, ServerError> {
+    if let Some(error) = params.error {
+        return Err(ServerError::Generic(
+            StatusCode::FORBIDDEN,
+            Some(format!("Identity provider returned error '{error}'")),
+        ));
+    }
+
+    let (Some(code), Some(csrf_state)) = (params.code, params.state) else {
+        return Err(StatusCode::BAD_REQUEST.into());
+    };
+    if csrf_state != *pending.csrf_token.secret() {
+        return Err(ServerError::Generic(
+            StatusCode::FORBIDDEN,
+            Some("Invalid state".to_owned()),
+        ));
+    }
+
+    let http_client = http_client()?;
+    let client = discover(config, &http_client).await?;
+    let response = client
+        .exchange_code(AuthorizationCode::new(code))
+        .map_err(provider_error)?
+        .set_pkce_verifier(pending.pkce_verifier)
+        .request_async(&http_client)
+        .await
+        .map_err(provider_error)?;
+
+    let id_token = response
+        .id_token()
+        .ok_or_else(|| provider_error("no ID token in token response"))?;
+    let claims = id_token
+        .claims(&client.id_token_verifier(), &pending.nonce)
+        .map_err(|err| {
+            log::warn!("Rejected ID token: {err}");
+            ServerError::Generic(StatusCode::FORBIDDEN, Some("Invalid ID token".to_owned()))
+        })?;
+
+    Ok(Identity {
+        issuer: claims.issuer().to_string(),
+        subject: claims.subject().to_string(),
+        preferred_username: claims
+            .preferred_username()
+            .map(|username| username.to_string()),
+    })
+}
+
+async fn callback(
+    State(state): State<AppState>,
+    ClientIp(ip_address): ClientIp,
+    mut auth_session: AuthSession,
+    session: Session,
+    Query(params): Query<CallbackParams>,
+) -> Result<Redirect, ServerError> {
+    let config = oidc_config(&state)?;
+    let Some(pending) = session.remove::<PendingOidcLogin>(OIDC_LOGIN_KEY).await? else {
+        return Err(StatusCode::UNAUTHORIZED.into());
+    };
+    let mode = pending.mode;
+    let link_user_id = pending.link_user_id;
+
+    let identity = verify_callback(config, pending, params).await?;
+
+    let user = match link_user_id {
+        Some(user_id) => {
+            // The user has to be logged in during the whole flow
+            let Some(user) = auth_session.user.clone().filter(|user| user.id == user_id) else {
+                return Err(StatusCode::UNAUTHORIZED.into());
+            };
+            if !link_identity(&state.database, &user, &identity).await? {
+                return Err(ServerError::Generic(
+                    StatusCode::CONFLICT,
+                    Some("This identity is linked to another account".to_owned()),
+                ));
+            }
+            user
+        }
+        None => match find_user(&state.database, &identity).await? {
+            Some(user) => user,
+            // Provisioning registers a new user, so it's only allowed while registration is open
+            None if config.auto_provision
+                && state.config.registration_mode == RegistrationMode::Open => provision_user(
+                &state.database,
+                &identity,
+                &state.config.hash_params,
+            )
+            .await?
+            .ok_or(ServerError::Generic(
+                StatusCode::CONFLICT,
+                Some(
+                    "Username is already taken, log in with your password and link the account"
+                        .to_owned(),
+                ),
+            ))?,
+            None => {
+                return Err(ServerError::Generic(
+                    StatusCode::FORBIDDEN,
+                    Some("No account is linked to this identity".to_owned()),
+                ));
+            }
+        },
+    };
+
+    if user.disabled {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    // Like with the password, a login with two-factor authentication is only completed by `login_second_factor`,
+    // which also records the attempt. Linking doesn't log in, the user already is.
+    if link_user_id.is_none() {
+        if totp::is_enabled(&state.database, user.id).await? {
+            auth_session.logout().await?;
+            session.cycle_id().await?;
+            let pending = PendingLogin {
+                user_id: user.id,
+                mode,
+                failed_attempts: 0,
+            };
+            session.insert(PENDING_LOGIN_KEY, pending).await?;
+            return Ok(Redirect::to(SECOND_FACTOR_REDIRECT));
+        }
+        rate_limit::record(&state.database, &user.username, ip_address.as_deref(), true).await?;
+    }
+
+    auth_session.login(&user).await?;
+    mode.apply(&session, &state.config).await?;
+
+    Ok(Redirect::to(LOGIN_REDIRECT))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Json,
+        body::Body,
+        http::{Request, header::LOCATION},
+        routing::post,
+    };
+    use chrono::{Duration, Utc};
+    use http_body_util::BodyExt;
+    use openidconnect::{
+        AccessToken, Audience, ClientId, EmptyAdditionalClaims, EmptyExtraTokenFields,
+        EndUserUsername, IssuerUrl, JsonWebKeyId, PrivateSigningKey, RedirectUrl, StandardClaims,
+        SubjectIdentifier,
+        core::{
+            CoreIdToken, CoreIdTokenClaims, CoreIdTokenFields, CoreJsonWebKeySet,
+            CoreJwsSigningAlgorithm, CoreRsaPrivateSigningKey, CoreTokenResponse, CoreTokenType,
+        },
+        url::Url,
+    };
+    use serde_json::{Value, json};
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::app::{App, auth::tests::session_cookie, config::Config};
+
+    const CLIENT_ID: &str = "kapitalbestand";
+    const SIGNING_KEY: &str = include_str!("oidc_test_key.pem");
+
+    /// Minimal identity provider that issues an ID token for `subject` with the nonce of the last authorization URL
+    #[derive(Clone, Default)]
+    struct MockProvider {
+        issuer: String,
+        nonce: Arc<Mutex<Option<String>>>,
+    }
+
+    fn signing_key() -> CoreRsaPrivateSigningKey {
+        CoreRsaPrivateSigningKey::from_pem(SIGNING_KEY, Some(JsonWebKeyId::new("key".to_owned())))
+            .expect("Invalid test key")
+    }
+
+    async fn start_provider() -> anyhow::Result<MockProvider> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let provider = MockProvider {
+            issuer: format!("http://{}", listener.local_addr()?),
+            ..Default::default()
+        };
+
+        let router = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(|State(provider): State<MockProvider>| async move {
+                    Json(json!({
+                        "issuer": provider.issuer,
+                        "authorization_endpoint": format!("{}/authorize", provider.issuer),
+                        "token_endpoint": format!("{}/token", provider.issuer),
+                        "jwks_uri": format!("{}/jwks", provider.issuer),
+                        "response_types_supported": ["code"],
+                        "subject_types_supported": ["public"],
+                        "id_token_signing_alg_values_supported": ["RS256"]
+                    }))
+                }),
+            )
+            .route(
+                "/jwks",
+                get(|| async {
+                    Json(CoreJsonWebKeySet::new(vec![
+                        signing_key().as_verification_key(),
+                    ]))
+                }),
+            )
+            .route(
+                "/token",
+                post(|State(provider): State<MockProvider>| async move {
+                    let nonce = provider.nonce.lock().unwrap().clone().map(Nonce::new);
+                    let claims = CoreIdTokenClaims::new(
+                        IssuerUrl::new(provider.issuer).unwrap(),
+                        vec![Audience::new(CLIENT_ID.to_owned())],
+                        Utc::now() + Duration::minutes(5),
+                        Utc::now(),
+                        StandardClaims::new(SubjectIdentifier::new("subject-1".to_owned()))
+                            .set_preferred_username(Some(EndUserUsername::new("carol".to_owned()))),
+                        EmptyAdditionalClaims {},
+                    )
+                    .set_nonce(nonce);
+                    let id_token = CoreIdToken::new(
+                        claims,
+                        &signing_key(),
+                        CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256,
+                        None,
+                        None,
+                    )
+                    .unwrap();
+                    Json(CoreTokenResponse::new(
+                        AccessToken::new("access".to_owned()),
+                        CoreTokenType::Bearer,
+                        CoreIdTokenFields::new(Some(id_token), EmptyExtraTokenFields {}),
+                    ))
+                }),
+            )
+            .with_state(provider.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Ok(provider)
+    }
+
+    fn config(provider: &MockProvider, auto_provision: bool) -> Config {
+        Config {
+            oidc: Some(OidcConfig {
+                issuer_url: IssuerUrl::new(provider.issuer.clone()).unwrap(),
+                client_id: ClientId::new(CLIENT_ID.to_owned()),
+                client_secret: None,
+                redirect_url: RedirectUrl::new(
+                    "http://localhost/api/auth/oidc/callback".to_owned(),
+                )
+                .unwrap(),
+                auto_provision,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Start a login and return the session cookie and the `state` parameter of the authorization URL
+    async fn start_login(app: Router, provider: &MockProvider) -> anyhow::Result<(String, String)> {
+        let response = app
+            .oneshot(Request::get("/api/auth/oidc/login").body(Body::empty())?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+
+        let location = Url::parse(response.headers()[LOCATION].to_str()?)?;
+        let param = |name: &str| {
+            location
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        assert!(location.as_str().starts_with(&provider.issuer));
+        *provider.nonce.lock().unwrap() = param("nonce");
+
+        Ok((
+            session_cookie(&response)?,
+            param("state").expect("No state"),
+        ))
+    }
+
+    fn callback_request(cookie: &str, state: &str) -> anyhow::Result<Request<Body>> {
+        Ok(
+            Request::get(format!("/api/auth/oidc/callback?code=code&state={state}"))
+                .header("Cookie", cookie)
+                .body(Body::empty())?,
+        )
+    }
+
+    #[sqlx::test]
+    async fn test_login_provisions_user(pool: PgPool) -> anyhow::Result<()> {
+        let provider = start_provider().await?;
+        let app = App::from_pool_with_config(pool.clone(), config(&provider, true)).router();
+
+        let (cookie, state) = start_login(app.clone(), &provider).await?;
+        let response = app
+            .clone()
+            .oneshot(callback_request(&cookie, "wrong")?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // The pending login is used up by the failed attempt
+        let response = app
+            .clone()
+            .oneshot(callback_request(&cookie, &state)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let (cookie, state) = start_login(app.clone(), &provider).await?;
+        let response = app
+            .clone()
+            .oneshot(callback_request(&cookie, &state)?)
+            .await?;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let cookie = session_cookie(&response)?;
+
+        let response = app
+            .oneshot(
+                Request::get("/api/auth/whoami")
+                    .header("Cookie", cookie)
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value =
+            serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+        assert_eq!(body["data"]["username"], "carol");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_login_with_second_factor(pool: PgPool) -> anyhow::Result<()> {
+        let provider = start_provider().await?;
+        let app = App::from_pool_with_config(pool.clone(), config(&provider, true)).router();
+
+        let (cookie, state) = start_login(app.clone(), &provider).await?;
+        let response = app
+            .clone()
+            .oneshot(callback_request(&cookie, &state)?)
+            .await?;
+        assert_eq!(response.headers()[LOCATION], LOGIN_REDIRECT);
+
+        let user = sqlx::query_as!(User, "SELECT * FROM users WHERE username = 'carol'")
+            .fetch_one(&pool)
+            .await?;
+        let enrollment = totp::enroll(&pool, &user).await?;
+        let generator = totp_rs::TOTP::from_url_unchecked(&enrollment.otpauth_uri)?;
+        let now = Utc::now().timestamp() as u64;
+        totp::confirm(&pool, &user, &generator.generate(now), &Params::default())
+            .await?
+            .expect("Failed to confirm enrollment");
+
+        let (cookie, state) = start_login(app.clone(), &provider).await?;
+        let response = app
+            .clone()
+            .oneshot(callback_request(&cookie, &state)?)
+            .await?;
+        assert_eq!(response.headers()[LOCATION], SECOND_FACTOR_REDIRECT);
+        let cookie = session_cookie(&response)?;
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/auth/whoami")
+                    .header("Cookie", &cookie)
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .oneshot(
+                Request::post("/api/auth/login/totp")
+                    .header("Cookie", &cookie)
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({ "code": generator.generate(now + 30) }).to_string(),
+                    ))?,
+            )
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Both logins are recorded
+        let attempts = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM login_attempts WHERE username = 'carol' AND succeeded"#
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(attempts, 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_login_without_linked_account(pool: PgPool) -> anyhow::Result<()> {
+        let provider = start_provider().await?;
+        let app = App::from_pool_with_config(pool.clone(), config(&provider, false)).router();
+
+        let (cookie, state) = start_login(app.clone(), &provider).await?;
+        let response = app.oneshot(callback_request(&cookie, &state)?).await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Auto-provisioning doesn't bypass invite-only or closed registration
+        for registration_mode in [RegistrationMode::Invite, RegistrationMode::Closed] {
+            let config = Config {
+                registration_mode,
+                ..config(&provider, true)
+            };
+            let app = App::from_pool_with_config(pool.clone(), config).router();
+
+            let (cookie, state) = start_login(app.clone(), &provider).await?;
+            let response = app.oneshot(callback_request(&cookie, &state)?).await?;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let users = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM users"#)
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(users, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_link_identity(pool: PgPool) -> anyhow::Result<()> {
+        let identity = Identity {
+            issuer: "https://id.example.com".to_owned(),
+            subject: "subject-1".to_owned(),
+            preferred_username: Some("bob_user".to_owned()),
+        };
+        let get_user = |id: i32| {
+            sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id).fetch_one(&pool)
+        };
+        let alice = get_user(1).await?;
+        let bob = get_user(2).await?;
+
+        // Auto-provisioning never takes over existing accounts
+        assert!(
+            provision_user(&pool, &identity, &Params::default())
+                .await?
+                .is_none()
+        );
+        assert!(find_user(&pool, &identity).await?.is_none());
+
+        assert!(link_identity(&pool, &alice, &identity).await?);
+        assert!(link_identity(&pool, &alice, &identity).await?);
+        assert!(!link_identity(&pool, &bob, &identity).await?);
+        assert_eq!(
+            find_user(&pool, &identity).await?.map(|user| user.id),
+            Some(1)
+        );
+
+        Ok(())
+    }
+}
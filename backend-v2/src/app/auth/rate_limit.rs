@@ -0,0 +1,235 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    app::{AppState, config::Config},
+    errors::ServerError,
+};
+
+/// Failed attempts that are allowed before the exponential backoff starts
+const FREE_ATTEMPTS: u32 = 3;
+/// Attempts are kept for this long so administrators can review them
+const ATTEMPT_RETENTION: Duration = Duration::days(30);
+
+/// Address of the client, if known
+pub struct ClientIp(pub Option<String>);
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if state.config.trust_forwarded_for
+            && let Some(forwarded_for) = parts
+                .headers
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                // The client controls the leftmost entries, the proxy appends the address it sees on the right
+                .and_then(|value| value.rsplit(',').next())
+        {
+            return Ok(ClientIp(Some(forwarded_for.trim().to_owned())));
+        }
+
+        Ok(ClientIp(
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string()),
+        ))
+    }
+}
+
+/// Failed attempts within the lockout window
+#[derive(Debug, Default)]
+struct Failures {
+    count: i64,
+    last: Option<DateTime<Utc>>,
+}
+
+/// Return the number of seconds until the next attempt is allowed, if the client has to wait
+fn retry_after(failures: &Failures, config: &Config, now: DateTime<Utc>) -> Option<u64> {
+    let last = failures.last?;
+    let count = u32::try_from(failures.count).unwrap_or(u32::MAX);
+    let lockout = config.login_lockout_duration.whole_seconds();
+
+    let wait = if count >= config.login_max_failures {
+        lockout
+    } else if count >= FREE_ATTEMPTS {
+        // 1, 2, 4, ... seconds, but never longer than a lockout
+        2i64.saturating_pow(count - FREE_ATTEMPTS).min(lockout)
+    } else {
+        return None;
+    };
+
+    let remaining = wait - (now - last).num_seconds();
+    (remaining > 0).then_some(remaining as u64)
+}
+
+async fn username_failures(
+    database: &PgPool,
+    username: &str,
+    since: DateTime<Utc>,
+) -> Result<Failures, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!", MAX(created_at) as last
+        FROM login_attempts
+        WHERE username = $1 AND NOT succeeded AND created_at > $2 AND created_at > COALESCE(
+            (SELECT MAX(created_at) FROM login_attempts WHERE username = $1 AND succeeded),
+            '-infinity'
+        )
+        "#,
+        username,
+        since
+    )
+    .fetch_one(database)
+    .await?;
+
+    Ok(Failures {
+        count: row.count,
+        last: row.last,
+    })
+}
+
+/// Unlike [`username_failures`], successful logins don't reset the count, otherwise an attacker could log into their
+/// own account in between to keep guessing the passwords of others
+async fn ip_address_failures(
+    database: &PgPool,
+    ip_address: &str,
+    since: DateTime<Utc>,
+) -> Result<Failures, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!", MAX(created_at) as last
+        FROM login_attempts
+        WHERE ip_address = $1 AND NOT succeeded AND created_at > $2
+        "#,
+        ip_address,
+        since
+    )
+    .fetch_one(database)
+    .await?;
+
+    Ok(Failures {
+        count: row.count,
+        last: row.last,
+    })
+}
+
+/// Reject the attempt with `ServerError::TooManyRequests` if the username or the address have to wait
+pub async fn check(
+    database: &PgPool,
+    config: &Config,
+    username: &str,
+    ip_address: Option<&str>,
+) -> Result<(), ServerError> {
+    let now = Utc::now();
+    let since = now - Duration::seconds(config.login_lockout_duration.whole_seconds());
+
+    let mut failures = vec![username_failures(database, username, since).await?];
+    if let Some(ip_address) = ip_address {
+        failures.push(ip_address_failures(database, ip_address, since).await?);
+    }
+
+    match failures
+        .iter()
+        .filter_map(|failures| retry_after(failures, config, now))
+        .max()
+    {
+        Some(seconds) => Err(ServerError::TooManyRequests(seconds)),
+        None => Ok(()),
+    }
+}
+
+pub async fn record(
+    database: &PgPool,
+    username: &str,
+    ip_address: Option<&str>,
+    succeeded: bool,
+) -> Result<(), sqlx::Error> {
+    if !succeeded {
+        log::warn!("Failed login attempt for user '{username}' from {ip_address:?}");
+    }
+
+    sqlx::query!(
+        "INSERT INTO login_attempts (username, ip_address, succeeded) VALUES ($1, $2, $3)",
+        username,
+        ip_address,
+        succeeded
+    )
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_old_attempts(database: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM login_attempts WHERE created_at < $1",
+        Utc::now() - ATTEMPT_RETENTION
+    )
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let config = Config::default();
+        let now = Utc::now();
+        let failures = |count, seconds_ago| Failures {
+            count,
+            last: Some(now - Duration::seconds(seconds_ago)),
+        };
+
+        assert_eq!(retry_after(&Failures::default(), &config, now), None);
+        assert_eq!(retry_after(&failures(2, 0), &config, now), None);
+        assert_eq!(retry_after(&failures(3, 0), &config, now), Some(1));
+        assert_eq!(retry_after(&failures(6, 0), &config, now), Some(8));
+        assert_eq!(retry_after(&failures(6, 8), &config, now), None);
+        assert_eq!(retry_after(&failures(10, 60), &config, now), Some(840));
+        assert_eq!(retry_after(&failures(10, 900), &config, now), None);
+    }
+
+    #[sqlx::test]
+    async fn test_check_counts_failures_since_last_success(pool: PgPool) -> anyhow::Result<()> {
+        let config = Config::default();
+
+        for _ in 0..3 {
+            record(&pool, "user", Some("192.0.2.1"), false).await?;
+        }
+        assert!(matches!(
+            check(&pool, &config, "user", None).await,
+            Err(ServerError::TooManyRequests(_))
+        ));
+        // The address is limited independent of the username
+        assert!(matches!(
+            check(&pool, &config, "other", Some("192.0.2.1")).await,
+            Err(ServerError::TooManyRequests(_))
+        ));
+        check(&pool, &config, "other", Some("192.0.2.2")).await?;
+
+        record(&pool, "user", Some("192.0.2.1"), true).await?;
+        check(&pool, &config, "user", Some("192.0.2.2")).await?;
+        // A successful login doesn't reset the failures of the address
+        assert!(matches!(
+            check(&pool, &config, "user", Some("192.0.2.1")).await,
+            Err(ServerError::TooManyRequests(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+use argon2::Params;
+use axum::{
+    Json, Router,
+    extract::Path,
+    http::{Method, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get},
+};
+use chrono::{DateTime, Utc};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::{
+    app::{
+        AppState,
+        api::ValidJson,
+        auth::{AuthSession, random_hex, session_user, validate_expiry},
+    },
+    errors::ServerError,
+    users::{self, User, hash_secret, verify_secret},
+};
+
+/// All tokens start with this marker so they are easy to recognize, e.g. by secret scanners
+const TOKEN_MARKER: &str = "kb";
+const PREFIX_BYTES: usize = 8;
+const SECRET_BYTES: usize = 24;
+
+/// Scopes restrict what a token may be used for. A token without scopes has full access to the API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "api_token_scope", rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    /// Only allow safe methods
+    ReadOnly,
+    /// Only allow transaction endpoints
+    TransactionsOnly,
+}
+
+impl ApiTokenScope {
+    /// Check whether this scope permits a request. `path` is the full, un-nested request path.
+    pub fn permits(&self, method: &Method, path: &str) -> bool {
+        match self {
+            ApiTokenScope::ReadOnly => method == Method::GET || method == Method::HEAD,
+            ApiTokenScope::TransactionsOnly => {
+                path == "/api/transactions" || path.starts_with("/api/transactions/")
+            }
+        }
+    }
+}
+
+/// A personal access token. The secret part of the token is only returned once on creation.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiTokenScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ApiTokenCreate {
+    #[garde(length(graphemes, min = 1))]
+    pub name: String,
+    #[serde(default)]
+    #[garde(skip)]
+    pub scopes: Vec<ApiTokenScope>,
+    #[garde(custom(validate_expiry))]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A new token together with its secret
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct CreatedToken {
+    /// The full token, only returned once
+    pub token: String,
+    pub details: ApiToken,
+}
+
+/// Split a token of the form `kb_<prefix>_<secret>` into prefix and secret
+fn parse_token(token: &str) -> Option<(&str, &str)> {
+    let mut parts = token.splitn(3, '_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(TOKEN_MARKER), Some(prefix), Some(secret))
+            if !prefix.is_empty() && !secret.is_empty() =>
+        {
+            Some((prefix, secret))
+        }
+        _ => None,
+    }
+}
+
+impl ApiToken {
+    /// Create a new token and return it along with the plaintext token string
+    pub async fn create(
+        database: &PgPool,
+        user: &User,
+        params: ApiTokenCreate,
+        hash_params: &Params,
+    ) -> Result<(ApiToken, String), users::Error> {
+        let prefix = random_hex(PREFIX_BYTES);
+        let secret = random_hex(SECRET_BYTES);
+
+        let hash = hash_secret(secret.clone(), hash_params).await?;
+
+        let token = sqlx::query_as!(
+            ApiToken,
+            r#"
+            INSERT INTO api_tokens (user_id, name, prefix, hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, prefix, scopes as "scopes: Vec<ApiTokenScope>", expires_at, last_used_at, created_at
+            "#,
+            user.id,
+            params.name,
+            prefix,
+            hash,
+            params.scopes as _,
+            params.expires_at
+        )
+        .fetch_one(database)
+        .await?;
+
+        Ok((token, format!("{TOKEN_MARKER}_{prefix}_{secret}")))
+    }
+
+    pub async fn fetch(database: &PgPool, user: &User) -> Result<Vec<ApiToken>, sqlx::Error> {
+        sqlx::query_as!(
+            ApiToken,
+            r#"
+            SELECT id, name, prefix, scopes as "scopes: Vec<ApiTokenScope>", expires_at, last_used_at, created_at
+            FROM api_tokens
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            "#,
+            user.id
+        )
+        .fetch_all(database)
+        .await
+    }
+
+    pub async fn remove(database: &PgPool, user: &User, id: i32) -> Result<u64, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM api_tokens WHERE user_id = $1 AND id = $2",
+            user.id,
+            id
+        )
+        .execute(database)
+        .await
+        .map(|result| result.rows_affected())
+    }
+
+    /// Return the owner and scopes of a token, or None if the token is invalid or expired
+    pub async fn authenticate(
+        database: &PgPool,
+        token: &str,
+    ) -> Result<Option<(User, Vec<ApiTokenScope>)>, users::Error> {
+        let Some((prefix, secret)) = parse_token(token) else {
+            return Ok(None);
+        };
+
+        let row = sqlx::query!(
+            r#"
+            SELECT id, user_id, hash, scopes as "scopes: Vec<ApiTokenScope>"
+            FROM api_tokens
+            WHERE prefix = $1 AND (expires_at IS NULL OR expires_at > now())
+            "#,
+            prefix
+        )
+        .fetch_optional(database)
+        .await?;
+
+        let Some(row) = row else { return Ok(None) };
+
+        if !verify_secret(row.hash, secret.to_owned()).await? {
+            return Ok(None);
+        }
+
+        sqlx::query!(
+            "UPDATE api_tokens SET last_used_at = now() WHERE id = $1",
+            row.id
+        )
+        .execute(database)
+        .await?;
+
+        let user = sqlx::query_as!(
+            User,
+            "SELECT * FROM users WHERE id = $1 AND NOT disabled",
+            row.user_id
+        )
+        .fetch_optional(database)
+        .await?;
+
+        Ok(user.map(|user| (user, row.scopes)))
+    }
+}
+
+/// Token management is only available to session-based logins, so a token can't be used to mint new tokens
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_tokens).post(create_token))
+        .route("/{id}", delete(revoke_token))
+}
+
+async fn create_token(
+    auth_session: AuthSession,
+    ValidJson(params): ValidJson<ApiTokenCreate>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = session_user(&auth_session)?;
+    let (token, secret) = ApiToken::create(
+        &auth_session.backend.database,
+        user,
+        params,
+        &auth_session.backend.hash_params,
+    )
+    .await?;
+
+    let created = CreatedToken {
+        token: secret,
+        details: token,
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "status": "success", "data": created })),
+    ))
+}
+
+async fn list_tokens(auth_session: AuthSession) -> Result<impl IntoResponse, ServerError> {
+    let user = session_user(&auth_session)?;
+    let tokens = ApiToken::fetch(&auth_session.backend.database, user).await?;
+
+    Ok(Json(json!({ "status": "success", "data": tokens })))
+}
+
+async fn revoke_token(
+    auth_session: AuthSession,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = session_user(&auth_session)?;
+
+    match ApiToken::remove(&auth_session.backend.database, user, id as i32).await? {
+        0 => Err(ServerError::Generic(StatusCode::NOT_FOUND, None)),
+        _ => Ok(Json(json!({ "status": "success" }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    fn create_params(scopes: Vec<ApiTokenScope>) -> ApiTokenCreate {
+        ApiTokenCreate {
+            name: "script".to_string(),
+            scopes,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_token() {
+        assert_eq!(parse_token("kb_abc_def"), Some(("abc", "def")));
+        assert_eq!(parse_token("kb_abc_def_ghi"), Some(("abc", "def_ghi")));
+        assert_eq!(parse_token("xx_abc_def"), None);
+        assert_eq!(parse_token("kb_abc"), None);
+        assert_eq!(parse_token("kb__def"), None);
+    }
+
+    #[test]
+    fn test_scope_permits() {
+        assert!(ApiTokenScope::ReadOnly.permits(&Method::GET, "/api/categories"));
+        assert!(!ApiTokenScope::ReadOnly.permits(&Method::POST, "/api/categories"));
+        assert!(ApiTokenScope::TransactionsOnly.permits(&Method::POST, "/api/transactions/oneoff"));
+        assert!(!ApiTokenScope::TransactionsOnly.permits(&Method::GET, "/api/categories"));
+        assert!(!ApiTokenScope::TransactionsOnly.permits(&Method::GET, "/api/transactionsfoo"));
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_create_and_authenticate_token(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let (token, secret) = ApiToken::create(
+            &pool,
+            &user,
+            create_params(vec![ApiTokenScope::ReadOnly]),
+            &Params::default(),
+        )
+        .await?;
+        assert!(secret.starts_with(&format!("kb_{}_", token.prefix)));
+        assert!(token.last_used_at.is_none());
+
+        let (authenticated, scopes) = ApiToken::authenticate(&pool, &secret)
+            .await?
+            .expect("Valid token was rejected");
+        assert_eq!(authenticated.id, user.id);
+        assert_eq!(scopes, vec![ApiTokenScope::ReadOnly]);
+
+        let tokens = ApiToken::fetch(&pool, &user).await?;
+        assert!(tokens[0].last_used_at.is_some());
+
+        // Wrong secret with a valid prefix
+        let forged = format!("kb_{}_{}", token.prefix, "0".repeat(SECRET_BYTES * 2));
+        assert!(ApiToken::authenticate(&pool, &forged).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_revoked_and_expired_tokens_rejected(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+        let other_user = get_user_by_id(&pool, 2).await;
+
+        let (token, secret) =
+            ApiToken::create(&pool, &user, create_params(vec![]), &Params::default()).await?;
+
+        // Tokens can only be revoked by their owner
+        assert_eq!(ApiToken::remove(&pool, &other_user, token.id).await?, 0);
+        assert_eq!(ApiToken::remove(&pool, &user, token.id).await?, 1);
+        assert!(ApiToken::authenticate(&pool, &secret).await?.is_none());
+
+        let (token, secret) =
+            ApiToken::create(&pool, &user, create_params(vec![]), &Params::default()).await?;
+        sqlx::query!(
+            "UPDATE api_tokens SET expires_at = now() - interval '1 day' WHERE id = $1",
+            token.id
+        )
+        .execute(&pool)
+        .await?;
+        assert!(ApiToken::authenticate(&pool, &secret).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,475 @@
+use argon2::Params;
+use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::post};
+use chrono::Utc;
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+use utoipa::ToSchema;
+
+use crate::{
+    app::{
+        AppState,
+        api::{AuthUser, ValidJson},
+        auth::{AuthSession, random_hex, session_user},
+    },
+    errors::ServerError,
+    users::{User, hash_secrets, verify_password, verify_secret},
+};
+
+const ISSUER: &str = "Kapitalbestand";
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+/// Number of steps before and after the current one that are accepted to account for clock drift
+const SKEW: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_BYTES: usize = 5;
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Enrollment {
+    /// Base32 encoded secret for manual entry in an authenticator app
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TotpCode {
+    #[garde(ascii, length(min = 6, max = 6))]
+    pub code: String,
+}
+
+/// Either a TOTP code or one of the recovery codes
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SecondFactor {
+    #[garde(inner(length(min = 1)))]
+    pub code: Option<String>,
+    #[garde(inner(length(min = 1)))]
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PasswordConfirmation {
+    #[garde(length(graphemes, min = 1))]
+    pub password: String,
+}
+
+/// Single-use codes that replace the TOTP code if the authenticator is lost
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+fn internal_error(err: impl std::fmt::Display) -> ServerError {
+    log::error!("{err}");
+    ServerError::Generic(StatusCode::INTERNAL_SERVER_ERROR, None)
+}
+
+fn build_totp(secret: &str, username: &str) -> Result<TOTP, ServerError> {
+    let secret = Secret::Encoded(secret.to_owned())
+        .to_bytes()
+        .map_err(|err| internal_error(format!("{err:?}")))?;
+
+    // The checked constructor rejects usernames containing ':', but they are URL encoded in the URI anyway.
+    // Skew is handled by `matching_step`, so the generated codes are only valid for their exact step.
+    Ok(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        DIGITS,
+        0,
+        STEP,
+        secret,
+        Some(ISSUER.to_owned()),
+        username.to_owned(),
+    ))
+}
+
+/// Return the time step the code is valid for, if any
+fn matching_step(totp: &TOTP, code: &str, now: u64) -> Option<i64> {
+    let current = now / STEP;
+    (current.saturating_sub(SKEW)..=current + SKEW)
+        .find(|step| totp.check(code, step * STEP))
+        .map(|step| step as i64)
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+/// Recovery codes are shown as `xxxxx-xxxxx`, but accepted with any formatting
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|char| char.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+pub async fn is_enabled(database: &PgPool, user_id: i32) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM user_totp WHERE user_id = $1 AND confirmed_at IS NOT NULL) as "exists!""#,
+        user_id
+    )
+    .fetch_one(database)
+    .await
+}
+
+/// Generate a new secret. It only becomes active once it is confirmed with a valid code.
+pub async fn enroll(database: &PgPool, user: &User) -> Result<Enrollment, ServerError> {
+    if is_enabled(database, user.id).await? {
+        return Err(ServerError::Generic(
+            StatusCode::CONFLICT,
+            Some("Two-factor authentication is already enabled".to_owned()),
+        ));
+    }
+
+    let Secret::Encoded(secret) = Secret::generate_secret().to_encoded() else {
+        unreachable!("Secret::to_encoded always returns an encoded secret");
+    };
+    let totp = build_totp(&secret, &user.username)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL
+        "#,
+        user.id,
+        secret
+    )
+    .execute(database)
+    .await?;
+
+    Ok(Enrollment {
+        otpauth_uri: totp.get_url(),
+        secret,
+    })
+}
+
+/// Activate a pending enrollment and return new recovery codes, or None if the code is invalid
+pub async fn confirm(
+    database: &PgPool,
+    user: &User,
+    code: &str,
+    hash_params: &Params,
+) -> Result<Option<Vec<String>>, ServerError> {
+    let row = sqlx::query!(
+        "SELECT secret FROM user_totp WHERE user_id = $1 AND confirmed_at IS NULL",
+        user.id
+    )
+    .fetch_optional(database)
+    .await?;
+
+    let Some(row) = row else {
+        return Err(ServerError::Generic(
+            StatusCode::BAD_REQUEST,
+            Some("No pending two-factor enrollment".to_owned()),
+        ));
+    };
+
+    let Some(step) = matching_step(&build_totp(&row.secret, &user.username)?, code, now()) else {
+        return Ok(None);
+    };
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = random_hex(RECOVERY_CODE_BYTES);
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect();
+    let hashes = hash_secrets(
+        codes
+            .iter()
+            .map(|code| normalize_recovery_code(code))
+            .collect(),
+        hash_params,
+    )
+    .await?;
+
+    let mut transaction = database.begin().await?;
+    sqlx::query!(
+        "UPDATE user_totp SET confirmed_at = now(), last_used_step = $2 WHERE user_id = $1",
+        user.id,
+        step
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM totp_recovery_codes WHERE user_id = $1",
+        user.id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO totp_recovery_codes (user_id, hash) SELECT $1, * FROM UNNEST($2::varchar[])",
+        user.id,
+        &hashes
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(Some(codes))
+}
+
+/// Check a second factor of a user with enabled two-factor authentication. Each code can only be used once.
+pub async fn verify(
+    database: &PgPool,
+    user: &User,
+    factor: SecondFactor,
+) -> Result<bool, ServerError> {
+    match factor {
+        SecondFactor {
+            code: Some(code),
+            recovery_code: None,
+        } => verify_code(database, user, &code).await,
+        SecondFactor {
+            code: None,
+            recovery_code: Some(recovery_code),
+        } => verify_recovery_code(database, user, &recovery_code).await,
+        _ => Err(ServerError::Generic(
+            StatusCode::BAD_REQUEST,
+            Some("Exactly one of code and recoveryCode is required".to_owned()),
+        )),
+    }
+}
+
+async fn verify_code(database: &PgPool, user: &User, code: &str) -> Result<bool, ServerError> {
+    let row = sqlx::query!(
+        "SELECT secret FROM user_totp WHERE user_id = $1 AND confirmed_at IS NOT NULL",
+        user.id
+    )
+    .fetch_optional(database)
+    .await?;
+
+    let Some(row) = row else { return Ok(false) };
+    let Some(step) = matching_step(&build_totp(&row.secret, &user.username)?, code, now()) else {
+        return Ok(false);
+    };
+
+    // Only accept codes of a later step than the last accepted code to prevent replays
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_totp SET last_used_step = $2
+        WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user.id,
+        step
+    )
+    .execute(database)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+async fn verify_recovery_code(
+    database: &PgPool,
+    user: &User,
+    recovery_code: &str,
+) -> Result<bool, ServerError> {
+    let recovery_code = normalize_recovery_code(recovery_code);
+    let rows = sqlx::query!(
+        "SELECT id, hash FROM totp_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+        user.id
+    )
+    .fetch_all(database)
+    .await?;
+
+    for row in rows {
+        if verify_secret(row.hash, recovery_code.clone()).await? {
+            let result = sqlx::query!(
+                "UPDATE totp_recovery_codes SET used_at = now() WHERE id = $1 AND used_at IS NULL",
+                row.id
+            )
+            .execute(database)
+            .await?;
+            return Ok(result.rows_affected() == 1);
+        }
+    }
+
+    Ok(false)
+}
+
+pub async fn disable(database: &PgPool, user: &User) -> Result<(), sqlx::Error> {
+    let mut transaction = database.begin().await?;
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user.id)
+        .execute(&mut *transaction)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM totp_recovery_codes WHERE user_id = $1",
+        user.id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(enroll_handler).delete(disable_handler))
+        .route("/confirm", post(confirm_handler))
+}
+
+async fn enroll_handler(
+    auth_session: AuthSession,
+    ValidJson(params): ValidJson<PasswordConfirmation>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = session_user(&auth_session)?;
+    if !verify_password(user, params.password).await? {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    let enrollment = enroll(&auth_session.backend.database, user).await?;
+
+    Ok(Json(json!({ "status": "success", "data": enrollment })))
+}
+
+async fn confirm_handler(
+    auth_session: AuthSession,
+    ValidJson(params): ValidJson<TotpCode>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = session_user(&auth_session)?;
+    let Some(recovery_codes) = confirm(
+        &auth_session.backend.database,
+        user,
+        &params.code,
+        &auth_session.backend.hash_params,
+    )
+    .await?
+    else {
+        return Err(ServerError::Generic(
+            StatusCode::FORBIDDEN,
+            Some("Invalid code".to_owned()),
+        ));
+    };
+
+    Ok(Json(
+        json!({ "status": "success", "data": RecoveryCodes { recovery_codes } }),
+    ))
+}
+
+async fn disable_handler(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    ValidJson(params): ValidJson<PasswordConfirmation>,
+) -> Result<impl IntoResponse, ServerError> {
+    if !verify_password(&user, params.password).await? {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    disable(&state.database, &user).await?;
+
+    Ok(Json(json!({ "status": "success" })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    fn current_code(enrollment: &Enrollment, user: &User, offset: i64) -> String {
+        build_totp(&enrollment.secret, &user.username)
+            .unwrap()
+            .generate((now() as i64 + offset) as u64)
+    }
+
+    fn code_factor(code: String) -> SecondFactor {
+        SecondFactor {
+            code: Some(code),
+            recovery_code: None,
+        }
+    }
+
+    #[test]
+    fn test_matching_step() {
+        let totp = build_totp("JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP", "user").unwrap();
+        let now = 1_700_000_000;
+
+        assert_eq!(
+            matching_step(&totp, &totp.generate(now), now),
+            Some((now / STEP) as i64)
+        );
+        assert_eq!(
+            matching_step(&totp, &totp.generate(now - STEP), now),
+            Some((now / STEP) as i64 - 1)
+        );
+        assert_eq!(
+            matching_step(&totp, &totp.generate(now - 3 * STEP), now),
+            None
+        );
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_enroll_and_confirm(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let enrollment = enroll(&pool, &user).await?;
+        assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+        assert!(!is_enabled(&pool, user.id).await?);
+
+        let expired_code = current_code(&enrollment, &user, -10 * STEP as i64);
+        assert!(
+            confirm(&pool, &user, &expired_code, &Params::default())
+                .await?
+                .is_none()
+        );
+
+        // Enrolling again replaces the pending secret
+        let enrollment = enroll(&pool, &user).await?;
+        let codes = confirm(
+            &pool,
+            &user,
+            &current_code(&enrollment, &user, 0),
+            &Params::default(),
+        )
+        .await?
+        .expect("Valid code was rejected");
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(is_enabled(&pool, user.id).await?);
+
+        enroll(&pool, &user)
+            .await
+            .expect_err("Enrollment was replaced while enabled");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "../resources/fixtures", scripts("base")))]
+    async fn test_verify_codes_only_once(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let enrollment = enroll(&pool, &user).await?;
+        let recovery_codes = confirm(
+            &pool,
+            &user,
+            &current_code(&enrollment, &user, -30),
+            &Params::default(),
+        )
+        .await?
+        .expect("Valid code was rejected");
+
+        let code = current_code(&enrollment, &user, 0);
+        assert!(verify(&pool, &user, code_factor(code.clone())).await?);
+        assert!(!verify(&pool, &user, code_factor(code)).await?);
+
+        let recovery_code = SecondFactor {
+            code: None,
+            recovery_code: Some(recovery_codes[0].to_uppercase()),
+        };
+        assert!(verify(&pool, &user, recovery_code.clone()).await?);
+        assert!(!verify(&pool, &user, recovery_code).await?);
+
+        disable(&pool, &user).await?;
+        assert!(!is_enabled(&pool, user.id).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use std::env;
+
+use anyhow::{Context, bail};
+use argon2::Params;
+use axum_login::tower_sessions::cookie::time::Duration;
+use chrono_tz::Tz;
+use openidconnect::{ClientId, ClientSecret, IssuerUrl, RedirectUrl};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::app::env_var_or_file;
+
+/// Where sessions are stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SessionStoreKind {
+    /// Sessions are lost on restart and can't be shared between multiple instances
+    Memory,
+    #[default]
+    Postgres,
+}
+
+/// Who can register new accounts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RegistrationMode {
+    #[default]
+    Open,
+    /// Registration requires a single-use invite code
+    Invite,
+    Closed,
+}
+
+/// OpenID Connect provider used for single sign-on
+#[derive(Clone, Debug)]
+pub struct OidcConfig {
+    pub issuer_url: IssuerUrl,
+    pub client_id: ClientId,
+    pub client_secret: Option<ClientSecret>,
+    /// Must point to `/api/auth/oidc/callback` of this backend
+    pub redirect_url: RedirectUrl,
+    /// Create accounts for unknown identities instead of rejecting them
+    pub auto_provision: bool,
+}
+
+impl OidcConfig {
+    /// Return None if no issuer is configured
+    fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(issuer_url) = env::var("OIDC_ISSUER_URL") else {
+            return Ok(None);
+        };
+
+        let client_id = env::var("OIDC_CLIENT_ID")
+            .context("OIDC_CLIENT_ID must be set with OIDC_ISSUER_URL")?;
+        let redirect_url = env::var("OIDC_REDIRECT_URL")
+            .context("OIDC_REDIRECT_URL must be set with OIDC_ISSUER_URL")?;
+
+        Ok(Some(Self {
+            issuer_url: IssuerUrl::new(issuer_url).context("invalid OIDC_ISSUER_URL")?,
+            client_id: ClientId::new(client_id),
+            client_secret: env_var_or_file("OIDC_CLIENT_SECRET").map(ClientSecret::new),
+            redirect_url: RedirectUrl::new(redirect_url).context("invalid OIDC_REDIRECT_URL")?,
+            auto_provision: bool_var("OIDC_AUTO_PROVISION", false)?,
+        }))
+    }
+}
+
+/// Runtime configuration read from environment variables. See `docs/configuration.md`.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub session_store: SessionStoreKind,
+    pub registration_mode: RegistrationMode,
+    /// Sessions expire after this duration of inactivity
+    pub session_max_age: Duration,
+    /// Inactivity expiry of sessions created with the remember-me flag
+    pub remember_me_max_age: Duration,
+    /// Use the `X-Forwarded-For` header to determine client addresses. Only enable behind a reverse proxy.
+    pub trust_forwarded_for: bool,
+    /// Failed logins per username or address after which further attempts are locked
+    pub login_max_failures: u32,
+    /// How long logins stay locked after reaching `login_max_failures`
+    pub login_lockout_duration: Duration,
+    /// Existing users that are granted admin rights on startup
+    pub admin_usernames: Vec<String>,
+    pub oidc: Option<OidcConfig>,
+    /// Argon2 parameters for new password hashes. Existing hashes with weaker parameters are upgraded on login.
+    pub hash_params: Params,
+    /// Timezone in which relative date ranges like `this_month` are resolved
+    pub timezone: Tz,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            session_store: SessionStoreKind::default(),
+            registration_mode: RegistrationMode::default(),
+            session_max_age: Duration::minutes(2),
+            remember_me_max_age: Duration::days(30),
+            trust_forwarded_for: false,
+            login_max_failures: 10,
+            login_lockout_duration: Duration::minutes(15),
+            admin_usernames: Vec::new(),
+            oidc: None,
+            hash_params: Params::default(),
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+/// Read a duration given in seconds, or return the default if the variable is not set
+fn seconds_var(name: &str, default: Duration) -> anyhow::Result<Duration> {
+    match env::var(name) {
+        Ok(value) => {
+            let seconds: u32 = value
+                .parse()
+                .with_context(|| format!("{name} must be a positive number of seconds"))?;
+            if seconds == 0 {
+                bail!("{name} must be a positive number of seconds");
+            }
+            Ok(Duration::seconds(seconds.into()))
+        }
+        Err(_) => Ok(default),
+    }
+}
+
+fn bool_var(name: &str, default: bool) -> anyhow::Result<bool> {
+    match env::var(name).as_deref() {
+        Ok("true" | "1") => Ok(true),
+        Ok("false" | "0") => Ok(false),
+        Ok(other) => bail!("invalid {name} value '{other}', expected 'true' or 'false'"),
+        Err(_) => Ok(default),
+    }
+}
+
+fn number_var(name: &str, default: u32) -> anyhow::Result<u32> {
+    match env::var(name) {
+        Ok(value) => {
+            let number: u32 = value
+                .parse()
+                .with_context(|| format!("{name} must be a positive number"))?;
+            if number == 0 {
+                bail!("{name} must be a positive number");
+            }
+            Ok(number)
+        }
+        Err(_) => Ok(default),
+    }
+}
+
+/// Read a comma-separated list, ignoring empty entries
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let session_store = match env::var("SESSION_STORE").as_deref() {
+            Err(_) | Ok("postgres") => SessionStoreKind::Postgres,
+            Ok("memory") => SessionStoreKind::Memory,
+            Ok(other) => {
+                bail!("invalid SESSION_STORE value '{other}', expected 'postgres' or 'memory'")
+            }
+        };
+
+        let registration_mode = match env::var("REGISTRATION_MODE").as_deref() {
+            Err(_) | Ok("open") => RegistrationMode::Open,
+            Ok("invite") => RegistrationMode::Invite,
+            Ok("closed") => RegistrationMode::Closed,
+            Ok(other) => {
+                bail!(
+                    "invalid REGISTRATION_MODE value '{other}', expected 'open', 'invite' or 'closed'"
+                )
+            }
+        };
+
+        let defaults = Self::default();
+
+        Ok(Self {
+            session_store,
+            registration_mode,
+            session_max_age: seconds_var("SESSION_MAX_AGE", defaults.session_max_age)?,
+            remember_me_max_age: seconds_var(
+                "SESSION_REMEMBER_ME_MAX_AGE",
+                defaults.remember_me_max_age,
+            )?,
+            trust_forwarded_for: bool_var("TRUST_FORWARDED_FOR", defaults.trust_forwarded_for)?,
+            login_max_failures: number_var("LOGIN_MAX_FAILURES", defaults.login_max_failures)?,
+            login_lockout_duration: seconds_var(
+                "LOGIN_LOCKOUT_DURATION",
+                defaults.login_lockout_duration,
+            )?,
+            admin_usernames: list_var("ADMIN_USERNAMES"),
+            oidc: OidcConfig::from_env()?,
+            hash_params: Params::new(
+                number_var("ARGON2_MEMORY_COST", defaults.hash_params.m_cost())?,
+                number_var("ARGON2_TIME_COST", defaults.hash_params.t_cost())?,
+                number_var("ARGON2_PARALLELISM", defaults.hash_params.p_cost())?,
+                None,
+            )
+            .map_err(|err| anyhow::anyhow!("invalid Argon2 parameters: {err}"))?,
+            timezone: match env::var("TIMEZONE") {
+                Ok(value) => value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid TIMEZONE value '{value}'"))?,
+                Err(_) => defaults.timezone,
+            },
+        })
+    }
+}
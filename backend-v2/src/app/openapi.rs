@@ -0,0 +1,874 @@
+use std::sync::LazyLock;
+
+use axum::{Json, Router, response::IntoResponse, routing::get};
+use utoipa::{
+    IntoParams, PartialSchema, ToSchema,
+    openapi::{
+        ArrayBuilder, ComponentsBuilder, ContentBuilder, Deprecated, HttpMethod, InfoBuilder,
+        ObjectBuilder, OneOfBuilder, OpenApi, OpenApiBuilder, Paths, Ref, RefOr, Required,
+        Response, ResponseBuilder, Schema, Type,
+        path::{OperationBuilder, ParameterBuilder, ParameterIn},
+        request_body::{RequestBody, RequestBodyBuilder},
+        security::{
+            ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme,
+        },
+    },
+};
+
+use crate::{
+    app::{
+        AppState,
+        admin::{LoginAttempt, UserOverview, UserUpdate},
+        api::pagination::{PageMeta, Pagination},
+        auth::{
+            RegistrationInfo, SecondFactorRequired, SessionInfo, WhoAmI,
+            invites::{Invite, InviteCreate},
+            oidc::{CallbackParams, OidcLoginParams},
+            tokens::{ApiToken, ApiTokenCreate, CreatedToken},
+            totp::{Enrollment, PasswordConfirmation, RecoveryCodes, SecondFactor, TotpCode},
+        },
+        resources::{
+            Listing, Resource,
+            categories_shops::{Category, Shop},
+            members::Member,
+            oneoff_transactions::OneoffTransaction,
+            recurring_transactions::{
+                AmountRevisionParams, Occurrence, OccurrenceExceptionParams, OccurrenceParams,
+                RecurringTransaction, year_month::YearMonth,
+            },
+            saved_views::SavedView,
+            settlements::Settlement,
+        },
+        settings::{UserSettings, UserSettingsUpdate},
+        splits::{Balances, Split, SplitParams},
+    },
+    errors::FieldError,
+    users::{
+        AccountDeletion, LoginCredentials, PasswordChange, PasswordReset, RegisterCredentials,
+    },
+};
+
+/// The document is the same for every request, so it is only built once
+static SPEC: LazyLock<OpenApi> = LazyLock::new(spec);
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/openapi.json", get(serve))
+}
+
+async fn serve() -> impl IntoResponse {
+    Json(&*SPEC)
+}
+
+/// Collects the paths of the API together with the schemas they reference
+#[derive(Default)]
+struct SpecBuilder {
+    paths: Paths,
+    schemas: Vec<(String, RefOr<Schema>)>,
+}
+
+impl SpecBuilder {
+    /// Reference a schema, registering it and all schemas it depends on as components
+    fn reference<T: ToSchema>(&mut self) -> RefOr<Schema> {
+        self.schemas.push((T::name().into_owned(), T::schema()));
+        T::schemas(&mut self.schemas);
+        RefOr::Ref(Ref::from_schema_name(T::name()))
+    }
+
+    /// Add the routes of [`crate::build_routes`] for a resource
+    fn resource<T: Resource>(&mut self, path: &str, plural: &str)
+    where
+        T::CreateParams: ToSchema,
+        T::FetchParams: IntoParams,
+        T::UpdateParams: ToSchema,
+        T::ReturnType: ToSchema,
+        T::VecReturnType: Listing,
+    {
+        let name = T::ReturnType::name();
+        let item = self.reference::<T::ReturnType>();
+        let list = ArrayBuilder::new().items(item.clone()).build().into();
+        let meta = T::VecReturnType::HAS_META.then(|| self.reference::<PageMeta>());
+        let create_params = self.reference::<T::CreateParams>();
+        let update_params = self.reference::<T::UpdateParams>();
+        let mut query = Pagination::into_params(|| Some(ParameterIn::Query));
+        query.extend(T::FetchParams::into_params(|| Some(ParameterIn::Query)));
+        let item_path = format!("{path}/{{id}}");
+
+        self.add(
+            path,
+            HttpMethod::Post,
+            operation(plural, format!("create{name}"))
+                .request_body(Some(request_body(create_params)))
+                .response("201", success(Some(item.clone()))),
+        );
+        self.add(
+            path,
+            HttpMethod::Get,
+            operation(plural, format!("fetch{plural}"))
+                .parameters(Some(query))
+                .response("200", list_success(list, meta)),
+        );
+        self.add(
+            &item_path,
+            HttpMethod::Get,
+            operation(plural, format!("get{name}"))
+                .parameter(id_parameter())
+                .response("200", success(Some(item.clone()))),
+        );
+        self.add(
+            &item_path,
+            HttpMethod::Patch,
+            operation(plural, format!("update{name}"))
+                .parameter(id_parameter())
+                .request_body(Some(request_body(update_params)))
+                .response("200", success(Some(item))),
+        );
+        self.add(
+            &item_path,
+            HttpMethod::Delete,
+            operation(plural, format!("remove{name}"))
+                .parameter(id_parameter())
+                .response("200", success(None)),
+        );
+    }
+
+    fn add(&mut self, path: &str, method: HttpMethod, operation: OperationBuilder) {
+        let operation = operation.response("default", RefOr::Ref(Ref::from_response_name("Error")));
+        self.paths
+            .add_path_operation(path, vec![method], operation.build());
+    }
+}
+
+fn operation(tag: &str, operation_id: String) -> OperationBuilder {
+    OperationBuilder::new()
+        .tag(tag)
+        .operation_id(Some(operation_id))
+}
+
+/// Operation that doesn't require authentication
+fn public(operation: OperationBuilder) -> OperationBuilder {
+    operation.securities(Some(Vec::<SecurityRequirement>::new()))
+}
+
+/// Operation that only accepts the session cookie, not API tokens
+fn session_only(operation: OperationBuilder) -> OperationBuilder {
+    operation.securities(Some([SecurityRequirement::new(
+        "session",
+        Vec::<String>::new(),
+    )]))
+}
+
+/// Redirect of the OpenID Connect flow
+fn redirect(description: &str) -> Response {
+    ResponseBuilder::new().description(description).build()
+}
+
+/// Login, registration, the session and the security settings of the account
+fn auth_paths(builder: &mut SpecBuilder) {
+    let session_info = builder.reference::<SessionInfo>();
+    let login_credentials = builder.reference::<LoginCredentials>();
+    let second_factor_required = builder.reference::<SecondFactorRequired>();
+    builder.add(
+        "/api/auth/login",
+        HttpMethod::Post,
+        public(operation("Auth", "login".to_owned()))
+            .request_body(Some(request_body(login_credentials)))
+            .response(
+                "200",
+                success(Some(
+                    OneOfBuilder::new()
+                        .item(session_info.clone())
+                        .item(second_factor_required)
+                        .into(),
+                )),
+            ),
+    );
+    let second_factor = builder.reference::<SecondFactor>();
+    builder.add(
+        "/api/auth/login/totp",
+        HttpMethod::Post,
+        public(operation("Auth", "loginSecondFactor".to_owned()))
+            .request_body(Some(request_body(second_factor)))
+            .response("200", success(Some(session_info.clone()))),
+    );
+    let register_credentials = builder.reference::<RegisterCredentials>();
+    builder.add(
+        "/api/auth/register",
+        HttpMethod::Post,
+        public(operation("Auth", "register".to_owned()))
+            .request_body(Some(request_body(register_credentials)))
+            .response("201", success(Some(session_info.clone()))),
+    );
+    let registration_info = builder.reference::<RegistrationInfo>();
+    builder.add(
+        "/api/auth/registration",
+        HttpMethod::Get,
+        public(operation("Auth", "getRegistration".to_owned()))
+            .response("200", success(Some(registration_info))),
+    );
+
+    let whoami = builder.reference::<WhoAmI>();
+    builder.add(
+        "/api/auth/whoami",
+        HttpMethod::Get,
+        session_only(operation("Auth", "whoami".to_owned()))
+            .response("200", success(Some(whoami.clone()))),
+    );
+    builder.add(
+        "/api/auth/refresh",
+        HttpMethod::Get,
+        session_only(operation("Auth", "refresh".to_owned()))
+            .deprecated(Some(Deprecated::True))
+            .response("200", success(Some(whoami))),
+    );
+    builder.add(
+        "/api/auth/logout",
+        HttpMethod::Get,
+        session_only(operation("Auth", "logout".to_owned())).response("200", success(None)),
+    );
+    let password_change = builder.reference::<PasswordChange>();
+    builder.add(
+        "/api/auth/password",
+        HttpMethod::Post,
+        session_only(operation("Auth", "changePassword".to_owned()))
+            .request_body(Some(request_body(password_change)))
+            .response("200", success(Some(session_info))),
+    );
+    let account_deletion = builder.reference::<AccountDeletion>();
+    builder.add(
+        "/api/auth/account",
+        HttpMethod::Delete,
+        session_only(operation("Auth", "deleteAccount".to_owned()))
+            .request_body(Some(request_body(account_deletion)))
+            .response("200", success(None)),
+    );
+
+    let token = builder.reference::<ApiToken>();
+    let created_token = builder.reference::<CreatedToken>();
+    let token_create = builder.reference::<ApiTokenCreate>();
+    builder.add(
+        "/api/auth/tokens",
+        HttpMethod::Get,
+        session_only(operation("ApiTokens", "fetchApiTokens".to_owned())).response(
+            "200",
+            success(Some(ArrayBuilder::new().items(token).build().into())),
+        ),
+    );
+    builder.add(
+        "/api/auth/tokens",
+        HttpMethod::Post,
+        session_only(operation("ApiTokens", "createApiToken".to_owned()))
+            .request_body(Some(request_body(token_create)))
+            .response("201", success(Some(created_token))),
+    );
+    builder.add(
+        "/api/auth/tokens/{id}",
+        HttpMethod::Delete,
+        session_only(operation("ApiTokens", "revokeApiToken".to_owned()))
+            .parameter(id_parameter())
+            .response("200", success(None)),
+    );
+
+    let enrollment = builder.reference::<Enrollment>();
+    let password_confirmation = builder.reference::<PasswordConfirmation>();
+    let totp_code = builder.reference::<TotpCode>();
+    let recovery_codes = builder.reference::<RecoveryCodes>();
+    builder.add(
+        "/api/auth/totp",
+        HttpMethod::Post,
+        session_only(operation("Totp", "enrollTotp".to_owned()))
+            .request_body(Some(request_body(password_confirmation.clone())))
+            .response("200", success(Some(enrollment))),
+    );
+    builder.add(
+        "/api/auth/totp",
+        HttpMethod::Delete,
+        operation("Totp", "disableTotp".to_owned())
+            .request_body(Some(request_body(password_confirmation)))
+            .response("200", success(None)),
+    );
+    builder.add(
+        "/api/auth/totp/confirm",
+        HttpMethod::Post,
+        session_only(operation("Totp", "confirmTotp".to_owned()))
+            .request_body(Some(request_body(totp_code)))
+            .response("200", success(Some(recovery_codes))),
+    );
+
+    let invite = builder.reference::<Invite>();
+    let invite_create = builder.reference::<InviteCreate>();
+    builder.add(
+        "/api/auth/invites",
+        HttpMethod::Get,
+        session_only(operation("Invites", "fetchInvites".to_owned())).response(
+            "200",
+            success(Some(
+                ArrayBuilder::new().items(invite.clone()).build().into(),
+            )),
+        ),
+    );
+    builder.add(
+        "/api/auth/invites",
+        HttpMethod::Post,
+        session_only(operation("Invites", "createInvite".to_owned()))
+            .request_body(Some({
+                // The body is optional, an invite without expiry is created without it
+                let mut body = request_body(invite_create);
+                body.required = Some(Required::False);
+                body
+            }))
+            .response("201", success(Some(invite))),
+    );
+    builder.add(
+        "/api/auth/invites/{id}",
+        HttpMethod::Delete,
+        session_only(operation("Invites", "revokeInvite".to_owned()))
+            .parameter(id_parameter())
+            .response("200", success(None)),
+    );
+
+    builder.add(
+        "/api/auth/oidc/login",
+        HttpMethod::Get,
+        public(operation("Oidc", "oidcLogin".to_owned()))
+            .parameters(Some(OidcLoginParams::into_params(|| None)))
+            .response("303", redirect("Redirect to the identity provider")),
+    );
+    builder.add(
+        "/api/auth/oidc/link",
+        HttpMethod::Get,
+        session_only(operation("Oidc", "oidcLink".to_owned()))
+            .response("303", redirect("Redirect to the identity provider")),
+    );
+    builder.add(
+        "/api/auth/oidc/callback",
+        HttpMethod::Get,
+        public(operation("Oidc", "oidcCallback".to_owned()))
+            .parameters(Some(CallbackParams::into_params(|| None)))
+            .response("303", redirect("Redirect back to the frontend")),
+    );
+}
+
+/// User management, only for admins logged in with a session
+fn admin_paths(builder: &mut SpecBuilder) {
+    let user = builder.reference::<UserOverview>();
+    let user_update = builder.reference::<UserUpdate>();
+    let password_reset = builder.reference::<PasswordReset>();
+    let login_attempt = builder.reference::<LoginAttempt>();
+    builder.add(
+        "/api/admin/users",
+        HttpMethod::Get,
+        session_only(operation("Admin", "fetchUsers".to_owned())).response(
+            "200",
+            success(Some(ArrayBuilder::new().items(user.clone()).build().into())),
+        ),
+    );
+    builder.add(
+        "/api/admin/users/{id}",
+        HttpMethod::Get,
+        session_only(operation("Admin", "getUser".to_owned()))
+            .parameter(id_parameter())
+            .response("200", success(Some(user.clone()))),
+    );
+    builder.add(
+        "/api/admin/users/{id}",
+        HttpMethod::Patch,
+        session_only(operation("Admin", "updateUser".to_owned()))
+            .parameter(id_parameter())
+            .request_body(Some(request_body(user_update)))
+            .response("200", success(Some(user))),
+    );
+    builder.add(
+        "/api/admin/users/{id}/password",
+        HttpMethod::Post,
+        session_only(operation("Admin", "resetPassword".to_owned()))
+            .parameter(id_parameter())
+            .request_body(Some(request_body(password_reset)))
+            .response("200", success(None)),
+    );
+    builder.add(
+        "/api/admin/login-attempts",
+        HttpMethod::Get,
+        session_only(operation("Admin", "fetchLoginAttempts".to_owned()))
+            .parameters(Some(Pagination::into_params(|| Some(ParameterIn::Query))))
+            .response(
+                "200",
+                success(Some(
+                    ArrayBuilder::new().items(login_attempt).build().into(),
+                )),
+            ),
+    );
+}
+
+fn id_parameter() -> ParameterBuilder {
+    ParameterBuilder::new()
+        .name("id")
+        .parameter_in(ParameterIn::Path)
+        .required(Required::True)
+        .schema(Some(u32::schema()))
+}
+
+fn request_body(schema: RefOr<Schema>) -> RequestBody {
+    RequestBodyBuilder::new()
+        .required(Some(Required::True))
+        .content(
+            "application/json",
+            ContentBuilder::new().schema(Some(schema)).build(),
+        )
+        .build()
+}
+
+/// Response in the `{"status": "success", "data": ...}` envelope, `data` is omitted if no schema is given
+fn success(data: Option<RefOr<Schema>>) -> Response {
+    envelope(data, None)
+}
+
+/// Response of a list, with a `meta` block next to `data` if given
+fn list_success(list: RefOr<Schema>, meta: Option<RefOr<Schema>>) -> Response {
+    envelope(Some(list), meta)
+}
+
+fn envelope(data: Option<RefOr<Schema>>, meta: Option<RefOr<Schema>>) -> Response {
+    let mut envelope = ObjectBuilder::new()
+        .property(
+            "status",
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .enum_values(Some(["success"])),
+        )
+        .required("status");
+    if let Some(data) = data {
+        envelope = envelope.property("data", data).required("data");
+    }
+    if let Some(meta) = meta {
+        envelope = envelope.property("meta", meta).required("meta");
+    }
+
+    ResponseBuilder::new()
+        .description("Success")
+        .content(
+            "application/json",
+            ContentBuilder::new().schema(Some(envelope)).build(),
+        )
+        .build()
+}
+
+/// The error format of [`crate::errors::ServerError`]
+fn error_response(builder: &mut SpecBuilder) -> Response {
+    let field_error = builder.reference::<FieldError>();
+    let schema = ObjectBuilder::new()
+        .property(
+            "status",
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .enum_values(Some(["error"])),
+        )
+        .required("status")
+        .property("code", ObjectBuilder::new().schema_type(Type::String))
+        .required("code")
+        .property("message", ObjectBuilder::new().schema_type(Type::String))
+        .required("message")
+        .property("fields", ArrayBuilder::new().items(field_error))
+        .required("fields");
+
+    ResponseBuilder::new()
+        .description("Error, see `code` for details")
+        .content(
+            "application/json",
+            ContentBuilder::new().schema(Some(schema)).build(),
+        )
+        .build()
+}
+
+pub fn spec() -> OpenApi {
+    let mut builder = SpecBuilder::default();
+    builder.resource::<Category>("/api/categories", "Categories");
+    builder.resource::<Shop>("/api/shops", "Shops");
+    builder.resource::<Member>("/api/members", "Members");
+    builder.resource::<OneoffTransaction>("/api/transactions/oneoff", "OneoffTransactions");
+    builder
+        .resource::<RecurringTransaction>("/api/transactions/recurring", "RecurringTransactions");
+    builder.resource::<Settlement>("/api/balances/settlements", "Settlements");
+    builder.resource::<SavedView>("/api/views", "SavedViews");
+
+    let split = builder.reference::<Split>();
+    let split_params = builder.reference::<SplitParams>();
+    let split_path = "/api/transactions/oneoff/{id}/split";
+    builder.add(
+        split_path,
+        HttpMethod::Get,
+        operation("OneoffTransactions", "getSplit".to_owned())
+            .parameter(id_parameter())
+            .response("200", success(Some(split.clone()))),
+    );
+    builder.add(
+        split_path,
+        HttpMethod::Put,
+        operation("OneoffTransactions", "putSplit".to_owned())
+            .parameter(id_parameter())
+            .request_body(Some(request_body(split_params)))
+            .response("200", success(Some(split))),
+    );
+    builder.add(
+        split_path,
+        HttpMethod::Delete,
+        operation("OneoffTransactions", "removeSplit".to_owned())
+            .parameter(id_parameter())
+            .response("200", success(None)),
+    );
+
+    let occurrence = builder.reference::<Occurrence>();
+    let mut occurrence_parameters = vec![id_parameter().build()];
+    occurrence_parameters.extend(OccurrenceParams::into_params(|| Some(ParameterIn::Query)));
+    builder.add(
+        "/api/transactions/recurring/{id}/occurrences",
+        HttpMethod::Get,
+        operation("RecurringTransactions", "getOccurrences".to_owned())
+            .parameters(Some(occurrence_parameters))
+            .response(
+                "200",
+                success(Some(ArrayBuilder::new().items(occurrence).build().into())),
+            ),
+    );
+
+    let recurring_transaction = builder.reference::<RecurringTransaction>();
+    let amount_revision_params = builder.reference::<AmountRevisionParams>();
+    builder.add(
+        "/api/transactions/recurring/{id}/amounts",
+        HttpMethod::Post,
+        operation("RecurringTransactions", "addAmountRevision".to_owned())
+            .parameter(id_parameter())
+            .request_body(Some(request_body(amount_revision_params)))
+            .response("201", success(Some(recurring_transaction.clone()))),
+    );
+
+    let exception_path = "/api/transactions/recurring/{id}/exceptions/{month}";
+    let month_parameter = || {
+        ParameterBuilder::new()
+            .name("month")
+            .parameter_in(ParameterIn::Path)
+            .required(Required::True)
+            .schema(Some(YearMonth::schema()))
+    };
+    let exception_params = builder.reference::<OccurrenceExceptionParams>();
+    builder.add(
+        exception_path,
+        HttpMethod::Put,
+        operation("RecurringTransactions", "setException".to_owned())
+            .parameter(id_parameter())
+            .parameter(month_parameter())
+            .request_body(Some(request_body(exception_params)))
+            .response("200", success(Some(recurring_transaction.clone()))),
+    );
+    builder.add(
+        exception_path,
+        HttpMethod::Delete,
+        operation("RecurringTransactions", "removeException".to_owned())
+            .parameter(id_parameter())
+            .parameter(month_parameter())
+            .response("200", success(Some(recurring_transaction))),
+    );
+
+    let balances = builder.reference::<Balances>();
+    builder.add(
+        "/api/balances",
+        HttpMethod::Get,
+        operation("Balances", "getBalances".to_owned()).response("200", success(Some(balances))),
+    );
+
+    let settings = builder.reference::<UserSettings>();
+    let settings_update = builder.reference::<UserSettingsUpdate>();
+    builder.add(
+        "/api/settings",
+        HttpMethod::Get,
+        operation("Settings", "getSettings".to_owned())
+            .response("200", success(Some(settings.clone()))),
+    );
+    builder.add(
+        "/api/settings",
+        HttpMethod::Patch,
+        operation("Settings", "updateSettings".to_owned())
+            .request_body(Some(request_body(settings_update)))
+            .response("200", success(Some(settings))),
+    );
+
+    auth_paths(&mut builder);
+    admin_paths(&mut builder);
+    builder.add(
+        "/api/openapi.json",
+        HttpMethod::Get,
+        public(operation("OpenApi", "getOpenApi".to_owned())).response(
+            "200",
+            ResponseBuilder::new()
+                .description("This document")
+                .content("application/json", ContentBuilder::new().build())
+                .build(),
+        ),
+    );
+
+    let error = error_response(&mut builder);
+    // Every operation accepts either a session cookie or an API token
+    let security = [
+        SecurityRequirement::new("session", Vec::<String>::new()),
+        SecurityRequirement::new("token", Vec::<String>::new()),
+    ];
+
+    OpenApiBuilder::new()
+        .info(
+            InfoBuilder::new()
+                .title("Kapitalbestand API")
+                .version(env!("CARGO_PKG_VERSION")),
+        )
+        .paths(builder.paths)
+        .components(Some(
+            ComponentsBuilder::new()
+                .schemas_from_iter(builder.schemas)
+                .response("Error", error)
+                .security_scheme(
+                    "session",
+                    SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id"))),
+                )
+                .security_scheme(
+                    "token",
+                    SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+                )
+                .build(),
+        ))
+        .security(Some(security))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode, header::CONTENT_TYPE},
+        response::Response,
+    };
+    use http_body_util::BodyExt;
+    use regex::Regex;
+    use serde_json::Value;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::app::App;
+
+    fn spec_json() -> Value {
+        serde_json::to_value(spec()).expect("Failed to serialize spec")
+    }
+
+    fn collect_references(value: &Value, references: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(reference)) = map.get("$ref") {
+                    references.push(reference.clone());
+                }
+                map.values()
+                    .for_each(|value| collect_references(value, references));
+            }
+            Value::Array(values) => values
+                .iter()
+                .for_each(|value| collect_references(value, references)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_references_resolve() {
+        let spec = spec_json();
+        let mut references = Vec::new();
+        collect_references(&spec, &mut references);
+        assert!(!references.is_empty());
+
+        for reference in references {
+            let pointer = reference.trim_start_matches('#');
+            assert!(spec.pointer(pointer).is_some(), "{reference} doesn't exist");
+        }
+    }
+
+    #[test]
+    fn test_schemas() {
+        let spec = spec_json();
+        let schemas = &spec["components"]["schemas"];
+
+        let frequencies: Vec<&Value> = schemas["Recurrence"]["oneOf"]
+            .as_array()
+            .expect("Recurrence is not a union")
+            .iter()
+            .map(|variant| &variant["properties"]["frequency"]["enum"][0])
+            .collect();
+        assert_eq!(
+            frequencies,
+            [
+                "yearly",
+                "monthly",
+                "weekly",
+                "biweekly",
+                "quarterly",
+                "everyNMonths"
+            ]
+        );
+        assert!(
+            schemas["Recurrence"]["oneOf"][1]["properties"]
+                .get("monthFrom")
+                .is_some()
+        );
+
+        assert_eq!(schemas["YearMonth"]["type"], "string");
+        assert!(schemas["YearMonth"].get("pattern").is_some());
+
+        assert_eq!(
+            schemas["DateFormat"]["enum"],
+            serde_json::json!(["YYYY-MM-DD", "DD.MM.YYYY", "DD/MM/YYYY", "MM/DD/YYYY"])
+        );
+        assert!(spec["paths"]["/api/settings"].get("patch").is_some());
+
+        // whoami returns the session together with the settings
+        assert!(spec["paths"]["/api/auth/whoami"].get("get").is_some());
+        let whoami = schemas["WhoAmI"].to_string();
+        assert!(whoami.contains("#/components/schemas/UserSettings"));
+        assert!(whoami.contains("#/components/schemas/SessionInfo"));
+        assert!(schemas["SessionInfo"]["properties"]["sessionTimeout"].is_object());
+
+        // TriState fields may be absent or null
+        let update = &schemas["OneoffTransactionUpdateParams"];
+        assert!(update.get("required").is_none());
+        assert_eq!(update["properties"]["shopId"]["oneOf"][0]["type"], "null");
+
+        let parameters: Vec<&Value> =
+            spec["paths"]["/api/transactions/oneoff"]["get"]["parameters"]
+                .as_array()
+                .expect("Missing parameters")
+                .iter()
+                .map(|parameter| &parameter["name"])
+                .collect();
+        for name in [
+            "limit",
+            "offset",
+            "dateFrom",
+            "shopFilter",
+            "orderKey",
+            "cursor",
+            "range",
+        ] {
+            assert!(
+                parameters.contains(&&Value::from(name)),
+                "{name} is missing"
+            );
+        }
+
+        // Id lists are comma separated
+        let category_ids = spec["paths"]["/api/transactions/oneoff"]["get"]["parameters"]
+            .as_array()
+            .expect("Missing parameters")
+            .iter()
+            .find(|parameter| parameter["name"] == "categoryIds")
+            .expect("categoryIds is missing");
+        assert_eq!(category_ids["style"], "form");
+        assert_eq!(category_ids["explode"], false);
+    }
+
+    #[test]
+    fn test_list_meta() {
+        let spec = spec_json();
+        let list_schema = |path: &str| {
+            spec["paths"][path]["get"]["responses"]["200"]["content"]["application/json"]["schema"]
+                .clone()
+        };
+
+        let transactions = list_schema("/api/transactions/oneoff");
+        assert_eq!(
+            transactions["properties"]["meta"]["$ref"],
+            "#/components/schemas/PageMeta"
+        );
+        assert!(
+            list_schema("/api/transactions/recurring")["properties"]
+                .get("meta")
+                .is_some()
+        );
+        assert!(
+            list_schema("/api/shops")["properties"]
+                .get("meta")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_spec_covers_routes() {
+        let spec = spec_json();
+        let mut documented: Vec<(&str, &str)> = spec["paths"]
+            .as_object()
+            .expect("Missing paths")
+            .iter()
+            .flat_map(|(path, item)| {
+                item.as_object()
+                    .expect("Invalid path item")
+                    .keys()
+                    .map(move |method| (method.as_str(), path.as_str()))
+            })
+            .collect();
+        documented.sort();
+        let mut routes = App::ROUTES.to_vec();
+        routes.sort();
+
+        assert_eq!(documented, routes);
+    }
+
+    #[sqlx::test]
+    async fn test_routes_are_served(pool: PgPool) -> anyhow::Result<()> {
+        let app = App::from_pool(pool).router();
+        let request = |method: &str, path: &str| {
+            Request::builder()
+                .method(method.to_uppercase().as_str())
+                .uri(path)
+                .body(Body::empty())
+        };
+        // Unknown paths end up at the static files, which don't answer with JSON errors like the API
+        let is_api_response = |response: &Response| {
+            response.status() != StatusCode::METHOD_NOT_ALLOWED
+                && response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .is_some_and(|value| value.as_bytes().starts_with(b"application/json"))
+        };
+
+        let response = app.clone().oneshot(request("get", "/api/unknown")?).await?;
+        assert!(!is_api_response(&response));
+        let response = app
+            .clone()
+            .oneshot(request("put", "/api/categories")?)
+            .await?;
+        assert!(!is_api_response(&response));
+
+        let parameter = Regex::new(r"\{[^}]+\}")?;
+        for (method, path) in App::ROUTES {
+            let response = app
+                .clone()
+                .oneshot(request(method, &parameter.replace_all(path, "1"))?)
+                .await?;
+            assert!(is_api_response(&response), "{method} {path} is not served");
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_serve_spec(pool: PgPool) -> anyhow::Result<()> {
+        let app = App::from_pool(pool).router();
+
+        // The spec is public, no session or token required
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/openapi.json")
+                    .body(Body::empty())?,
+            )
+            .await?;
+        assert_eq!(response.status(), 200);
+        let body: Value =
+            serde_json::from_slice(&response.into_body().collect().await?.to_bytes())?;
+        assert_eq!(body, spec_json());
+
+        Ok(())
+    }
+}
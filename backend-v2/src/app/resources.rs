@@ -11,15 +11,50 @@ use sqlx::PgPool;
 use crate::{
     app::{
         AppState,
-        api::{AuthUser, ValidJson, ValidQuery, pagination::Pagination},
+        api::{
+            AuthUser, ListQuery, ValidJson,
+            pagination::{Page, PageMeta, Pagination},
+        },
+        config::Config,
     },
     errors::ServerError,
     users::User,
 };
 
 pub mod categories_shops;
+pub mod members;
 pub mod oneoff_transactions;
 pub mod recurring_transactions;
+pub mod saved_views;
+pub mod settlements;
+
+/// Return type of [`Resource::fetch`]
+pub trait Listing {
+    type Item: Serialize;
+
+    /// Whether lists come with [`PageMeta`]
+    const HAS_META: bool;
+
+    fn into_parts(self) -> (Vec<Self::Item>, Option<PageMeta>);
+}
+
+impl<T: Serialize> Listing for Vec<T> {
+    type Item = T;
+    const HAS_META: bool = false;
+
+    fn into_parts(self) -> (Vec<T>, Option<PageMeta>) {
+        (self, None)
+    }
+}
+
+impl<T: Serialize> Listing for Page<T> {
+    type Item = T;
+    const HAS_META: bool = true;
+
+    fn into_parts(self) -> (Vec<T>, Option<PageMeta>) {
+        (self.items, Some(self.meta))
+    }
+}
 
 pub trait Resource {
     type CreateParams;
@@ -38,6 +73,7 @@ pub trait Resource {
     async fn fetch(
         database: &PgPool,
         user: &User,
+        config: &Config,
         params: Self::FetchParams,
         pagination: Pagination,
     ) -> Result<Self::VecReturnType, Self::Error>;
@@ -45,12 +81,14 @@ pub trait Resource {
     async fn get_by_id(
         database: &PgPool,
         user: &User,
+        config: &Config,
         id: i32,
     ) -> Result<Option<Self::ReturnType>, Self::Error>;
 
     async fn update(
         database: &PgPool,
         user: &User,
+        config: &Config,
         id: i32,
         params: Self::UpdateParams,
     ) -> Result<Option<Self::ReturnType>, Self::Error>;
@@ -82,16 +120,18 @@ where
 pub async fn fetch<T: Resource>(
     State(state): State<AppState>,
     AuthUser(user): AuthUser,
-    ValidQuery(pagination): ValidQuery<Pagination>,
-    ValidQuery(params): ValidQuery<T::FetchParams>,
+    ListQuery(pagination, params): ListQuery<T::FetchParams>,
 ) -> Result<impl IntoResponse, ServerError>
 where
     ServerError: From<T::Error>,
-    T::VecReturnType: Serialize,
+    T::VecReturnType: Listing,
 {
-    let result = T::fetch(&state.database, &user, params, pagination).await?;
+    let result = T::fetch(&state.database, &user, &state.config, params, pagination).await?;
 
-    Ok(Json(json!({ "status": "success", "data": result })))
+    Ok(Json(match result.into_parts() {
+        (data, Some(meta)) => json!({ "status": "success", "data": data, "meta": meta }),
+        (data, None) => json!({ "status": "success", "data": data }),
+    }))
 }
 
 pub async fn get_by_id<T: Resource>(
@@ -103,7 +143,7 @@ where
     ServerError: From<T::Error>,
     T::ReturnType: Serialize,
 {
-    let result = T::get_by_id(&state.database, &user, id as i32).await?;
+    let result = T::get_by_id(&state.database, &user, &state.config, id as i32).await?;
 
     let Some(instance) = result else {
         return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
@@ -122,7 +162,7 @@ where
     ServerError: From<T::Error>,
     T::ReturnType: Serialize,
 {
-    let result = T::update(&state.database, &user, id as i32, params).await?;
+    let result = T::update(&state.database, &user, &state.config, id as i32, params).await?;
 
     let Some(instance) = result else {
         return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
@@ -2,27 +2,31 @@ use chrono::{DateTime, Utc};
 use garde::Validate;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Postgres, QueryBuilder, prelude::FromRow};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    app::{api::pagination::Pagination, resources::Resource},
+    app::{api::pagination::Pagination, config::Config, resources::Resource},
     users::User,
 };
 
-#[derive(Debug, Clone, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct CategoryShopCreate {
     #[garde(length(graphemes, min = 1))]
+    #[schema(min_length = 1)]
     name: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate, IntoParams)]
 #[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
 pub struct CategoryShopFetch {
     #[garde(length(graphemes, min = 1))]
+    #[param(min_length = 1)]
     name: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
 pub struct Category {
     pub id: i32,
     pub name: String,
@@ -57,6 +61,7 @@ impl Resource for Category {
     async fn fetch(
         database: &PgPool,
         user: &User,
+        _config: &Config,
         params: Self::FetchParams,
         Pagination { limit, offset }: Pagination,
     ) -> Result<Self::VecReturnType, Self::Error> {
@@ -83,6 +88,7 @@ impl Resource for Category {
     async fn get_by_id(
         database: &sqlx::PgPool,
         user: &User,
+        _config: &Config,
         id: i32,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
         sqlx::query_as!(
@@ -98,6 +104,7 @@ impl Resource for Category {
     async fn update(
         database: &sqlx::PgPool,
         user: &User,
+        _config: &Config,
         id: i32,
         params: Self::CreateParams,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
@@ -125,7 +132,7 @@ impl Resource for Category {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
 pub struct Shop {
     pub id: i32,
     pub name: String,
@@ -160,6 +167,7 @@ impl Resource for Shop {
     async fn fetch(
         database: &PgPool,
         user: &User,
+        _config: &Config,
         params: Self::FetchParams,
         Pagination { limit, offset }: Pagination,
     ) -> Result<Self::VecReturnType, Self::Error> {
@@ -183,6 +191,7 @@ impl Resource for Shop {
     async fn get_by_id(
         database: &sqlx::PgPool,
         user: &User,
+        _config: &Config,
         id: i32,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
         sqlx::query_as!(
@@ -198,6 +207,7 @@ impl Resource for Shop {
     async fn update(
         database: &sqlx::PgPool,
         user: &User,
+        _config: &Config,
         id: i32,
         params: Self::CreateParams,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
@@ -312,6 +322,7 @@ mod category_tests {
         let categories = Category::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::default(),
         )
@@ -337,7 +348,14 @@ mod category_tests {
             name: Some("Groceries".to_string()),
         };
 
-        let categories = Category::fetch(&pool, &user, params, Pagination::default()).await?;
+        let categories = Category::fetch(
+            &pool,
+            &user,
+            &Config::default(),
+            params,
+            Pagination::default(),
+        )
+        .await?;
 
         assert_eq!(categories.len(), 1);
         assert_eq!(categories[0].name, "Groceries");
@@ -353,6 +371,7 @@ mod category_tests {
         let categories = Category::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch {
                 name: Some("NonExistentCategory".to_string()),
             },
@@ -371,7 +390,8 @@ mod category_tests {
         let params = CategoryShopFetch { name: None };
         let pagination = Pagination::new(Limit(3), Offset(0));
 
-        let categories = Category::fetch(&pool, &user, params, pagination).await?;
+        let categories =
+            Category::fetch(&pool, &user, &Config::default(), params, pagination).await?;
 
         assert!(categories.len() == 3);
 
@@ -386,6 +406,7 @@ mod category_tests {
         let categories_1 = Category::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::new(Limit(5), Offset(0)),
         )
@@ -395,6 +416,7 @@ mod category_tests {
         let categories_2 = Category::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::new(Limit(5), Offset(5)),
         )
@@ -429,9 +451,22 @@ mod category_tests {
         let user2 = get_user_by_id(&pool, 2).await; // Bob
         let params = CategoryShopFetch { name: None };
 
-        let categories_1 =
-            Category::fetch(&pool, &user1, params.clone(), Pagination::default()).await?;
-        let categories_2 = Category::fetch(&pool, &user2, params, Pagination::default()).await?;
+        let categories_1 = Category::fetch(
+            &pool,
+            &user1,
+            &Config::default(),
+            params.clone(),
+            Pagination::default(),
+        )
+        .await?;
+        let categories_2 = Category::fetch(
+            &pool,
+            &user2,
+            &Config::default(),
+            params,
+            Pagination::default(),
+        )
+        .await?;
 
         // All categories should belong to their respective users
         for category in &categories_1 {
@@ -458,9 +493,10 @@ mod category_tests {
         .fetch_one(&pool)
         .await?;
 
-        let fetched_category = Category::get_by_id(&pool, &user, existing_category.id)
-            .await?
-            .expect("Category not found by ID");
+        let fetched_category =
+            Category::get_by_id(&pool, &user, &Config::default(), existing_category.id)
+                .await?
+                .expect("Category not found by ID");
 
         assert_eq!(fetched_category, existing_category);
 
@@ -471,7 +507,7 @@ mod category_tests {
     async fn test_get_by_id_nonexistent(pool: PgPool) -> anyhow::Result<()> {
         let user = get_user_by_id(&pool, 1).await;
 
-        let result = Category::get_by_id(&pool, &user, 99999).await?;
+        let result = Category::get_by_id(&pool, &user, &Config::default(), 99999).await?;
 
         assert!(result.is_none());
 
@@ -491,7 +527,7 @@ mod category_tests {
         .await?;
 
         // Try to access it with user2
-        let result = Category::get_by_id(&pool, &user2, category.id).await?;
+        let result = Category::get_by_id(&pool, &user2, &Config::default(), category.id).await?;
 
         assert!(result.is_none());
 
@@ -514,6 +550,7 @@ mod category_tests {
         let updated_category = Category::update(
             &pool,
             &user,
+            &Config::default(),
             original_category.id,
             CategoryShopCreate {
                 name: "Updated Groceries".to_string(),
@@ -529,7 +566,7 @@ mod category_tests {
         assert!(updated_category.updated_at > original_category.updated_at);
 
         // Verify the change persisted
-        let fetched = Category::get_by_id(&pool, &user, original_category.id)
+        let fetched = Category::get_by_id(&pool, &user, &Config::default(), original_category.id)
             .await?
             .expect("Category not found after update");
         assert_eq!(fetched.name, "Updated Groceries");
@@ -544,6 +581,7 @@ mod category_tests {
         let result = Category::update(
             &pool,
             &user,
+            &Config::default(),
             99999,
             CategoryShopCreate {
                 name: "Should Not Update".to_string(),
@@ -574,6 +612,7 @@ mod category_tests {
         let updated_category_option = Category::update(
             &pool,
             &user2,
+            &Config::default(),
             category.id,
             CategoryShopCreate {
                 name: "Hacked Update".to_string(),
@@ -584,7 +623,7 @@ mod category_tests {
         assert!(updated_category_option.is_none()); // Should not update
 
         // Verify original category is unchanged
-        let original = Category::get_by_id(&pool, &user1, category.id)
+        let original = Category::get_by_id(&pool, &user1, &Config::default(), category.id)
             .await?
             .expect("Original category not found when it should exist");
         assert_eq!(original, category);
@@ -609,6 +648,7 @@ mod category_tests {
         let updated_category = Category::update(
             &pool,
             &user,
+            &Config::default(),
             created.id,
             CategoryShopCreate {
                 name: "Updated with Special Chars: Café".to_string(),
@@ -639,7 +679,8 @@ mod category_tests {
         assert_eq!(Category::remove(&pool, &user, created.id).await?, 1u64);
 
         // Verify it's deleted
-        let fetched_option = Category::get_by_id(&pool, &user, created.id).await?;
+        let fetched_option =
+            Category::get_by_id(&pool, &user, &Config::default(), created.id).await?;
         assert!(fetched_option.is_none());
 
         Ok(())
@@ -663,6 +704,7 @@ mod category_tests {
         let categories = Category::fetch(
             &pool,
             &user1,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::new(Limit(1), Offset(0)),
         )
@@ -678,7 +720,7 @@ mod category_tests {
         assert_eq!(Category::remove(&pool, &user2, category.id).await?, 0);
 
         // Verify category still exists for user1
-        let original_category = Category::get_by_id(&pool, &user1, category.id)
+        let original_category = Category::get_by_id(&pool, &user1, &Config::default(), category.id)
             .await?
             .expect("Failed to find category");
         assert_eq!(&original_category, category);
@@ -716,6 +758,7 @@ mod category_tests {
         let updated = Category::update(
             &pool,
             &user,
+            &Config::default(),
             created.id,
             CategoryShopCreate {
                 name: "Updated Timestamp Test".to_string(),
@@ -823,6 +866,7 @@ mod shop_tests {
         let shops = Shop::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::default(),
         )
@@ -848,7 +892,14 @@ mod shop_tests {
             name: Some("Whole Foods".to_string()),
         };
 
-        let shops = Shop::fetch(&pool, &user, params, Pagination::default()).await?;
+        let shops = Shop::fetch(
+            &pool,
+            &user,
+            &Config::default(),
+            params,
+            Pagination::default(),
+        )
+        .await?;
 
         assert_eq!(shops.len(), 1);
         assert_eq!(shops[0].name, "Whole Foods");
@@ -864,6 +915,7 @@ mod shop_tests {
         let shops = Shop::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch {
                 name: Some("NonExistentShop".to_string()),
             },
@@ -882,7 +934,7 @@ mod shop_tests {
         let params = CategoryShopFetch { name: None };
         let pagination = Pagination::new(Limit(3), Offset(0));
 
-        let shops = Shop::fetch(&pool, &user, params, pagination).await?;
+        let shops = Shop::fetch(&pool, &user, &Config::default(), params, pagination).await?;
 
         assert!(shops.len() == 3);
 
@@ -897,6 +949,7 @@ mod shop_tests {
         let shops_1 = Shop::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::new(Limit(5), Offset(0)),
         )
@@ -906,6 +959,7 @@ mod shop_tests {
         let shops_2 = Shop::fetch(
             &pool,
             &user,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::new(Limit(5), Offset(5)),
         )
@@ -940,8 +994,22 @@ mod shop_tests {
         let user2 = get_user_by_id(&pool, 2).await; // Bob
         let params = CategoryShopFetch { name: None };
 
-        let shops_1 = Shop::fetch(&pool, &user1, params.clone(), Pagination::default()).await?;
-        let shops_2 = Shop::fetch(&pool, &user2, params, Pagination::default()).await?;
+        let shops_1 = Shop::fetch(
+            &pool,
+            &user1,
+            &Config::default(),
+            params.clone(),
+            Pagination::default(),
+        )
+        .await?;
+        let shops_2 = Shop::fetch(
+            &pool,
+            &user2,
+            &Config::default(),
+            params,
+            Pagination::default(),
+        )
+        .await?;
 
         // All shops should belong to their respective users
         for shop in &shops_1 {
@@ -968,7 +1036,7 @@ mod shop_tests {
         .fetch_one(&pool)
         .await?;
 
-        let fetched_shop = Shop::get_by_id(&pool, &user, existing_shop.id)
+        let fetched_shop = Shop::get_by_id(&pool, &user, &Config::default(), existing_shop.id)
             .await?
             .expect("Shop not found by ID");
 
@@ -981,7 +1049,7 @@ mod shop_tests {
     async fn test_get_shop_by_id_nonexistent(pool: PgPool) -> anyhow::Result<()> {
         let user = get_user_by_id(&pool, 1).await;
 
-        let result = Shop::get_by_id(&pool, &user, 99999).await?;
+        let result = Shop::get_by_id(&pool, &user, &Config::default(), 99999).await?;
 
         assert!(result.is_none());
 
@@ -997,7 +1065,7 @@ mod shop_tests {
             .await?;
 
         // Try to access it with user2
-        let result = Shop::get_by_id(&pool, &user2, shop.id).await?;
+        let result = Shop::get_by_id(&pool, &user2, &Config::default(), shop.id).await?;
 
         assert!(result.is_none());
 
@@ -1020,6 +1088,7 @@ mod shop_tests {
         let updated_shop = Shop::update(
             &pool,
             &user,
+            &Config::default(),
             original_shop.id,
             CategoryShopCreate {
                 name: "Updated Shop Name".to_string(),
@@ -1035,7 +1104,7 @@ mod shop_tests {
         assert!(updated_shop.updated_at > original_shop.updated_at);
 
         // Verify the change persisted
-        let fetched = Shop::get_by_id(&pool, &user, original_shop.id)
+        let fetched = Shop::get_by_id(&pool, &user, &Config::default(), original_shop.id)
             .await?
             .expect("Shop not found after update");
         assert_eq!(fetched.name, "Updated Shop Name");
@@ -1050,6 +1119,7 @@ mod shop_tests {
         let result = Shop::update(
             &pool,
             &user,
+            &Config::default(),
             99999,
             CategoryShopCreate {
                 name: "Should Not Update".to_string(),
@@ -1076,6 +1146,7 @@ mod shop_tests {
         let updated_shop_option = Shop::update(
             &pool,
             &user2,
+            &Config::default(),
             shop.id,
             CategoryShopCreate {
                 name: "Hacked Update".to_string(),
@@ -1086,7 +1157,7 @@ mod shop_tests {
         assert!(updated_shop_option.is_none()); // Should not update
 
         // Verify original shop is unchanged
-        let original = Shop::get_by_id(&pool, &user1, shop.id)
+        let original = Shop::get_by_id(&pool, &user1, &Config::default(), shop.id)
             .await?
             .expect("Original shop not found when it should exist");
         assert_eq!(original, shop);
@@ -1111,6 +1182,7 @@ mod shop_tests {
         let updated_shop = Shop::update(
             &pool,
             &user,
+            &Config::default(),
             created.id,
             CategoryShopCreate {
                 name: "Updated Shop: Café & Stuff (100%)".to_string(),
@@ -1141,7 +1213,7 @@ mod shop_tests {
         assert_eq!(Shop::remove(&pool, &user, created.id).await?, 1u64);
 
         // Verify it's deleted
-        let fetched_option = Shop::get_by_id(&pool, &user, created.id).await?;
+        let fetched_option = Shop::get_by_id(&pool, &user, &Config::default(), created.id).await?;
         assert!(fetched_option.is_none());
 
         Ok(())
@@ -1165,6 +1237,7 @@ mod shop_tests {
         let shops = Shop::fetch(
             &pool,
             &user1,
+            &Config::default(),
             CategoryShopFetch { name: None },
             Pagination::new(Limit(1), Offset(0)),
         )
@@ -1180,7 +1253,7 @@ mod shop_tests {
         assert_eq!(Shop::remove(&pool, &user2, shop.id).await?, 0);
 
         // Verify shop still exists for user1
-        let original_shop = Shop::get_by_id(&pool, &user1, shop.id)
+        let original_shop = Shop::get_by_id(&pool, &user1, &Config::default(), shop.id)
             .await?
             .expect("Failed to find shop");
         assert_eq!(&original_shop, shop);
@@ -1218,6 +1291,7 @@ mod shop_tests {
         let updated = Shop::update(
             &pool,
             &user,
+            &Config::default(),
             created.id,
             CategoryShopCreate {
                 name: "Updated Timestamp Shop".to_string(),
@@ -0,0 +1,8 @@
+-- Household members used for splitting transactions
+INSERT INTO members (name, user_id) VALUES
+-- Alice's household
+('Alice', 1),
+('Carol', 1),
+('Dave', 1),
+-- Bob's household
+('Bob', 2);
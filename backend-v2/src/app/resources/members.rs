@@ -0,0 +1,262 @@
+use std::borrow::Cow;
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder, prelude::FromRow};
+use utoipa::{
+    IntoParams, PartialSchema, ToSchema,
+    openapi::{RefOr, Schema},
+};
+
+use crate::{
+    app::{
+        api::pagination::Pagination, config::Config, resources::Resource, transactions::id_schema,
+    },
+    errors::ServerError,
+    users::User,
+};
+
+/// A household member that transactions can be split between. Members don't need an account of their own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, FromRow, ToSchema)]
+pub struct Member {
+    pub id: i32,
+    pub name: String,
+    pub user_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MemberCreate {
+    #[garde(length(graphemes, min = 1))]
+    #[schema(min_length = 1)]
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct MemberFetch {
+    #[garde(length(graphemes, min = 1))]
+    #[param(min_length = 1)]
+    name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Validate)]
+#[serde(transparent)]
+#[garde(transparent)]
+pub struct UnvalidatedMemberId(#[garde(range(min = 1))] i32);
+
+impl UnvalidatedMemberId {
+    #[cfg(test)]
+    pub fn from(id: i32) -> Self {
+        Self(id)
+    }
+
+    pub async fn validate(self, user: &User, database: &PgPool) -> Result<i32, ServerError> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM members WHERE user_id = $1 AND id = $2) AS "exists!""#,
+            user.id,
+            self.0
+        )
+        .fetch_one(database)
+        .await?;
+        if !exists {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some("Invalid member id".to_owned()),
+            ));
+        }
+        Ok(self.0)
+    }
+}
+
+impl PartialSchema for UnvalidatedMemberId {
+    fn schema() -> RefOr<Schema> {
+        id_schema()
+    }
+}
+
+impl ToSchema for UnvalidatedMemberId {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("MemberId")
+    }
+}
+
+impl Resource for Member {
+    type CreateParams = MemberCreate;
+    type FetchParams = MemberFetch;
+    type UpdateParams = MemberCreate;
+    type ReturnType = Member;
+    type VecReturnType = Vec<Self::ReturnType>;
+    type Error = sqlx::Error;
+
+    async fn create(
+        database: &PgPool,
+        user: &User,
+        params: Self::CreateParams,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        sqlx::query_as!(
+            Member,
+            "INSERT INTO members (user_id, name) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING *",
+            user.id,
+            params.name
+        )
+        .fetch_optional(database)
+        .await
+    }
+
+    async fn fetch(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        params: Self::FetchParams,
+        Pagination { limit, offset }: Pagination,
+    ) -> Result<Self::VecReturnType, Self::Error> {
+        let mut builder: QueryBuilder<Postgres> =
+            QueryBuilder::new("SELECT * FROM members WHERE user_id = ");
+        builder.push_bind(user.id);
+
+        if let Some(name) = params.name {
+            builder.push(" AND name = ").push_bind(name);
+        }
+
+        builder
+            .push(" ORDER BY name, id LIMIT ")
+            .push_bind(limit.0)
+            .push(" OFFSET ")
+            .push_bind(offset.0);
+
+        builder.build_query_as::<Member>().fetch_all(database).await
+    }
+
+    async fn get_by_id(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        id: i32,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        sqlx::query_as!(
+            Member,
+            "SELECT * FROM members WHERE user_id = $1 AND id = $2",
+            user.id,
+            id
+        )
+        .fetch_optional(database)
+        .await
+    }
+
+    async fn update(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        id: i32,
+        params: Self::UpdateParams,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        sqlx::query_as!(
+            Member,
+            "UPDATE members SET name = $3 WHERE user_id = $1 AND id = $2 RETURNING *",
+            user.id,
+            id,
+            params.name
+        )
+        .fetch_optional(database)
+        .await
+    }
+
+    async fn remove(database: &PgPool, user: &User, id: i32) -> Result<u64, Self::Error> {
+        sqlx::query!(
+            "DELETE FROM members WHERE user_id = $1 AND id = $2",
+            user.id,
+            id
+        )
+        .execute(database)
+        .await
+        .map(|result| result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    #[sqlx::test(fixtures("base", "members"))]
+    async fn test_create_member(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let member = Member::create(
+            &pool,
+            &user,
+            MemberCreate {
+                name: "Charlie".to_string(),
+            },
+        )
+        .await?
+        .expect("Failed to create member");
+
+        assert_eq!(member.name, "Charlie");
+        assert_eq!(member.user_id, user.id);
+
+        // Names are unique per user
+        let duplicate = Member::create(
+            &pool,
+            &user,
+            MemberCreate {
+                name: "Charlie".to_string(),
+            },
+        )
+        .await?;
+        assert!(duplicate.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("base", "members"))]
+    async fn test_fetch_members_user_isolation(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let members = Member::fetch(
+            &pool,
+            &user,
+            &Config::default(),
+            MemberFetch { name: None },
+            Pagination::default(),
+        )
+        .await?;
+
+        assert_eq!(members.len(), 3);
+        assert!(members.iter().all(|m| m.user_id == user.id));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("base", "members"))]
+    async fn test_validate_member_id(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        assert_eq!(
+            UnvalidatedMemberId::from(1).validate(&user, &pool).await?,
+            1
+        );
+        UnvalidatedMemberId::from(4)
+            .validate(&user, &pool)
+            .await
+            .expect_err("Member of a different user was accepted");
+        UnvalidatedMemberId::from(99999)
+            .validate(&user, &pool)
+            .await
+            .expect_err("Nonexistent member was accepted");
+
+        Ok(())
+    }
+}
@@ -1,21 +1,30 @@
 use std::ops::Deref;
 
+use axum::http::StatusCode;
 use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 use garde::Validate;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::app::api::column_filter::{ColumnFilter, FilterMode};
-use crate::app::api::pagination::Pagination;
+use crate::app::api::column_filter::FilterMode;
+use crate::app::api::pagination::{Cursor, Page, Pagination, SortValue};
 use crate::app::api::tri_state_field::TriState;
+use crate::app::config::Config;
 use crate::app::resources::Resource;
+use crate::app::resources::saved_views::{SavedView, SavedViewParams};
+use crate::app::settings::UserSettings;
+use crate::app::transactions::date_range::DateRange;
+use crate::app::transactions::filters::{IdList, TransactionFilterParams, TransactionFilters};
 use crate::app::transactions::{
-    Amount, Description, OrderKey, Ordering, UnvalidatedCategoryId, UnvalidatedShopId,
+    Amount, Description, Keyset, Order, OrderKey, Ordering, SortColumn, Totals,
+    UnvalidatedCategoryId, UnvalidatedShopId,
 };
 use crate::errors::ServerError;
 use crate::users::User;
 
-#[derive(Clone, Debug, Serialize, FromRow, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, FromRow, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct OneoffTransaction {
     id: i32,
@@ -32,7 +41,7 @@ pub struct OneoffTransaction {
     shop: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Validate)]
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct OneoffTransactionCreateParams {
     #[garde(skip)]
@@ -49,8 +58,9 @@ pub struct OneoffTransactionCreateParams {
     shop_id: Option<UnvalidatedShopId>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Validate)]
+#[derive(Clone, Debug, Default, Deserialize, Validate, IntoParams)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
 pub struct OneoffTransactionFetchParams {
     #[garde(skip)]
     is_expense: Option<bool>,
@@ -58,25 +68,124 @@ pub struct OneoffTransactionFetchParams {
     date_from: Option<NaiveDate>,
     #[garde(skip)]
     date_to: Option<NaiveDate>,
+    /// Relative date range in the timezone of the user, `dateFrom` and `dateTo` take precedence
+    #[garde(skip)]
+    #[param(inline)]
+    range: Option<DateRange>,
     #[garde(dive)]
     amount_from: Option<Amount>,
     #[garde(dive)]
     amount_to: Option<Amount>,
     #[garde(dive)]
     category_id: Option<UnvalidatedCategoryId>,
+    /// Comma separated, only transactions of one of these categories
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    category_ids: Option<IdList>,
+    /// Comma separated, no transactions of these categories
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    exclude_category_ids: Option<IdList>,
     #[garde(skip)]
+    #[param(inline)]
     shop_filter: Option<FilterMode>,
     #[garde(dive)]
     shop_id: Option<UnvalidatedShopId>,
-    #[serde(default)]
+    /// Comma separated, only transactions of one of these shops
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    shop_ids: Option<IdList>,
+    /// Comma separated, no transactions of these shops. Transactions without shop are kept.
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    exclude_shop_ids: Option<IdList>,
+    /// Only transactions whose description contains this text, ignoring case
+    #[garde(length(min = 1))]
+    description_contains: Option<String>,
+    /// `true` only returns transactions without description, `false` only those with one
     #[garde(skip)]
-    ordering: Ordering,
-    #[serde(default)]
+    description_is_null: Option<bool>,
+    /// Sort keys with their own directions like `category:asc,amount:desc`, takes precedence over `ordering` and
+    /// `orderKey`
+    #[garde(skip)]
+    #[param(value_type = Option<String>, example = "category:asc,amount:desc")]
+    order: Option<Order>,
+    /// Defaults to the `ordering` of the user settings
     #[garde(skip)]
-    order_key: OrderKey,
+    #[param(inline)]
+    ordering: Option<Ordering>,
+    /// Defaults to the `orderKey` of the user settings
+    #[garde(skip)]
+    #[param(inline)]
+    order_key: Option<OrderKey>,
+    /// `nextCursor` of the previous page, continues after its last row
+    #[garde(skip)]
+    cursor: Option<Cursor>,
+    /// Id of a saved view whose parameters apply unless they are given explicitly
+    #[garde(range(min = 1))]
+    view: Option<i32>,
+}
+
+impl OneoffTransactionFetchParams {
+    /// Fill in all parameters that aren't given explicitly from a saved view
+    fn with_view(self, view: SavedViewParams) -> Self {
+        // The ordering of the view only applies if no ordering is given at all
+        let (order, ordering, order_key) = match (&self.order, &self.ordering, &self.order_key) {
+            (None, None, None) => (view.order, view.ordering, view.order_key),
+            _ => (
+                self.order.clone(),
+                self.ordering.clone(),
+                self.order_key.clone(),
+            ),
+        };
+        // Likewise, the dates of the view only apply if neither dates nor a range are given, to never mix both
+        let (date_from, date_to, range) = match (&self.date_from, &self.date_to, &self.range) {
+            (None, None, None) => (view.date_from, view.date_to, view.range),
+            _ => (self.date_from, self.date_to, self.range),
+        };
+        OneoffTransactionFetchParams {
+            is_expense: self.is_expense.or(view.is_expense),
+            date_from,
+            date_to,
+            range,
+            amount_from: self.amount_from.or(view.amount_from),
+            amount_to: self.amount_to.or(view.amount_to),
+            category_ids: self.category_ids.or(view.category_ids.map(Into::into)),
+            exclude_category_ids: self
+                .exclude_category_ids
+                .or(view.exclude_category_ids.map(Into::into)),
+            shop_ids: self.shop_ids.or(view.shop_ids.map(Into::into)),
+            exclude_shop_ids: self
+                .exclude_shop_ids
+                .or(view.exclude_shop_ids.map(Into::into)),
+            description_contains: self.description_contains.or(view.description_contains),
+            description_is_null: self.description_is_null.or(view.description_is_null),
+            order,
+            ordering,
+            order_key,
+            ..self
+        }
+    }
+
+    fn filter_params(&self) -> TransactionFilterParams {
+        TransactionFilterParams {
+            is_expense: self.is_expense,
+            amount_from: self.amount_from,
+            amount_to: self.amount_to,
+            category_id: self.category_id.clone(),
+            category_ids: self.category_ids.clone(),
+            exclude_category_ids: self.exclude_category_ids.clone(),
+            shop_filter: self.shop_filter,
+            shop_id: self.shop_id.clone(),
+            shop_ids: self.shop_ids.clone(),
+            exclude_shop_ids: self.exclude_shop_ids.clone(),
+            description_contains: self.description_contains.clone(),
+            description_is_null: self.description_is_null,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Validate)]
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct OneoffTransactionUpdateParams {
     #[garde(skip)]
@@ -85,22 +194,100 @@ pub struct OneoffTransactionUpdateParams {
     is_expense: Option<bool>,
     #[garde(dive)]
     amount: Option<Amount>,
+    /// `null` removes the description
     #[serde(default)]
     #[garde(dive)]
+    #[schema(value_type = Option<Description>)]
     description: TriState<Description>,
     #[garde(dive)]
     category_id: Option<UnvalidatedCategoryId>,
+    /// `null` removes the shop
     #[serde(default)]
     #[garde(dive)]
+    #[schema(value_type = Option<UnvalidatedShopId>)]
     shop_id: TriState<UnvalidatedShopId>,
 }
 
+const FETCH_FROM: &str = r#"
+    FROM oneoff_transactions ot
+    INNER JOIN categories c ON ot.category_id = c.id
+    LEFT JOIN shops s ON ot.shop_id = s.id
+"#;
+
+/// Filters of [`OneoffTransactionFetchParams`], with ids checked to belong to the user
+struct Filters {
+    transaction: TransactionFilters,
+    date_from: Option<NaiveDate>,
+    date_to: Option<NaiveDate>,
+}
+
+impl Filters {
+    async fn resolve(
+        database: &sqlx::PgPool,
+        user: &User,
+        params: &OneoffTransactionFetchParams,
+        timezone: Tz,
+    ) -> Result<Self, ServerError> {
+        let range = params.range.map(|range| range.dates(timezone));
+        Ok(Filters {
+            transaction: TransactionFilters::resolve(database, user, params.filter_params())
+                .await?,
+            date_from: params.date_from.or(range.map(|(from, _)| from)),
+            date_to: params.date_to.or(range.map(|(_, to)| to)),
+        })
+    }
+
+    fn push(&self, query_builder: &mut QueryBuilder<Postgres>) {
+        self.transaction
+            .push(query_builder, "ot")
+            .ge("date", self.date_from)
+            .le("date", self.date_to);
+    }
+}
+
+/// Columns of an [`OrderKey`], oneoff transactions can't be sorted by recurring keys
+fn sort_columns(order_key: &OrderKey) -> Option<Vec<SortColumn>> {
+    let column = match order_key {
+        OrderKey::Time => SortColumn::new("ot.date"),
+        OrderKey::Amount => SortColumn::new("ot.amount"),
+        OrderKey::Category => SortColumn::new("c.name"),
+        OrderKey::Shop => SortColumn::nullable("s.name", "''"),
+        OrderKey::Description => SortColumn::nullable("ot.description", "''"),
+        OrderKey::CreatedAt => SortColumn::new("ot.created_at"),
+        OrderKey::UpdatedAt => SortColumn::new("ot.updated_at"),
+        OrderKey::Frequency | OrderKey::IntervalTo => return None,
+    };
+    Some(vec![column])
+}
+
+impl OneoffTransaction {
+    /// Values of the [`sort_columns`] of this transaction
+    fn sort_values(&self, order_key: &OrderKey) -> Vec<SortValue> {
+        match order_key {
+            OrderKey::Time => vec![SortValue::Date(self.date)],
+            OrderKey::Amount => vec![SortValue::Int(self.amount)],
+            OrderKey::Category => vec![SortValue::Text(self.category.clone())],
+            OrderKey::Shop => vec![
+                SortValue::Bool(self.shop.is_none()),
+                SortValue::Text(self.shop.clone().unwrap_or_default()),
+            ],
+            OrderKey::Description => vec![
+                SortValue::Bool(self.description.is_none()),
+                SortValue::Text(self.description.clone().unwrap_or_default()),
+            ],
+            OrderKey::CreatedAt => vec![SortValue::Timestamp(self.created_at)],
+            OrderKey::UpdatedAt => vec![SortValue::Timestamp(self.updated_at)],
+            OrderKey::Frequency | OrderKey::IntervalTo => vec![],
+        }
+    }
+}
+
 impl Resource for OneoffTransaction {
     type CreateParams = OneoffTransactionCreateParams;
     type FetchParams = OneoffTransactionFetchParams;
     type UpdateParams = OneoffTransactionUpdateParams;
     type ReturnType = OneoffTransaction;
-    type VecReturnType = Vec<OneoffTransaction>;
+    type VecReturnType = Page<OneoffTransaction>;
     type Error = ServerError;
 
     async fn create(
@@ -140,91 +327,68 @@ impl Resource for OneoffTransaction {
     async fn fetch(
         database: &sqlx::PgPool,
         user: &User,
+        config: &Config,
         params: Self::FetchParams,
         pagination: Pagination,
     ) -> Result<Self::VecReturnType, Self::Error> {
-        // "shop?" annotation is not needed in contrast to get_by_id which uses the macro
-        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-            r#"
-                SELECT
-                    ot.*,
-                    c.name as category,
-                    s.name as shop
-                FROM oneoff_transactions ot
-                INNER JOIN categories c ON ot.category_id = c.id
-                LEFT JOIN shops s ON ot.shop_id = s.id
-                WHERE ot.user_id =
-        "#,
-        );
-        query_builder.push_bind(user.id);
-
-        if let Some(value) = params.is_expense {
-            query_builder.push(" AND ot.is_expense = ").push_bind(value);
-        }
-
-        if let Some(date_from) = params.date_from {
-            query_builder.push(" AND ot.date >= ").push_bind(date_from);
-        }
-
-        if let Some(date_to) = params.date_to {
-            query_builder.push(" AND ot.date <= ").push_bind(date_to);
-        }
-
-        if let Some(amount_from) = params.amount_from {
-            query_builder
-                .push(" AND ot.amount >= ")
-                .push_bind(*amount_from);
-        }
-
-        if let Some(amount_to) = params.amount_to {
-            query_builder
-                .push(" AND ot.amount <= ")
-                .push_bind(*amount_to);
-        }
+        let params = match params.view {
+            Some(id) => params.with_view(SavedView::load(database, user, id).await?),
+            None => params,
+        };
+        let settings = UserSettings::get(database, user).await?;
+        let filters =
+            Filters::resolve(database, user, &params, settings.timezone(config.timezone)).await?;
+        let order = params.order.clone().unwrap_or_else(|| {
+            Order::new(
+                params.order_key.clone().unwrap_or(settings.order_key),
+                params.ordering.clone().unwrap_or(settings.ordering),
+            )
+        });
+        let keyset = Keyset::new(order, sort_columns, "ot.id")?;
 
-        if let Some(category_id) = params.category_id {
-            query_builder
-                .push(" AND ot.category_id = ")
-                .push_bind(category_id.validate(user, database).await?);
-        }
+        let mut totals_query = Totals::query(FETCH_FROM, "ot");
+        filters.push(&mut totals_query);
+        let totals: Totals = totals_query.build_query_as().fetch_one(database).await?;
 
-        match ColumnFilter::resolve(params.shop_filter, params.shop_id, "shop")? {
-            ColumnFilter::Any => {}
-            ColumnFilter::Null => {
-                query_builder.push(" AND ot.shop_id IS NULL");
-            }
-            ColumnFilter::Specific(shop_id) => {
-                query_builder
-                    .push(" AND ot.shop_id = ")
-                    .push_bind(shop_id.validate(user, database).await?);
-            }
+        // "shop?" annotation is not needed in contrast to get_by_id which uses the macro
+        let mut query_builder = QueryBuilder::new(format!(
+            "SELECT ot.*, c.name as category, s.name as shop {FETCH_FROM}"
+        ));
+        filters.push(&mut query_builder);
+        if let Some(cursor) = &params.cursor {
+            keyset.push_condition(&mut query_builder, cursor, pagination.limit)?;
         }
+        keyset.push_order_by(&mut query_builder);
 
-        // Handle ordering
-        query_builder.push(" ORDER BY");
-
-        match params.order_key {
-            OrderKey::Time => query_builder.push(format!(" ot.date {}", params.ordering)),
-            OrderKey::Amount => query_builder.push(format!(" ot.amount {}", params.ordering)),
-            OrderKey::Category => query_builder.push(format!(" c.name {}", params.ordering)),
-            OrderKey::Shop => query_builder.push(format!(" s.name {}", params.ordering)),
-        };
-        query_builder.push(format!(", ot.id {}", params.ordering));
-
-        query_builder.push(" LIMIT ").push_bind(pagination.limit.0);
+        // Fetch one more row than requested to know if there is a next page
+        query_builder
+            .push(" LIMIT ")
+            .push_bind(pagination.limit.0 + 1);
         query_builder
             .push(" OFFSET ")
             .push_bind(pagination.offset.0);
 
-        let result: Vec<OneoffTransaction> =
+        let mut items: Vec<OneoffTransaction> =
             query_builder.build_query_as().fetch_all(database).await?;
 
-        Ok(result)
+        let mut next_cursor = None;
+        if items.len() > pagination.limit.0 as usize {
+            items.truncate(pagination.limit.0 as usize);
+            next_cursor = items.last().map(|transaction| {
+                keyset.cursor(|key| transaction.sort_values(key), transaction.id)
+            });
+        }
+
+        Ok(Page {
+            items,
+            meta: totals.into_meta(next_cursor),
+        })
     }
 
     async fn get_by_id(
         database: &sqlx::PgPool,
         user: &User,
+        _config: &Config,
         id: i32,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
         // explicit annotation "shop?" is required to mark the shop column as nullable because sqlx doesn't understand left joins
@@ -250,6 +414,7 @@ impl Resource for OneoffTransaction {
     async fn update(
         database: &sqlx::PgPool,
         user: &User,
+        config: &Config,
         id: i32,
         params: Self::UpdateParams,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
@@ -291,7 +456,34 @@ impl Resource for OneoffTransaction {
 
         // If no fields to update, return instance without changes
         if query_parts.is_empty() {
-            return Self::get_by_id(database, user, id).await;
+            return Self::get_by_id(database, user, config, id).await;
+        }
+
+        // Exact shares add up to the amount they were recorded for, changing it would break the balances
+        if let Some(amount) = params.amount {
+            let has_exact_split = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM transaction_splits ts
+                    INNER JOIN oneoff_transactions ot ON ts.oneoff_transaction_id = ot.id
+                    WHERE ot.user_id = $1 AND ot.id = $2 AND ts.mode = 'exact' AND ot.amount <> $3
+                ) AS "exists!"
+                "#,
+                user.id,
+                id,
+                *amount
+            )
+            .fetch_one(database)
+            .await?;
+            if has_exact_split {
+                return Err(ServerError::Generic(
+                    StatusCode::BAD_REQUEST,
+                    Some(
+                        "The amount of a transaction with an exact split can't be changed, update or remove the split first"
+                            .to_owned(),
+                    ),
+                ));
+            }
         }
 
         // Build the complete SQL query
@@ -342,7 +534,7 @@ impl Resource for OneoffTransaction {
         if result.rows_affected() == 0 {
             Ok(None)
         } else {
-            Self::get_by_id(database, user, id).await
+            Self::get_by_id(database, user, config, id).await
         }
     }
 
@@ -368,7 +560,12 @@ mod tests {
     use crate::{
         app::{
             api::pagination::{Limit, Offset},
-            resources::categories_shops::{Category, Shop},
+            resources::{
+                categories_shops::{Category, Shop},
+                saved_views::SavedViewCreate,
+            },
+            settings::UserSettingsUpdate,
+            transactions::Order,
         },
         users::User,
     };
@@ -415,16 +612,17 @@ mod tests {
             &sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
                 .fetch_one(pool)
                 .await?,
+            &Config::default(),
             OneoffTransactionFetchParams {
-                ordering,
-                order_key,
+                ordering: Some(ordering),
+                order_key: Some(order_key),
                 ..Default::default()
             },
             Pagination::default(),
         )
         .await?;
 
-        Ok(result)
+        Ok(result.items)
     }
 
     mod tests_create {
@@ -616,6 +814,7 @@ mod tests {
             let result = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 Pagination::default(),
             )
@@ -633,6 +832,7 @@ mod tests {
             let result = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 Pagination::default(),
             )
@@ -657,8 +857,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All should be incomes
             assert!(result.iter().all(|t| !t.is_expense));
@@ -676,8 +882,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should be on or after the date_from
             assert!(result.iter().all(|t| t.date >= date_from));
@@ -695,8 +907,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should be on or before the date_to
             assert!(result.iter().all(|t| t.date <= date_to));
@@ -716,8 +934,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should be within the date range
             assert!(
@@ -730,6 +954,60 @@ mod tests {
             Ok(())
         }
 
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_relative_range(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await; // Alice
+            let ids =
+                |page: Page<OneoffTransaction>| -> Vec<i32> { page.iter().map(|t| t.id).collect() };
+
+            for range in [DateRange::LastYear, DateRange::Last12Months] {
+                let (date_from, date_to) = range.dates(Config::default().timezone);
+                let expected = OneoffTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    OneoffTransactionFetchParams {
+                        date_from: Some(date_from),
+                        date_to: Some(date_to),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+                .await?;
+                let result = OneoffTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    OneoffTransactionFetchParams {
+                        range: Some(range),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+                .await?;
+                assert_eq!(ids(result), ids(expected));
+            }
+
+            // Absolute dates take precedence over the range
+            let params = OneoffTransactionFetchParams {
+                range: Some(DateRange::ThisMonth),
+                date_from: NaiveDate::from_ymd_opt(2024, 2, 1),
+                date_to: NaiveDate::from_ymd_opt(2024, 2, 29),
+                ..Default::default()
+            };
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 8);
+
+            Ok(())
+        }
+
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_fetch_with_negative_date_range_no_results(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await; // Alice
@@ -741,8 +1019,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
             assert!(result.is_empty());
 
             Ok(())
@@ -757,8 +1041,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should have amount >= amount_from
             assert!(result.iter().all(|t| t.amount >= *amount_from));
@@ -776,8 +1066,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should have amount <= amount_to
             assert!(result.iter().all(|t| t.amount <= *amount_to));
@@ -797,8 +1093,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should be within the amount range
             assert!(
@@ -822,8 +1124,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
             assert!(result.is_empty());
 
             Ok(())
@@ -838,8 +1146,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should belong to the specified category
             assert!(result.iter().all(|t| t.category_id == category.id));
@@ -856,6 +1170,7 @@ mod tests {
             OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams {
                     category_id: Some(UnvalidatedCategoryId::from(999999)),
                     ..Default::default()
@@ -868,7 +1183,7 @@ mod tests {
             OneoffTransaction::fetch(
             &pool,
             &user,
-            OneoffTransactionFetchParams {
+            &Config::default(), OneoffTransactionFetchParams {
                 category_id: Some(UnvalidatedCategoryId::from(14)),
                 ..Default::default()
             },
@@ -893,7 +1208,13 @@ mod tests {
             };
 
             // Alice trying to filter by Bob's category should fail
-            OneoffTransaction::fetch(&pool, &alice, params, Pagination::default())
+            OneoffTransaction::fetch(
+                &pool,
+                &alice,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
             .await
             .expect_err(
                 "Oneoff transaction fetch with category belonging to different user didn't fail",
@@ -912,8 +1233,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should belong to the specified shop
             assert!(result.iter().all(|t| t.shop_id == Some(shop.id)));
@@ -931,8 +1258,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                OneoffTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should belong to the specified shop
             assert!(result.iter().all(|t| t.shop_id.is_none()));
@@ -949,6 +1282,7 @@ mod tests {
             OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams {
                     shop_filter: Some(FilterMode::Specific),
                     shop_id: Some(UnvalidatedShopId::from(999999)),
@@ -962,6 +1296,7 @@ mod tests {
             OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams {
                     shop_filter: Some(FilterMode::Specific),
                     shop_id: Some(UnvalidatedShopId::from(16)),
@@ -987,11 +1322,17 @@ mod tests {
             };
 
             // Alice trying to filter by Bob's shop should fail
-            OneoffTransaction::fetch(&pool, &alice, params, Pagination::default())
-                .await
-                .expect_err(
-                    "Oneoff transaction fetch with shop belonging to different user didn't fail",
-                );
+            OneoffTransaction::fetch(
+                &pool,
+                &alice,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await
+            .expect_err(
+                "Oneoff transaction fetch with shop belonging to different user didn't fail",
+            );
 
             Ok(())
         }
@@ -1005,6 +1346,7 @@ mod tests {
             OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams {
                     shop_filter: Some(FilterMode::Specific),
                     shop_id: None,
@@ -1019,6 +1361,7 @@ mod tests {
             OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams {
                     shop_filter: None,
                     shop_id: Some(UnvalidatedShopId::from(shop.id)),
@@ -1033,6 +1376,7 @@ mod tests {
             OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams {
                     shop_filter: Some(FilterMode::Null),
                     shop_id: Some(UnvalidatedShopId::from(shop.id)),
@@ -1173,6 +1517,7 @@ mod tests {
             let result = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 Pagination::new(Limit(5), Offset(0)),
             )
@@ -1191,6 +1536,7 @@ mod tests {
             let result1 = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 pagination1,
             )
@@ -1201,6 +1547,7 @@ mod tests {
             let result2 = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 pagination2,
             )
@@ -1228,6 +1575,7 @@ mod tests {
             let result = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 pagination,
             )
@@ -1246,6 +1594,7 @@ mod tests {
             let result = OneoffTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 OneoffTransactionFetchParams::default(),
                 pagination,
             )
@@ -1254,65 +1603,680 @@ mod tests {
             assert_eq!(result.len(), 0);
             Ok(())
         }
-    }
-
-    mod tests_get_by_id {
-        use super::*;
 
         #[sqlx::test(fixtures("base", "oneoff"))]
-        fn test_get_by_id_basic(pool: PgPool) -> anyhow::Result<()> {
+        fn test_fetch_meta(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
 
-            // Get the first transaction from fixtures: Weekly grocery shopping
-            let result = OneoffTransaction::get_by_id(&pool, &user, 1)
-                .await?
-                .expect("Failed to get transaction by id");
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    is_expense: Some(false),
+                    ..Default::default()
+                },
+                Pagination::new(Limit(1), Offset(0)),
+            )
+            .await?;
+            assert_eq!(result.len(), 1);
+            assert_eq!(result.meta.total, 6);
+            assert!(result.meta.next_cursor.is_some());
 
-            assert_eq!(result.id, 1);
-            assert_eq!(result.date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
-            assert!(result.is_expense);
-            assert_eq!(result.amount, 8542);
-            assert_eq!(
-                result.description,
-                Some("Weekly grocery shopping".to_string())
-            );
-            assert_eq!(result.category_id, 1);
-            assert_eq!(result.category, "Groceries"); // From fixtures
-            assert_eq!(result.shop_id, Some(1));
-            assert_eq!(result.shop, Some("Whole Foods".to_string())); // From fixtures
-            assert_eq!(result.user_id, user.id);
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams::default(),
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.meta.total, 22);
+            assert_eq!(result.meta.next_cursor, None);
+
+            let sum = |is_expense: bool| -> i64 {
+                result
+                    .iter()
+                    .filter(|t| t.is_expense == is_expense)
+                    .map(|t| t.amount as i64)
+                    .sum()
+            };
+            let (income, expenses) = (sum(false), sum(true));
+            assert_eq!(result.meta.income, income);
+            assert_eq!(result.meta.expenses, expenses);
+            assert_eq!(result.meta.net, income - expenses);
+
+            // The sums cover all matching rows, not only the page
+            let page = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    is_expense: Some(false),
+                    ..Default::default()
+                },
+                Pagination::new(Limit(1), Offset(0)),
+            )
+            .await?;
+            assert_eq!(page.meta.income, income);
+            assert_eq!(page.meta.expenses, 0);
+            assert_eq!(page.meta.net, income);
 
             Ok(())
         }
 
         #[sqlx::test(fixtures("base", "oneoff"))]
-        fn test_get_by_id_wrong_user(pool: PgPool) -> anyhow::Result<()> {
-            let user1 = get_user_by_id(&pool, 1).await;
-            let user2 = get_user_by_id(&pool, 2).await;
+        fn test_fetch_default_ordering_of_settings(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            UserSettings::update(
+                &pool,
+                &user,
+                UserSettingsUpdate {
+                    ordering: Some(Ordering::Desc),
+                    order_key: Some(OrderKey::Amount),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams::default(),
+                Pagination::default(),
+            )
+            .await?;
+            assert!(result.windows(2).all(|w| w[0].amount >= w[1].amount));
+
+            // Explicit parameters take precedence over the settings
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    ordering: Some(Ordering::Asc),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert!(result.windows(2).all(|w| w[0].amount <= w[1].amount));
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_view(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let groceries = get_category_by_name(&pool, user.id, "Groceries").await;
+            let restaurants = get_category_by_name(&pool, user.id, "Restaurants").await;
+
+            let view = SavedView::create(
+                &pool,
+                &user,
+                SavedViewCreate::new(
+                    "Food",
+                    SavedViewParams {
+                        category_ids: Some(vec![groceries.id, restaurants.id]),
+                        date_from: NaiveDate::from_ymd_opt(2024, 2, 1),
+                        ordering: Some(Ordering::Desc),
+                        ..Default::default()
+                    },
+                ),
+            )
+            .await?
+            .expect("Failed to create view");
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    view: Some(view.id),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 4);
+            assert!(result.iter().all(|t| {
+                (t.category_id == groceries.id || t.category_id == restaurants.id)
+                    && t.date >= NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()
+            }));
+            assert!(result.windows(2).all(|w| w[0].date >= w[1].date));
+
+            // Explicit parameters take precedence over the view
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    view: Some(view.id),
+                    date_from: NaiveDate::from_ymd_opt(2024, 3, 1),
+                    ordering: Some(Ordering::Asc),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 2);
+            assert!(result.windows(2).all(|w| w[0].date <= w[1].date));
+
+            // A range replaces all dates of the view
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    view: Some(view.id),
+                    range: Some(DateRange::ThisMonth),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            let (from, _) = DateRange::ThisMonth.dates(Config::default().timezone);
+            assert!(result.iter().all(|t| t.date >= from));
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_other_users_view(pool: PgPool) -> anyhow::Result<()> {
+            let alice = get_user_by_id(&pool, 1).await;
+            let bob = get_user_by_id(&pool, 2).await;
+
+            let view = SavedView::create(
+                &pool,
+                &bob,
+                SavedViewCreate::new("Everything", SavedViewParams::default()),
+            )
+            .await?
+            .expect("Failed to create view");
+
+            OneoffTransaction::fetch(
+                &pool,
+                &alice,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    view: Some(view.id),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await
+            .expect_err("Oneoff transaction fetch with other user's view didn't fail");
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_cursor_pagination(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            for order_key in [
+                OrderKey::Time,
+                OrderKey::Amount,
+                OrderKey::Category,
+                OrderKey::Shop,
+                OrderKey::Description,
+                OrderKey::CreatedAt,
+            ] {
+                for ordering in [Ordering::Asc, Ordering::Desc] {
+                    let expected = fetch_all_transactions_with_ordering(
+                        &pool,
+                        user.id,
+                        ordering.clone(),
+                        order_key.clone(),
+                    )
+                    .await?;
+
+                    // Walk through all pages, they must match the unpaginated list
+                    let mut ids = Vec::new();
+                    let mut cursor = None;
+                    loop {
+                        let page = OneoffTransaction::fetch(
+                            &pool,
+                            &user,
+                            &Config::default(),
+                            OneoffTransactionFetchParams {
+                                ordering: Some(ordering.clone()),
+                                order_key: Some(order_key.clone()),
+                                cursor,
+                                ..Default::default()
+                            },
+                            Pagination::new(Limit(3), Offset(0)),
+                        )
+                        .await?;
+                        assert_eq!(page.meta.total, 22);
+                        ids.extend(page.iter().map(|t| t.id));
+                        cursor = page.meta.next_cursor;
+                        if cursor.is_none() {
+                            break;
+                        }
+                    }
+
+                    let expected: Vec<i32> = expected.iter().map(|t| t.id).collect();
+                    assert_eq!(ids, expected, "{order_key:?} {ordering}");
+                }
+            }
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_multiple_order_keys(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let order: Order = "category:asc,shop:desc:nullsLast,amount:desc"
+                .parse()
+                .map_err(anyhow::Error::msg)?;
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    order: Some(order.clone()),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 22);
+            let mut expected = result.items.clone();
+            expected.sort_by(|a, b| {
+                a.category
+                    .cmp(&b.category)
+                    .then_with(|| b.shop.is_some().cmp(&a.shop.is_some()))
+                    .then_with(|| b.shop.cmp(&a.shop))
+                    .then_with(|| b.amount.cmp(&a.amount))
+                    .then_with(|| a.id.cmp(&b.id))
+            });
+            assert_eq!(result.items, expected);
+
+            // Walk through all pages, they must match the unpaginated list
+            let mut ids = Vec::new();
+            let mut cursor = None;
+            loop {
+                let page = OneoffTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    OneoffTransactionFetchParams {
+                        order: Some(order.clone()),
+                        cursor,
+                        ..Default::default()
+                    },
+                    Pagination::new(Limit(4), Offset(0)),
+                )
+                .await?;
+                ids.extend(page.iter().map(|t| t.id));
+                cursor = page.meta.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+            assert_eq!(ids, expected.iter().map(|t| t.id).collect::<Vec<i32>>());
+
+            // `order` takes precedence over `ordering` and `orderKey`
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    order: Some("amount:desc".parse().map_err(anyhow::Error::msg)?),
+                    ordering: Some(Ordering::Asc),
+                    order_key: Some(OrderKey::Time),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert!(result.windows(2).all(|w| w[0].amount >= w[1].amount));
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_recurring_order_key_rejected(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    order: Some("amount,intervalTo".parse().map_err(anyhow::Error::msg)?),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await
+            .expect_err("Oneoff transactions were ordered by the interval end");
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_cursor_of_other_ordering(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            let page = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams::default(),
+                Pagination::new(Limit(3), Offset(0)),
+            )
+            .await?;
+
+            OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    order_key: Some(OrderKey::Amount),
+                    cursor: page.meta.next_cursor,
+                    ..Default::default()
+                },
+                Pagination::new(Limit(3), Offset(0)),
+            )
+            .await
+            .expect_err("Oneoff transaction fetch with cursor of another ordering didn't fail");
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_cursor_with_zero_limit(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            let page = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams::default(),
+                Pagination::new(Limit(3), Offset(0)),
+            )
+            .await?;
+
+            OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    cursor: page.meta.next_cursor,
+                    ..Default::default()
+                },
+                Pagination::new(Limit(0), Offset(0)),
+            )
+            .await
+            .expect_err("Oneoff transaction fetch with cursor and limit 0 didn't fail");
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_category_ids(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let groceries = get_category_by_name(&pool, user.id, "Groceries").await;
+            let restaurants = get_category_by_name(&pool, user.id, "Restaurants").await;
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    category_ids: Some(IdList::from(vec![groceries.id, restaurants.id])),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+
+            assert_eq!(result.len(), 6);
+            assert!(
+                result
+                    .iter()
+                    .all(|t| t.category_id == groceries.id || t.category_id == restaurants.id)
+            );
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_exclude_category_ids(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let groceries = get_category_by_name(&pool, user.id, "Groceries").await;
+            let restaurants = get_category_by_name(&pool, user.id, "Restaurants").await;
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    exclude_category_ids: Some(IdList::from(vec![groceries.id, restaurants.id])),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+
+            assert_eq!(result.len(), 16);
+            assert!(
+                result
+                    .iter()
+                    .all(|t| t.category_id != groceries.id && t.category_id != restaurants.id)
+            );
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_shop_ids(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let whole_foods = get_shop_by_name(&pool, user.id, "Whole Foods").await;
+            let starbucks = get_shop_by_name(&pool, user.id, "Starbucks").await;
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    shop_ids: Some(IdList::from(vec![whole_foods.id, starbucks.id])),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+
+            assert_eq!(result.len(), 4);
+            assert!(
+                result
+                    .iter()
+                    .all(|t| t.shop_id == Some(whole_foods.id) || t.shop_id == Some(starbucks.id))
+            );
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_exclude_shop_ids_keeps_no_shop(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let whole_foods = get_shop_by_name(&pool, user.id, "Whole Foods").await;
+
+            let result = OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    exclude_shop_ids: Some(IdList::from(vec![whole_foods.id])),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+
+            assert_eq!(result.len(), 20);
+            assert!(result.iter().all(|t| t.shop_id != Some(whole_foods.id)));
+            assert!(result.iter().any(|t| t.shop_id.is_none()));
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_other_users_category_ids(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let groceries = get_category_by_name(&pool, user.id, "Groceries").await;
+            let bob_category = get_category_by_name(&pool, 2, "Food").await;
+
+            OneoffTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                OneoffTransactionFetchParams {
+                    exclude_category_ids: Some(IdList::from(vec![groceries.id, bob_category.id])),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await
+            .expect_err("Oneoff transaction fetch with other user's category id didn't fail");
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_description_contains(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            let config = Config::default();
+            let fetch = |text: &str| {
+                OneoffTransaction::fetch(
+                    &pool,
+                    &user,
+                    &config,
+                    OneoffTransactionFetchParams {
+                        description_contains: Some(text.to_owned()),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+            };
+
+            let result = fetch("GROCERY").await?;
+            assert_eq!(result.len(), 2);
+            assert!(result.iter().all(|t| {
+                t.description
+                    .as_ref()
+                    .is_some_and(|d| d.to_lowercase().contains("grocery"))
+            }));
+
+            // Wildcards of ILIKE are matched literally
+            assert_eq!(fetch("%").await?.len(), 0);
+            assert_eq!(fetch("_").await?.len(), 0);
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_fetch_with_description_is_null(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let category = get_category_by_name(&pool, user.id, "Groceries").await;
+            let created = OneoffTransaction::create(
+                &pool,
+                &user,
+                OneoffTransactionCreateParams {
+                    date: NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+                    is_expense: true,
+                    amount: Amount(100),
+                    description: None,
+                    category_id: UnvalidatedCategoryId::from(category.id),
+                    shop_id: None,
+                },
+            )
+            .await?
+            .expect("Failed to create oneoff transaction");
+
+            let config = Config::default();
+            let fetch = |is_null: bool| {
+                OneoffTransaction::fetch(
+                    &pool,
+                    &user,
+                    &config,
+                    OneoffTransactionFetchParams {
+                        description_is_null: Some(is_null),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+            };
+
+            let result = fetch(true).await?;
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].id, created.id);
+            assert_eq!(fetch(false).await?.len(), 22);
+
+            Ok(())
+        }
+    }
+
+    mod tests_get_by_id {
+        use super::*;
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_get_by_id_basic(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            // Get the first transaction from fixtures: Weekly grocery shopping
+            let result = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
+                .await?
+                .expect("Failed to get transaction by id");
+
+            assert_eq!(result.id, 1);
+            assert_eq!(result.date, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+            assert!(result.is_expense);
+            assert_eq!(result.amount, 8542);
+            assert_eq!(
+                result.description,
+                Some("Weekly grocery shopping".to_string())
+            );
+            assert_eq!(result.category_id, 1);
+            assert_eq!(result.category, "Groceries"); // From fixtures
+            assert_eq!(result.shop_id, Some(1));
+            assert_eq!(result.shop, Some("Whole Foods".to_string())); // From fixtures
+            assert_eq!(result.user_id, user.id);
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "oneoff"))]
+        fn test_get_by_id_wrong_user(pool: PgPool) -> anyhow::Result<()> {
+            let user1 = get_user_by_id(&pool, 1).await;
+            let user2 = get_user_by_id(&pool, 2).await;
 
             // Transaction ID 1 belongs to user 1, try to access it as user 2
-            let result = OneoffTransaction::get_by_id(&pool, &user2, 1).await?;
+            let result = OneoffTransaction::get_by_id(&pool, &user2, &Config::default(), 1).await?;
             assert!(
                 result.is_none(),
                 "Should not be able to access another user's transaction"
             );
 
             // Verify that user 1 can still access their own transaction
-            let result = OneoffTransaction::get_by_id(&pool, &user1, 1).await?;
+            let result = OneoffTransaction::get_by_id(&pool, &user1, &Config::default(), 1).await?;
             assert!(
                 result.is_some(),
                 "User 1 should be able to access their own transaction"
             );
 
             // Test accessing user 2's transaction as user 1
-            let result = OneoffTransaction::get_by_id(&pool, &user1, 20).await?;
+            let result =
+                OneoffTransaction::get_by_id(&pool, &user1, &Config::default(), 20).await?;
             assert!(
                 result.is_none(),
                 "User 1 should not be able to access user 2's transaction"
             );
 
             // Verify that user 2 can access their own transaction
-            let result = OneoffTransaction::get_by_id(&pool, &user2, 20).await?;
+            let result =
+                OneoffTransaction::get_by_id(&pool, &user2, &Config::default(), 20).await?;
             assert!(
                 result.is_some(),
                 "User 2 should be able to access their own transaction"
@@ -1326,7 +2290,8 @@ mod tests {
             let user = get_user_by_id(&pool, 1).await;
 
             // Try to get a transaction with an ID that doesn't exist
-            let result = OneoffTransaction::get_by_id(&pool, &user, 99999).await?;
+            let result =
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 99999).await?;
 
             assert!(
                 result.is_none(),
@@ -1343,13 +2308,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_nothing(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams::default(),
             )
@@ -1365,13 +2331,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_date(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     date: Some(NaiveDate::from_ymd_opt(2030, 1, 1).unwrap()),
@@ -1390,7 +2357,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1402,13 +2369,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_is_expense(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     is_expense: Some(false),
@@ -1427,7 +2395,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1439,13 +2407,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_amount(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     amount: Some(Amount(10000)),
@@ -1464,7 +2433,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1473,16 +2442,66 @@ mod tests {
             Ok(())
         }
 
+        #[sqlx::test(fixtures("base", "oneoff", "members"))]
+        fn test_update_amount_with_exact_split(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            // Transaction 1 has an amount of 8542
+            sqlx::query!(
+                "INSERT INTO transaction_splits (oneoff_transaction_id, paid_by_member_id, mode) VALUES (1, 1, 'exact')"
+            )
+            .execute(&pool)
+            .await?;
+            sqlx::query!(
+                "INSERT INTO transaction_split_shares (oneoff_transaction_id, member_id, value) VALUES (1, 1, 4000), (1, 2, 4542)"
+            )
+            .execute(&pool)
+            .await?;
+
+            let result = OneoffTransaction::update(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                OneoffTransactionUpdateParams {
+                    amount: Some(Amount(10000)),
+                    ..Default::default()
+                },
+            )
+            .await;
+            assert!(matches!(
+                result,
+                Err(ServerError::Generic(StatusCode::BAD_REQUEST, _))
+            ));
+
+            // Keeping the amount is fine
+            OneoffTransaction::update(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                OneoffTransactionUpdateParams {
+                    amount: Some(Amount(8542)),
+                    is_expense: Some(false),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .expect("Should return updated instance");
+
+            Ok(())
+        }
+
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_description_some(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     description: TriState::Defined(Some(Description(
@@ -1503,7 +2522,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1515,13 +2534,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_description_none(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     description: TriState::Defined(None),
@@ -1540,7 +2560,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1552,13 +2572,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_valid_category_id(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     category_id: Some(UnvalidatedCategoryId::from(4)),
@@ -1578,7 +2599,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1594,6 +2615,7 @@ mod tests {
             OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     category_id: Some(UnvalidatedCategoryId::from(999999)),
@@ -1606,6 +2628,7 @@ mod tests {
             OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     category_id: Some(UnvalidatedCategoryId::from(14)),
@@ -1621,13 +2644,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_valid_shop_id_some(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     shop_id: TriState::Defined(Some(UnvalidatedShopId::from(4))),
@@ -1647,7 +2671,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1659,13 +2683,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "oneoff"))]
         fn test_update_valid_shop_id_none(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = OneoffTransaction::get_by_id(&pool, &user, 1)
+            let instance = OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     shop_id: TriState::Defined(None),
@@ -1685,7 +2710,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                OneoffTransaction::get_by_id(&pool, &user, 1)
+                OneoffTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1701,6 +2726,7 @@ mod tests {
             OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     shop_id: TriState::Defined(Some(UnvalidatedShopId::from(999999))),
@@ -1713,6 +2739,7 @@ mod tests {
             OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     shop_id: TriState::Defined(Some(UnvalidatedShopId::from(16))),
@@ -1732,6 +2759,7 @@ mod tests {
             let instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     date: Some(NaiveDate::from_ymd_opt(2030, 1, 1).unwrap()),
@@ -1781,6 +2809,7 @@ mod tests {
             let updated_instance = OneoffTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 OneoffTransactionUpdateParams {
                     amount: Some(Amount(10000)),
@@ -1810,6 +2839,7 @@ mod tests {
                 OneoffTransaction::update(
                     &pool,
                     &user,
+                    &Config::default(),
                     user_2_transaction.id,
                     OneoffTransactionUpdateParams {
                         description: TriState::Defined(Some(Description(
@@ -1851,6 +2881,7 @@ mod tests {
                 OneoffTransaction::update(
                     &pool,
                     &user,
+                    &Config::default(),
                     999999,
                     OneoffTransactionUpdateParams {
                         description: TriState::Defined(Some(Description(
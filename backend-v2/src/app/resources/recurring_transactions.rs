@@ -1,33 +1,53 @@
-use axum::http::StatusCode;
-use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use std::collections::HashMap;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post, put},
+};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
 use garde::Validate;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::{Postgres, QueryBuilder, prelude::FromRow};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
     app::{
+        AppState,
         api::{
-            column_filter::{ColumnFilter, FilterMode},
+            AuthUser, ValidJson, ValidQuery,
+            column_filter::FilterMode,
+            pagination::{Cursor, Page, SortValue},
             tri_state_field::TriState,
         },
+        config::Config,
         resources::{
             Resource,
             recurring_transactions::{
-                recurrence::{Recurrence, RecurrenceFrequency},
+                recurrence::{RawRecurrence, Recurrence, RecurrenceFrequency},
                 year_month::YearMonth,
             },
         },
+        settings::UserSettings,
         transactions::{
-            Amount, Description, OrderKey, Ordering, UnvalidatedCategoryId, UnvalidatedShopId,
+            Amount, Description, Keyset, Order, OrderKey, Ordering, SortColumn, Totals,
+            UnvalidatedCategoryId, UnvalidatedShopId,
+            date_range::DateRange,
+            filters::{IdList, TransactionFilterParams, TransactionFilters},
         },
     },
     errors::ServerError,
+    users::User,
 };
 
-mod recurrence;
-mod year_month;
+pub mod recurrence;
+pub mod year_month;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RecurringTransaction {
     id: i32,
@@ -36,7 +56,14 @@ pub struct RecurringTransaction {
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     is_expense: bool,
+    /// Amount that applies today
     amount: i32,
+    /// Amount before the first revision, set by `amount` on create and update
+    initial_amount: i32,
+    /// Changes of the amount, ordered by `effectiveFrom`
+    amount_revisions: Vec<AmountRevision>,
+    /// Skipped and changed occurrences, ordered by `month`
+    exceptions: Vec<OccurrenceException>,
     description: Option<String>,
     category_id: i32,
     category: String,
@@ -44,26 +71,37 @@ pub struct RecurringTransaction {
     shop: Option<String>,
 }
 
+/// An amount that applies from the first day of `effectiveFrom` on, until the next revision
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AmountRevision {
+    effective_from: YearMonth,
+    amount: i32,
+}
+
+/// Exception of the occurrences in `month`. Skipped occurrences don't take place, all others use the `amount` and
+/// `description` of the exception where set.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OccurrenceException {
+    month: YearMonth,
+    skip: bool,
+    amount: Option<i32>,
+    description: Option<String>,
+}
+
 impl TryFrom<RawRecurringTransaction> for RecurringTransaction {
     type Error = garde::Report;
 
     fn try_from(value: RawRecurringTransaction) -> Result<Self, garde::Report> {
-        let recurrence = match value.frequency {
-            RecurrenceFrequency::Monthly => Recurrence::Monthly {
-                month_from: YearMonth::new(
-                    value.interval_from.year(),
-                    value.interval_from.month0() + 1,
-                )?,
-                month_to: match value.interval_to {
-                    Some(val) => Some(YearMonth::new(val.year(), val.month0() + 1)?),
-                    None => None,
-                },
-            },
-            RecurrenceFrequency::Yearly => Recurrence::Yearly {
-                year_from: value.interval_from.year(),
-                year_to: value.interval_to.map(|val| val.year()),
-            },
-        };
+        let recurrence = Recurrence::try_from(RawRecurrence {
+            frequency: value.frequency.clone(),
+            interval_from: value.interval_from,
+            interval_to: value.interval_to,
+            interval_months: value.interval_months,
+            day_of_month: value.day_of_month,
+            month_of_year: value.month_of_year,
+        })?;
 
         let RawRecurringTransaction {
             id,
@@ -72,6 +110,7 @@ impl TryFrom<RawRecurringTransaction> for RecurringTransaction {
             updated_at,
             is_expense,
             amount,
+            initial_amount,
             description,
             category_id,
             category,
@@ -88,6 +127,9 @@ impl TryFrom<RawRecurringTransaction> for RecurringTransaction {
             updated_at,
             is_expense,
             amount,
+            initial_amount,
+            amount_revisions: Vec::new(),
+            exceptions: Vec::new(),
             description,
             category_id,
             category,
@@ -97,17 +139,110 @@ impl TryFrom<RawRecurringTransaction> for RecurringTransaction {
     }
 }
 
+impl RecurringTransaction {
+    /// Amount that applies on the date
+    fn amount_on(&self, date: NaiveDate) -> i32 {
+        self.amount_revisions
+            .iter()
+            .rev()
+            .find(|revision| revision.effective_from.to_naive_date() <= date)
+            .map_or(self.initial_amount, |revision| revision.amount)
+    }
+
+    /// Exception of the occurrences in the period of the date, i.e. its year for yearly transactions and its month for
+    /// all others. This is the same rule as the one of the SQL function `recurring_transaction_period_amount`.
+    fn exception_on(&self, date: NaiveDate) -> Option<&OccurrenceException> {
+        let yearly = matches!(self.recurrence, Recurrence::Yearly { .. });
+        self.exceptions.iter().find(|exception| {
+            let month = exception.month.to_naive_date();
+            month.year() == date.year() && (yearly || month.month() == date.month())
+        })
+    }
+}
+
+/// Load the [`AmountRevision`]s and [`OccurrenceException`]s of the transactions
+async fn load_details(
+    database: &sqlx::PgPool,
+    transactions: &mut [RecurringTransaction],
+) -> Result<(), ServerError> {
+    let ids: Vec<i32> = transactions
+        .iter()
+        .map(|transaction| transaction.id)
+        .collect();
+    let rows = sqlx::query!(
+        r#"
+        SELECT recurring_transaction_id, effective_from, amount
+        FROM recurring_transaction_amounts
+        WHERE recurring_transaction_id = ANY($1)
+        ORDER BY recurring_transaction_id, effective_from
+        "#,
+        &ids
+    )
+    .fetch_all(database)
+    .await?;
+
+    let mut revisions: HashMap<i32, Vec<AmountRevision>> = HashMap::new();
+    for row in rows {
+        revisions
+            .entry(row.recurring_transaction_id)
+            .or_default()
+            .push(AmountRevision {
+                effective_from: YearMonth::new(
+                    row.effective_from.year(),
+                    row.effective_from.month(),
+                )?,
+                amount: row.amount,
+            });
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT recurring_transaction_id, month, skip, amount, description
+        FROM recurring_transaction_exceptions
+        WHERE recurring_transaction_id = ANY($1)
+        ORDER BY recurring_transaction_id, month
+        "#,
+        &ids
+    )
+    .fetch_all(database)
+    .await?;
+
+    let mut exceptions: HashMap<i32, Vec<OccurrenceException>> = HashMap::new();
+    for row in rows {
+        exceptions
+            .entry(row.recurring_transaction_id)
+            .or_default()
+            .push(OccurrenceException {
+                month: YearMonth::new(row.month.year(), row.month.month())?,
+                skip: row.skip,
+                amount: row.amount,
+                description: row.description,
+            });
+    }
+
+    for transaction in transactions {
+        transaction.amount_revisions = revisions.remove(&transaction.id).unwrap_or_default();
+        transaction.exceptions = exceptions.remove(&transaction.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
 #[derive(FromRow)]
 struct RawRecurringTransaction {
     id: i32,
     frequency: RecurrenceFrequency,
     interval_from: NaiveDate,
     interval_to: Option<NaiveDate>,
+    interval_months: Option<i32>,
+    day_of_month: Option<i16>,
+    month_of_year: Option<i16>,
     user_id: i32,
     created_at: DateTime<Utc>,
     updated_at: DateTime<Utc>,
     is_expense: bool,
     amount: i32,
+    initial_amount: i32,
     description: Option<String>,
     category_id: i32,
     category: String,
@@ -115,7 +250,7 @@ struct RawRecurringTransaction {
     shop: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Validate)]
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RecurringTransactionCreateParams {
     #[garde(dive)]
@@ -125,6 +260,7 @@ pub struct RecurringTransactionCreateParams {
     #[garde(dive)]
     amount: Amount,
     #[garde(length(graphemes, min = 1))]
+    #[schema(min_length = 1)]
     description: Option<String>,
     #[garde(dive)]
     category_id: UnvalidatedCategoryId,
@@ -132,15 +268,22 @@ pub struct RecurringTransactionCreateParams {
     shop_id: Option<UnvalidatedShopId>,
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Validate)]
+#[derive(Debug, Clone, Default, Deserialize, Validate, IntoParams)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
 pub struct RecurringTransactionFetchParams {
     #[garde(skip)]
+    #[param(inline)]
     frequency: Option<RecurrenceFrequency>,
     #[garde(dive)]
     interval_ends_ge: Option<YearMonth>,
     #[garde(dive)]
     interval_starts_le: Option<YearMonth>,
+    /// Only transactions active at some point of this relative date range in the timezone of the user.
+    /// `intervalEndsGe` and `intervalStartsLe` take precedence.
+    #[garde(skip)]
+    #[param(inline)]
+    range: Option<DateRange>,
     #[garde(skip)]
     is_terminating: Option<bool>,
     #[garde(skip)]
@@ -151,19 +294,71 @@ pub struct RecurringTransactionFetchParams {
     amount_to: Option<Amount>,
     #[garde(dive)]
     category_id: Option<UnvalidatedCategoryId>,
+    /// Comma separated, only transactions of one of these categories
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    category_ids: Option<IdList>,
+    /// Comma separated, no transactions of these categories
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    exclude_category_ids: Option<IdList>,
     #[garde(skip)]
+    #[param(inline)]
     shop_filter: Option<FilterMode>,
     #[garde(dive)]
     shop_id: Option<UnvalidatedShopId>,
-    #[serde(default)]
+    /// Comma separated, only transactions of one of these shops
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    shop_ids: Option<IdList>,
+    /// Comma separated, no transactions of these shops. Transactions without shop are kept.
+    #[garde(dive)]
+    #[param(value_type = Option<Vec<i32>>, style = Form, explode = false)]
+    exclude_shop_ids: Option<IdList>,
+    /// Only transactions whose description contains this text, ignoring case
+    #[garde(length(min = 1))]
+    description_contains: Option<String>,
+    /// `true` only returns transactions without description, `false` only those with one
     #[garde(skip)]
-    ordering: Ordering,
-    #[serde(default)]
+    description_is_null: Option<bool>,
+    /// Sort keys with their own directions like `frequency:asc,intervalTo:desc:nullsLast`, takes precedence over
+    /// `ordering` and `orderKey`
     #[garde(skip)]
-    order_key: OrderKey,
+    #[param(value_type = Option<String>, example = "frequency:asc,intervalTo:desc:nullsLast")]
+    order: Option<Order>,
+    /// Defaults to the `ordering` of the user settings
+    #[garde(skip)]
+    #[param(inline)]
+    ordering: Option<Ordering>,
+    /// Defaults to the `orderKey` of the user settings
+    #[garde(skip)]
+    #[param(inline)]
+    order_key: Option<OrderKey>,
+    /// `nextCursor` of the previous page, continues after its last row
+    #[garde(skip)]
+    cursor: Option<Cursor>,
+}
+
+impl RecurringTransactionFetchParams {
+    fn filter_params(&self) -> TransactionFilterParams {
+        TransactionFilterParams {
+            is_expense: self.is_expense,
+            amount_from: self.amount_from,
+            amount_to: self.amount_to,
+            category_id: self.category_id.clone(),
+            category_ids: self.category_ids.clone(),
+            exclude_category_ids: self.exclude_category_ids.clone(),
+            shop_filter: self.shop_filter,
+            shop_id: self.shop_id.clone(),
+            shop_ids: self.shop_ids.clone(),
+            exclude_shop_ids: self.exclude_shop_ids.clone(),
+            description_contains: self.description_contains.clone(),
+            description_is_null: self.description_is_null,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Validate)]
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct RecurringTransactionUpdateParams {
     #[garde(dive)]
@@ -172,22 +367,147 @@ pub struct RecurringTransactionUpdateParams {
     is_expense: Option<bool>,
     #[garde(dive)]
     amount: Option<Amount>,
+    /// `null` removes the description
     #[serde(default)]
     #[garde(dive)]
+    #[schema(value_type = Option<Description>)]
     description: TriState<Description>,
     #[garde(dive)]
     category_id: Option<UnvalidatedCategoryId>,
+    /// `null` removes the shop
     #[serde(default)]
     #[garde(dive)]
+    #[schema(value_type = Option<UnvalidatedShopId>)]
     shop_id: TriState<UnvalidatedShopId>,
 }
 
+/// `FROM` clause of lists. The `amount` of `rt` is the amount that applies on `today`, which is formatted by chrono.
+/// `period_amount` additionally respects the exception of the current month, or year for yearly transactions.
+fn fetch_from(today: NaiveDate) -> String {
+    format!(
+        r#"
+    FROM (
+        SELECT
+            r.id, r.frequency, r.interval_from, r.interval_to, r.interval_months, r.day_of_month, r.month_of_year,
+            r.user_id, r.created_at, r.updated_at, r.is_expense, r.description, r.category_id, r.shop_id,
+            r.amount AS initial_amount,
+            recurring_transaction_amount(r.id, r.amount, DATE '{today}') AS amount,
+            recurring_transaction_period_amount(
+                r.id, r.frequency, recurring_transaction_amount(r.id, r.amount, DATE '{today}'), DATE '{today}'
+            ) AS period_amount
+        FROM recurring_transactions r
+    ) rt
+    INNER JOIN categories c ON rt.category_id = c.id
+    LEFT JOIN shops s ON rt.shop_id = s.id
+"#
+    )
+}
+
+/// Filters of [`RecurringTransactionFetchParams`], with ids checked to belong to the user
+struct Filters {
+    transaction: TransactionFilters,
+    frequency: Option<RecurrenceFrequency>,
+    interval_ends_ge: Option<NaiveDate>,
+    interval_starts_le: Option<NaiveDate>,
+    is_terminating: Option<bool>,
+}
+
+impl Filters {
+    async fn resolve(
+        database: &sqlx::PgPool,
+        user: &crate::users::User,
+        params: &RecurringTransactionFetchParams,
+        timezone: Tz,
+    ) -> Result<Self, ServerError> {
+        let range = params.range.map(|range| range.dates(timezone));
+        Ok(Filters {
+            transaction: TransactionFilters::resolve(database, user, params.filter_params())
+                .await?,
+            frequency: params.frequency.clone(),
+            // Month based intervals are stored as first days of months, so the range start is rounded down to its
+            // month. Weekly intervals may start on any day of the last month.
+            interval_ends_ge: params
+                .interval_ends_ge
+                .as_ref()
+                .map(YearMonth::to_naive_date)
+                .or(range.and_then(|(from, _)| from.with_day(1))),
+            interval_starts_le: params
+                .interval_starts_le
+                .as_ref()
+                .map(YearMonth::last_day)
+                .or(range.map(|(_, to)| to)),
+            is_terminating: params.is_terminating,
+        })
+    }
+
+    fn push(&self, query_builder: &mut QueryBuilder<Postgres>) {
+        self.transaction
+            .push(query_builder, "rt")
+            .eq("frequency", self.frequency.clone())
+            // Transactions without end never end before the given month
+            .ge_or_null("interval_to", self.interval_ends_ge)
+            .le("interval_from", self.interval_starts_le)
+            .is_null(
+                "interval_to",
+                self.is_terminating.map(|is_terminating| !is_terminating),
+            );
+    }
+}
+
+/// Columns of an [`OrderKey`]. Time sorts by the start and then by the end of the interval.
+fn sort_columns(order_key: &OrderKey) -> Option<Vec<SortColumn>> {
+    let interval_to = SortColumn::nullable("rt.interval_to", "rt.interval_from");
+    let columns = match order_key {
+        OrderKey::Time => vec![SortColumn::new("rt.interval_from"), interval_to],
+        OrderKey::Amount => vec![SortColumn::new("rt.amount")],
+        OrderKey::Category => vec![SortColumn::new("c.name")],
+        OrderKey::Shop => vec![SortColumn::nullable("s.name", "''")],
+        OrderKey::Description => vec![SortColumn::nullable("rt.description", "''")],
+        OrderKey::CreatedAt => vec![SortColumn::new("rt.created_at")],
+        OrderKey::UpdatedAt => vec![SortColumn::new("rt.updated_at")],
+        OrderKey::Frequency => vec![SortColumn::new("rt.frequency")],
+        OrderKey::IntervalTo => vec![interval_to],
+    };
+    Some(columns)
+}
+
+impl RawRecurringTransaction {
+    /// Values of the [`sort_columns`] of this transaction
+    fn sort_values(&self, order_key: &OrderKey) -> Vec<SortValue> {
+        let interval_to = [
+            SortValue::Bool(self.interval_to.is_none()),
+            SortValue::Date(self.interval_to.unwrap_or(self.interval_from)),
+        ];
+        match order_key {
+            OrderKey::Time => [
+                vec![SortValue::Date(self.interval_from)],
+                interval_to.to_vec(),
+            ]
+            .concat(),
+            OrderKey::Amount => vec![SortValue::Int(self.amount)],
+            OrderKey::Category => vec![SortValue::Text(self.category.clone())],
+            OrderKey::Shop => vec![
+                SortValue::Bool(self.shop.is_none()),
+                SortValue::Text(self.shop.clone().unwrap_or_default()),
+            ],
+            OrderKey::Description => vec![
+                SortValue::Bool(self.description.is_none()),
+                SortValue::Text(self.description.clone().unwrap_or_default()),
+            ],
+            OrderKey::CreatedAt => vec![SortValue::Timestamp(self.created_at)],
+            OrderKey::UpdatedAt => vec![SortValue::Timestamp(self.updated_at)],
+            OrderKey::Frequency => vec![SortValue::Frequency(self.frequency.clone())],
+            OrderKey::IntervalTo => interval_to.to_vec(),
+        }
+    }
+}
+
 impl Resource for RecurringTransaction {
     type CreateParams = RecurringTransactionCreateParams;
     type FetchParams = RecurringTransactionFetchParams;
     type UpdateParams = RecurringTransactionUpdateParams;
     type ReturnType = RecurringTransaction;
-    type VecReturnType = Vec<RecurringTransaction>;
+    type VecReturnType = Page<RecurringTransaction>;
     type Error = ServerError;
 
     async fn create(
@@ -195,55 +515,32 @@ impl Resource for RecurringTransaction {
         user: &crate::users::User,
         params: Self::CreateParams,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
-        let recurrence_frequency;
-        let interval_from;
-        let interval_to;
-        match params.recurrence {
-            Recurrence::Yearly { year_from, year_to } => {
-                recurrence_frequency = RecurrenceFrequency::Yearly;
-                interval_from = NaiveDate::from_ymd_opt(year_from, 1, 1)
-                    .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
-                interval_to = year_to
-                    .map(|value| {
-                        NaiveDate::from_ymd_opt(value, 1, 1)
-                            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
-                    })
-                    .transpose()?;
-            }
-            Recurrence::Monthly {
-                month_from,
-                month_to,
-            } => {
-                recurrence_frequency = RecurrenceFrequency::Monthly;
-                interval_from = NaiveDate::from_ymd_opt(month_from.year, month_from.month, 1)
-                    .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
-                interval_to = month_to
-                    .map(|value| {
-                        NaiveDate::from_ymd_opt(value.year, value.month, 1)
-                            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
-                    })
-                    .transpose()?;
-            }
-        }
+        let recurrence = params
+            .recurrence
+            .to_raw()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
         let result = sqlx::query_as!(
             RawRecurringTransaction,
             r#"
             WITH insert AS (
                 INSERT INTO recurring_transactions
-                (user_id, frequency, interval_from, interval_to, is_expense, amount, description, category_id, shop_id)
+                (user_id, frequency, interval_from, interval_to, interval_months, day_of_month, month_of_year, is_expense, amount, description, category_id, shop_id)
                 VALUES
-                ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                 RETURNING *
             )
-            SELECT rt.id, rt.interval_from, rt.interval_to, rt.created_at, rt.updated_at, rt.user_id, rt.is_expense, rt.amount, rt.description, rt.category_id, rt.shop_id, rt.frequency as "frequency: RecurrenceFrequency", c.name as category, s.name as "shop?"
+            SELECT rt.id, rt.interval_from, rt.interval_to, rt.interval_months, rt.day_of_month, rt.month_of_year, rt.created_at, rt.updated_at, rt.user_id, rt.is_expense, rt.amount, rt.amount as initial_amount, rt.description, rt.category_id, rt.shop_id, rt.frequency as "frequency: RecurrenceFrequency", c.name as category, s.name as "shop?"
             FROM insert rt
             INNER JOIN categories c on rt.category_id = c.id
             LEFT JOIN shops s on rt.shop_id = s.id
             "#,
             user.id,
-            recurrence_frequency as _,
-            interval_from,
-            interval_to,
+            recurrence.frequency as _,
+            recurrence.interval_from,
+            recurrence.interval_to,
+            recurrence.interval_months,
+            recurrence.day_of_month,
+            recurrence.month_of_year,
             params.is_expense,
             *params.amount,
             params.description,
@@ -263,149 +560,103 @@ impl Resource for RecurringTransaction {
     async fn fetch(
         database: &sqlx::PgPool,
         user: &crate::users::User,
+        config: &Config,
         params: Self::FetchParams,
         pagination: crate::app::api::pagination::Pagination,
     ) -> Result<Self::VecReturnType, Self::Error> {
-        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-            r#"
-                SELECT
-                    rt.*,
-                    c.name as category,
-                    s.name as shop
-                FROM recurring_transactions rt
-                INNER JOIN categories c ON rt.category_id = c.id
-                LEFT JOIN shops s ON rt.shop_id = s.id
-                WHERE rt.user_id =
-        "#,
-        );
-        query_builder.push_bind(user.id);
-
-        if let Some(is_expense) = params.is_expense {
-            query_builder
-                .push(" AND rt.is_expense = ")
-                .push_bind(is_expense);
-        }
-
-        if let Some(frequency) = params.frequency {
-            query_builder
-                .push(" AND rt.frequency = ")
-                .push_bind(frequency);
-        }
-
-        if let Some(ends_after) = params.interval_ends_ge {
-            // If some, all transaction intervals must end after the given value. This includes the case interval_to = NULL
-            query_builder
-                .push(" AND (rt.interval_to >= ")
-                .push_bind(ends_after.to_naive_date())
-                .push(" OR rt.interval_to IS NULL)");
-        }
-
-        if let Some(starts_before) = params.interval_starts_le {
-            // If some, all instances must start before the given value.
-            query_builder
-                .push(" AND rt.interval_from <= ")
-                .push_bind(starts_before.to_naive_date());
-        }
-
-        if let Some(false) = params.is_terminating {
-            // If some, all instances must start before the given value.
-            query_builder.push(" AND rt.interval_to IS NULL");
-        }
-
-        if let Some(true) = params.is_terminating {
-            // If some, all instances must start before the given value.
-            query_builder.push(" AND rt.interval_to IS NOT NULL");
-        }
-
-        if let Some(amount_from) = params.amount_from {
-            query_builder
-                .push(" AND rt.amount >= ")
-                .push_bind(*amount_from);
-        }
-
-        if let Some(amount_to) = params.amount_to {
-            query_builder
-                .push(" AND rt.amount <= ")
-                .push_bind(*amount_to);
-        }
-
-        if let Some(category_id) = params.category_id {
-            query_builder
-                .push(" AND rt.category_id = ")
-                .push_bind(category_id.validate(user, database).await?);
-        }
-
-        match ColumnFilter::resolve(params.shop_filter, params.shop_id, "shop")? {
-            ColumnFilter::Any => {}
-            ColumnFilter::Null => {
-                query_builder.push(" AND rt.shop_id IS NULL");
-            }
-            ColumnFilter::Specific(shop_id) => {
-                query_builder
-                    .push(" AND rt.shop_id = ")
-                    .push_bind(shop_id.validate(user, database).await?);
-            }
+        let settings = UserSettings::get(database, user).await?;
+        let filters =
+            Filters::resolve(database, user, &params, settings.timezone(config.timezone)).await?;
+        let fetch_from = fetch_from(settings.today(config.timezone));
+        let order = params.order.clone().unwrap_or_else(|| {
+            Order::new(
+                params.order_key.clone().unwrap_or(settings.order_key),
+                params.ordering.clone().unwrap_or(settings.ordering),
+            )
+        });
+        let keyset = Keyset::new(order, sort_columns, "rt.id")?;
+
+        let mut totals_query = Totals::query_with_amount(&fetch_from, "rt", "period_amount");
+        filters.push(&mut totals_query);
+        let totals: Totals = totals_query.build_query_as().fetch_one(database).await?;
+
+        let mut query_builder = QueryBuilder::new(format!(
+            "SELECT rt.*, c.name as category, s.name as shop {fetch_from}"
+        ));
+        filters.push(&mut query_builder);
+        if let Some(cursor) = &params.cursor {
+            keyset.push_condition(&mut query_builder, cursor, pagination.limit)?;
         }
+        keyset.push_order_by(&mut query_builder);
 
-        // Handle ordering
-        query_builder.push(" ORDER BY");
-
-        match params.order_key {
-            OrderKey::Time => query_builder.push(format!(
-                " rt.interval_from {}, rt.interval_to {}",
-                params.ordering, params.ordering
-            )),
-            OrderKey::Amount => query_builder.push(format!(" rt.amount {}", params.ordering)),
-            OrderKey::Category => query_builder.push(format!(" c.name {}", params.ordering)),
-            OrderKey::Shop => query_builder.push(format!(" s.name {}", params.ordering)),
-        };
-        query_builder.push(format!(", rt.id {}", params.ordering));
-
-        query_builder.push(" LIMIT ").push_bind(pagination.limit.0);
+        // Fetch one more row than requested to know if there is a next page
+        query_builder
+            .push(" LIMIT ")
+            .push_bind(pagination.limit.0 + 1);
         query_builder
             .push(" OFFSET ")
             .push_bind(pagination.offset.0);
 
-        let result: Result<Vec<RecurringTransaction>, _> = query_builder
-            .build_query_as::<RawRecurringTransaction>()
-            .fetch_all(database)
-            .await?
+        let mut rows: Vec<RawRecurringTransaction> =
+            query_builder.build_query_as().fetch_all(database).await?;
+
+        let mut next_cursor = None;
+        if rows.len() > pagination.limit.0 as usize {
+            rows.truncate(pagination.limit.0 as usize);
+            next_cursor = rows
+                .last()
+                .map(|row| keyset.cursor(|key| row.sort_values(key), row.id));
+        }
+
+        let mut items = rows
             .into_iter()
             .map(|rt| rt.try_into())
-            .collect();
+            .collect::<Result<Vec<RecurringTransaction>, _>>()?;
+        load_details(database, &mut items).await?;
 
-        Ok(result?)
+        Ok(Page {
+            items,
+            meta: totals.into_meta(next_cursor),
+        })
     }
 
     async fn get_by_id(
         database: &sqlx::PgPool,
         user: &crate::users::User,
+        config: &Config,
         id: i32,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
         let result = sqlx::query_as!(
             RawRecurringTransaction,
             r#"
-            SELECT rt.id, rt.interval_from, rt.interval_to, rt.created_at, rt.updated_at, rt.user_id, rt.is_expense, rt.amount, rt.description, rt.category_id, rt.shop_id, rt.frequency as "frequency: RecurrenceFrequency", c.name as category, s.name as "shop?"
+            SELECT rt.id, rt.interval_from, rt.interval_to, rt.interval_months, rt.day_of_month, rt.month_of_year, rt.created_at, rt.updated_at, rt.user_id, rt.is_expense, recurring_transaction_amount(rt.id, rt.amount, $3) as "amount!", rt.amount as initial_amount, rt.description, rt.category_id, rt.shop_id, rt.frequency as "frequency: RecurrenceFrequency", c.name as category, s.name as "shop?"
             FROM recurring_transactions rt
             INNER JOIN categories c on rt.category_id = c.id
             LEFT JOIN shops s on rt.shop_id = s.id
             WHERE rt.user_id = $1 AND rt.id = $2
             "#,
             user.id,
-            id
+            id,
+            UserSettings::get(database, user)
+                .await?
+                .today(config.timezone)
         )
         .fetch_optional(database)
         .await?;
 
-        Ok(match result {
-            Some(val) => Some(val.try_into()?),
-            None => None,
-        })
+        let Some(raw) = result else {
+            return Ok(None);
+        };
+        let mut transactions = [raw.try_into()?];
+        load_details(database, &mut transactions).await?;
+        let [transaction] = transactions;
+        Ok(Some(transaction))
     }
 
     async fn update(
         database: &sqlx::PgPool,
         user: &crate::users::User,
+        config: &Config,
         id: i32,
         params: Self::UpdateParams,
     ) -> Result<Option<Self::ReturnType>, Self::Error> {
@@ -423,6 +674,12 @@ impl Resource for RecurringTransaction {
             bind_count += 1;
             query_parts.push(format!("interval_to = ${bind_count}"));
             bind_count += 1;
+            query_parts.push(format!("interval_months = ${bind_count}"));
+            bind_count += 1;
+            query_parts.push(format!("day_of_month = ${bind_count}"));
+            bind_count += 1;
+            query_parts.push(format!("month_of_year = ${bind_count}"));
+            bind_count += 1;
         }
 
         if params.is_expense.is_some() {
@@ -452,7 +709,7 @@ impl Resource for RecurringTransaction {
 
         // If no fields to update, return instance without changes
         if query_parts.is_empty() {
-            return Self::get_by_id(database, user, id).await;
+            return Self::get_by_id(database, user, config, id).await;
         }
 
         // Build the complete SQL query
@@ -467,27 +724,48 @@ impl Resource for RecurringTransaction {
         let mut query = sqlx::query(&sql);
 
         // Bind parameters in the same order as they were added to query_parts
-        if let Some(recurrence) = params.recurrence {
-            match recurrence {
-                Recurrence::Monthly {
-                    month_from,
-                    month_to,
-                } => {
-                    query = query.bind(RecurrenceFrequency::Monthly);
-                    query = query.bind(month_from.to_naive_date());
-                    query = query.bind(month_to.map(|val| val.to_naive_date()));
-                }
-                Recurrence::Yearly { year_from, year_to } => {
-                    query = query.bind(RecurrenceFrequency::Yearly);
-                    query = query.bind(
-                        NaiveDate::from_ymd_opt(year_from, 1, 1)
-                            .expect("Couldn't create NaiveDate year"),
-                    );
-                    query = query.bind(year_to.map(|val| {
-                        NaiveDate::from_ymd_opt(val, 1, 1).expect("Couldn't create NaiveDate year")
-                    }));
-                }
-            };
+        let recurrence = params.recurrence;
+        if let Some(recurrence) = &recurrence {
+            let recurrence = recurrence
+                .to_raw()
+                .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            // Like `add_amount_revision` requires, the revisions have to stay after the first month
+            let first_revision = sqlx::query_scalar!(
+                r#"
+                SELECT MIN(a.effective_from)
+                FROM recurring_transaction_amounts a
+                INNER JOIN recurring_transactions rt ON a.recurring_transaction_id = rt.id
+                WHERE rt.user_id = $1 AND rt.id = $2
+                "#,
+                user.id,
+                id
+            )
+            .fetch_one(database)
+            .await?;
+            let first_month = recurrence
+                .interval_from
+                .with_day(1)
+                .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+            if let Some(first_revision) = first_revision
+                && first_revision <= first_month
+            {
+                return Err(ServerError::Generic(
+                    StatusCode::BAD_REQUEST,
+                    Some(format!(
+                        "The transaction has an amount revision from {}, so it has to start before that month",
+                        first_revision.format("%Y-%m")
+                    )),
+                ));
+            }
+
+            query = query
+                .bind(recurrence.frequency)
+                .bind(recurrence.interval_from)
+                .bind(recurrence.interval_to)
+                .bind(recurrence.interval_months)
+                .bind(recurrence.day_of_month)
+                .bind(recurrence.month_of_year);
         }
 
         if let Some(is_expense) = params.is_expense {
@@ -517,13 +795,41 @@ impl Resource for RecurringTransaction {
         query = query.bind(user.id).bind(id);
 
         // Execute the query
-        let result = query.execute(database).await?;
-
+        let mut transaction = database.begin().await?;
+        let result = query.execute(&mut *transaction).await?;
         if result.rows_affected() == 0 {
-            Ok(None)
-        } else {
-            Self::get_by_id(database, user, id).await
+            return Ok(None);
+        }
+
+        // Exceptions have to lie in a month with an occurrence, like `set_exception` requires
+        if let Some(recurrence) = &recurrence {
+            let months = sqlx::query_scalar!(
+                "SELECT month FROM recurring_transaction_exceptions WHERE recurring_transaction_id = $1",
+                id
+            )
+            .fetch_all(&mut *transaction)
+            .await?;
+            let stale: Vec<NaiveDate> = months
+                .into_iter()
+                .filter(|&month| {
+                    let last_day = month
+                        .checked_add_months(Months::new(1))
+                        .and_then(|next| next.pred_opt())
+                        .unwrap_or(month);
+                    recurrence.occurrences(month, last_day).is_empty()
+                })
+                .collect();
+            sqlx::query!(
+                "DELETE FROM recurring_transaction_exceptions WHERE recurring_transaction_id = $1 AND month = ANY($2)",
+                id,
+                &stale
+            )
+            .execute(&mut *transaction)
+            .await?;
         }
+        transaction.commit().await?;
+
+        Self::get_by_id(database, user, config, id).await
     }
 
     async fn remove(
@@ -543,6 +849,330 @@ impl Resource for RecurringTransaction {
     }
 }
 
+/// A single date a recurring transaction falls on
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Occurrence {
+    date: NaiveDate,
+    is_expense: bool,
+    amount: i32,
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, IntoParams)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct OccurrenceParams {
+    /// First day, inclusive
+    #[garde(skip)]
+    from: NaiveDate,
+    /// Last day, inclusive. At most ten years after `from`.
+    #[garde(custom(validate_occurrence_range(&self.from)))]
+    to: NaiveDate,
+}
+
+fn validate_occurrence_range(from: &NaiveDate) -> impl FnOnce(&NaiveDate, &()) -> garde::Result {
+    move |to, _| {
+        if to < from {
+            return Err(garde::Error::new(format!("less than from={from}")));
+        }
+        if from
+            .checked_add_months(Months::new(120))
+            .is_some_and(|max| *to > max)
+        {
+            return Err(garde::Error::new("more than ten years after `from`"));
+        }
+        Ok(())
+    }
+}
+
+impl RecurringTransaction {
+    /// Occurrences of the transaction in the range of `params`, `None` if the transaction doesn't exist
+    pub async fn occurrences(
+        database: &sqlx::PgPool,
+        user: &User,
+        config: &Config,
+        id: i32,
+        params: &OccurrenceParams,
+    ) -> Result<Option<Vec<Occurrence>>, ServerError> {
+        let Some(transaction) = Self::get_by_id(database, user, config, id).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            transaction
+                .recurrence
+                .occurrences(params.from, params.to)
+                .into_iter()
+                .filter_map(|date| {
+                    let exception = transaction.exception_on(date);
+                    if exception.is_some_and(|exception| exception.skip) {
+                        return None;
+                    }
+                    Some(Occurrence {
+                        date,
+                        is_expense: transaction.is_expense,
+                        amount: exception
+                            .and_then(|exception| exception.amount)
+                            .unwrap_or_else(|| transaction.amount_on(date)),
+                        description: exception
+                            .and_then(|exception| exception.description.clone())
+                            .or_else(|| transaction.description.clone()),
+                    })
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AmountRevisionParams {
+    /// Must be after the first month of the transaction and after the last revision
+    #[garde(dive)]
+    effective_from: YearMonth,
+    #[garde(dive)]
+    amount: Amount,
+}
+
+impl RecurringTransaction {
+    /// Append an [`AmountRevision`], `None` if the transaction doesn't exist
+    pub async fn add_amount_revision(
+        database: &sqlx::PgPool,
+        user: &User,
+        config: &Config,
+        id: i32,
+        params: AmountRevisionParams,
+    ) -> Result<Option<Self>, ServerError> {
+        let Some(transaction) = Self::get_by_id(database, user, config, id).await? else {
+            return Ok(None);
+        };
+
+        let effective_from = params.effective_from.to_naive_date();
+        let first_month = transaction
+            .recurrence
+            .to_raw()
+            .and_then(|raw| raw.interval_from.with_day(1))
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        if effective_from <= first_month {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some(
+                    "`effectiveFrom` must be after the first month of the transaction".to_string(),
+                ),
+            ));
+        }
+        if let Some(last) = transaction.amount_revisions.last()
+            && effective_from <= last.effective_from.to_naive_date()
+        {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some(format!(
+                    "`effectiveFrom` must be after the last revision of {}",
+                    last.effective_from
+                )),
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recurring_transaction_amounts (recurring_transaction_id, effective_from, amount)
+            VALUES ($1, $2, $3)
+            "#,
+            id,
+            effective_from,
+            *params.amount
+        )
+        .execute(database)
+        .await?;
+
+        Self::get_by_id(database, user, config, id).await
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OccurrenceExceptionParams {
+    /// Skipped occurrences can't set `amount` or `description`, all others must set at least one of them
+    #[garde(skip)]
+    #[serde(default)]
+    skip: bool,
+    #[garde(dive)]
+    amount: Option<Amount>,
+    #[garde(length(graphemes, min = 1))]
+    #[schema(min_length = 1)]
+    description: Option<String>,
+}
+
+impl RecurringTransaction {
+    /// Set the [`OccurrenceException`] of a month, replacing an existing one. `None` if the transaction doesn't exist.
+    pub async fn set_exception(
+        database: &sqlx::PgPool,
+        user: &User,
+        config: &Config,
+        id: i32,
+        month: YearMonth,
+        params: OccurrenceExceptionParams,
+    ) -> Result<Option<Self>, ServerError> {
+        let Some(transaction) = Self::get_by_id(database, user, config, id).await? else {
+            return Ok(None);
+        };
+
+        if transaction
+            .recurrence
+            .occurrences(month.to_naive_date(), month.last_day())
+            .is_empty()
+        {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some(format!("The transaction doesn't occur in {month}")),
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recurring_transaction_exceptions (recurring_transaction_id, month, skip, amount, description)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (recurring_transaction_id, month)
+            DO UPDATE SET skip = EXCLUDED.skip, amount = EXCLUDED.amount, description = EXCLUDED.description
+            "#,
+            id,
+            month.to_naive_date(),
+            params.skip,
+            params.amount.map(|amount| *amount),
+            params.description
+        )
+        .execute(database)
+        .await?;
+
+        Self::get_by_id(database, user, config, id).await
+    }
+
+    /// Remove the [`OccurrenceException`] of a month. `None` if the transaction or the exception doesn't exist.
+    pub async fn remove_exception(
+        database: &sqlx::PgPool,
+        user: &User,
+        config: &Config,
+        id: i32,
+        month: YearMonth,
+    ) -> Result<Option<Self>, ServerError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM recurring_transaction_exceptions e
+            USING recurring_transactions rt
+            WHERE e.recurring_transaction_id = rt.id AND rt.user_id = $1 AND rt.id = $2 AND e.month = $3
+            "#,
+            user.id,
+            id,
+            month.to_naive_date()
+        )
+        .execute(database)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        Self::get_by_id(database, user, config, id).await
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/{id}/occurrences", get(get_occurrences))
+        .route("/{id}/amounts", post(add_amount_revision))
+        .route(
+            "/{id}/exceptions/{month}",
+            put(set_exception).delete(remove_exception),
+        )
+}
+
+async fn set_exception(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path((id, month)): Path<(u32, YearMonth)>,
+    ValidJson(params): ValidJson<OccurrenceExceptionParams>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(transaction) = RecurringTransaction::set_exception(
+        &state.database,
+        &user,
+        &state.config,
+        id as i32,
+        month,
+        params,
+    )
+    .await?
+    else {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    };
+
+    Ok(Json(json!({ "status": "success", "data": transaction })))
+}
+
+async fn remove_exception(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path((id, month)): Path<(u32, YearMonth)>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(transaction) = RecurringTransaction::remove_exception(
+        &state.database,
+        &user,
+        &state.config,
+        id as i32,
+        month,
+    )
+    .await?
+    else {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    };
+
+    Ok(Json(json!({ "status": "success", "data": transaction })))
+}
+
+async fn add_amount_revision(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<u32>,
+    ValidJson(params): ValidJson<AmountRevisionParams>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(transaction) = RecurringTransaction::add_amount_revision(
+        &state.database,
+        &user,
+        &state.config,
+        id as i32,
+        params,
+    )
+    .await?
+    else {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "status": "success", "data": transaction })),
+    ))
+}
+
+async fn get_occurrences(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<u32>,
+    ValidQuery(params): ValidQuery<OccurrenceParams>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(occurrences) = RecurringTransaction::occurrences(
+        &state.database,
+        &user,
+        &state.config,
+        id as i32,
+        &params,
+    )
+    .await?
+    else {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    };
+
+    Ok(Json(json!({ "status": "success", "data": occurrences })))
+}
+
 #[cfg(test)]
 mod tests {
     use std::cmp;
@@ -555,30 +1185,16 @@ mod tests {
         app::{
             api::pagination::{Limit, Offset, Pagination},
             resources::categories_shops::{Category, Shop},
-            transactions::Ordering,
+            transactions::{Order, Ordering},
         },
         users::User,
     };
 
     fn compare_recurrence(lhs: &Recurrence, rhs: &Recurrence) -> cmp::Ordering {
-        fn to_interval(recurrence: &Recurrence) -> (NaiveDate, Option<NaiveDate>) {
-            match recurrence {
-                Recurrence::Yearly { year_from, year_to } => (
-                    NaiveDate::from_ymd_opt(*year_from, 1, 1).unwrap(),
-                    year_to.map(|value| NaiveDate::from_ymd_opt(value, 1, 1).unwrap()),
-                ),
-                Recurrence::Monthly {
-                    month_from,
-                    month_to,
-                } => (
-                    month_from.to_naive_date(),
-                    month_to.as_ref().map(|value| value.to_naive_date()),
-                ),
-            }
-        }
-
-        let interval_lhs = to_interval(lhs);
-        let interval_rhs = to_interval(rhs);
+        let lhs = lhs.to_raw().unwrap();
+        let rhs = rhs.to_raw().unwrap();
+        let interval_lhs = (lhs.interval_from, lhs.interval_to);
+        let interval_rhs = (rhs.interval_from, rhs.interval_to);
 
         interval_lhs
             .0
@@ -633,16 +1249,17 @@ mod tests {
             &sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
                 .fetch_one(pool)
                 .await?,
+            &Config::default(),
             RecurringTransactionFetchParams {
-                ordering,
-                order_key,
+                ordering: Some(ordering),
+                order_key: Some(order_key),
                 ..Default::default()
             },
             Pagination::default(),
         )
         .await?;
 
-        Ok(result)
+        Ok(result.items)
     }
 
     mod tests_create {
@@ -660,6 +1277,8 @@ mod tests {
                     recurrence: Recurrence::Yearly {
                         year_from: 2024,
                         year_to: Some(2025),
+                        month: None,
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -676,6 +1295,8 @@ mod tests {
                 Recurrence::Yearly {
                     year_from: 2024,
                     year_to: Some(2025),
+                    month: None,
+                    day_of_month: None,
                 }
             );
             assert!(result.is_expense);
@@ -709,6 +1330,7 @@ mod tests {
                     recurrence: Recurrence::Monthly {
                         month_from: YearMonth::new(2024, 12)?,
                         month_to: Some(YearMonth::new(2025, 12)?),
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -725,6 +1347,7 @@ mod tests {
                 Recurrence::Monthly {
                     month_from: YearMonth::new(2024, 12)?,
                     month_to: Some(YearMonth::new(2025, 12)?),
+                    day_of_month: None,
                 },
             );
             assert!(result.is_expense);
@@ -747,18 +1370,125 @@ mod tests {
         }
 
         #[sqlx::test(fixtures("base"))]
-        fn test_create_instance_with_shop(pool: PgPool) -> anyhow::Result<()> {
+        fn test_create_instance_other_frequencies(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
             let category = get_category_by_name(&pool, user.id, "Groceries").await;
-            let shop = get_shop_by_name(&pool, user.id, "Whole Foods").await;
 
-            let result = RecurringTransaction::create(
-                &pool,
+            for recurrence in [
+                Recurrence::Weekly {
+                    date_from: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+                    date_to: None,
+                },
+                Recurrence::Biweekly {
+                    date_from: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+                    date_to: Some(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()),
+                },
+                Recurrence::Quarterly {
+                    month_from: YearMonth::new(2024, 2)?,
+                    month_to: Some(YearMonth::new(2025, 11)?),
+                    day_of_month: None,
+                },
+                Recurrence::EveryNMonths {
+                    months: 6,
+                    month_from: YearMonth::new(2024, 4)?,
+                    month_to: None,
+                    day_of_month: None,
+                },
+                Recurrence::Monthly {
+                    month_from: YearMonth::new(2024, 1)?,
+                    month_to: None,
+                    day_of_month: Some(28),
+                },
+                Recurrence::Yearly {
+                    year_from: 2024,
+                    year_to: None,
+                    month: Some(3),
+                    day_of_month: Some(3),
+                },
+            ] {
+                let result = RecurringTransaction::create(
+                    &pool,
+                    &user,
+                    RecurringTransactionCreateParams {
+                        recurrence: recurrence.clone(),
+                        is_expense: true,
+                        amount: Amount(1),
+                        description: None,
+                        category_id: UnvalidatedCategoryId::from(category.id),
+                        shop_id: None,
+                    },
+                )
+                .await?
+                .expect("Failed to create recurring transaction");
+                assert_eq!(result.recurrence, recurrence);
+
+                let stored =
+                    RecurringTransaction::get_by_id(&pool, &user, &Config::default(), result.id)
+                        .await?
+                        .expect("Failed to get created transaction");
+                assert_eq!(stored.recurrence, recurrence);
+            }
+
+            // Only weekly intervals may start within a month
+            sqlx::query!(
+                r#"
+                INSERT INTO recurring_transactions
+                    (user_id, category_id, is_expense, amount, frequency, interval_from)
+                VALUES (1, 1, true, 100, 'quarterly', '2024-03-14')
+                "#
+            )
+            .execute(&pool)
+            .await
+            .expect_err("Quarterly interval starting within a month was accepted");
+            sqlx::query!(
+                r#"
+                INSERT INTO recurring_transactions
+                    (user_id, category_id, is_expense, amount, frequency, interval_from)
+                VALUES (1, 1, true, 100, 'every_n_months', '2024-03-01')
+                "#
+            )
+            .execute(&pool)
+            .await
+            .expect_err("Every-N-months interval without number of months was accepted");
+            sqlx::query!(
+                r#"
+                INSERT INTO recurring_transactions
+                    (user_id, category_id, is_expense, amount, frequency, interval_from, day_of_month)
+                VALUES (1, 1, true, 100, 'weekly', '2024-03-14', 14)
+                "#
+            )
+            .execute(&pool)
+            .await
+            .expect_err("Weekly interval with day of month was accepted");
+            sqlx::query!(
+                r#"
+                INSERT INTO recurring_transactions
+                    (user_id, category_id, is_expense, amount, frequency, interval_from, month_of_year)
+                VALUES (1, 1, true, 100, 'yearly', '2024-01-01', 3)
+                "#
+            )
+            .execute(&pool)
+            .await
+            .expect_err("Yearly interval with month but without day was accepted");
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base"))]
+        fn test_create_instance_with_shop(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let category = get_category_by_name(&pool, user.id, "Groceries").await;
+            let shop = get_shop_by_name(&pool, user.id, "Whole Foods").await;
+
+            let result = RecurringTransaction::create(
+                &pool,
                 &user,
                 RecurringTransactionCreateParams {
                     recurrence: Recurrence::Yearly {
                         year_from: 2024,
                         year_to: Some(2025),
+                        month: None,
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -775,6 +1505,8 @@ mod tests {
                 Recurrence::Yearly {
                     year_from: 2024,
                     year_to: Some(2025),
+                    month: None,
+                    day_of_month: None,
                 }
             );
             assert!(result.is_expense);
@@ -804,6 +1536,8 @@ mod tests {
                     recurrence: Recurrence::Yearly {
                         year_from: 2024,
                         year_to: Some(0),
+                        month: None,
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -822,6 +1556,8 @@ mod tests {
                     recurrence: Recurrence::Yearly {
                         year_from: 2024,
                         year_to: Some(0),
+                        month: None,
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -862,6 +1598,8 @@ mod tests {
                     recurrence: Recurrence::Yearly {
                         year_from: 2024,
                         year_to: Some(0),
+                        month: None,
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -880,6 +1618,8 @@ mod tests {
                     recurrence: Recurrence::Yearly {
                         year_from: 2024,
                         year_to: Some(0),
+                        month: None,
+                        day_of_month: None,
                     },
                     is_expense: true,
                     amount: Amount(1),
@@ -913,6 +1653,7 @@ mod tests {
             let result = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 Pagination::default(),
             )
@@ -930,6 +1671,7 @@ mod tests {
             let result = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 Pagination::default(),
             )
@@ -954,8 +1696,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All should be incomes
             assert!(result.iter().all(|t| !t.is_expense));
@@ -972,8 +1720,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All should be yearly
             assert!(
@@ -994,8 +1748,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All should be yearly
             assert!(
@@ -1016,20 +1776,82 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All should start in (january) 2024 or in an earlier year
             assert!(result.iter().all(|t| match &t.recurrence {
                 Recurrence::Yearly { year_from, .. } => *year_from <= 2024,
                 Recurrence::Monthly { month_from, .. } =>
                     (month_from.year == 2024 && month_from.month == 1) || month_from.year < 2024,
+                _ => unreachable!("Fixtures only contain monthly and yearly transactions"),
             }));
             assert_eq!(result.len(), 7);
 
             Ok(())
         }
 
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_fetch_weekly(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let category = get_category_by_name(&pool, user.id, "Groceries").await;
+            let weekly = RecurringTransaction::create(
+                &pool,
+                &user,
+                RecurringTransactionCreateParams {
+                    recurrence: Recurrence::Weekly {
+                        date_from: NaiveDate::from_ymd_opt(2024, 3, 14).unwrap(),
+                        date_to: Some(NaiveDate::from_ymd_opt(2024, 4, 10).unwrap()),
+                    },
+                    is_expense: true,
+                    amount: Amount(2500),
+                    description: None,
+                    category_id: UnvalidatedCategoryId::from(category.id),
+                    shop_id: None,
+                },
+            )
+            .await?
+            .expect("Failed to create recurring transaction");
+
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                RecurringTransactionFetchParams {
+                    frequency: Some(RecurrenceFrequency::Weekly),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.items, vec![weekly.clone()]);
+
+            // Starting in the middle of March is still active in March, but not in May
+            for (month, expected) in [(2, false), (3, true), (4, true), (5, false)] {
+                let result = RecurringTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    RecurringTransactionFetchParams {
+                        interval_starts_le: Some(YearMonth::new(2024, month)?),
+                        interval_ends_ge: Some(YearMonth::new(2024, month)?),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+                .await?;
+                assert_eq!(result.contains(&weekly), expected, "Month {month}");
+            }
+
+            Ok(())
+        }
+
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_fetch_ends_ge(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
@@ -1038,14 +1860,21 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All should end in 2025 or later
             assert!(result.iter().all(|t| match &t.recurrence {
                 Recurrence::Yearly { year_to, .. } => year_to.is_none_or(|year| year >= 2025),
                 Recurrence::Monthly { month_to, .. } =>
                     month_to.as_ref().is_none_or(|month| month.year >= 2025),
+                _ => unreachable!("Fixtures only contain monthly and yearly transactions"),
             }));
             assert_eq!(result.len(), 5);
 
@@ -1063,8 +1892,72 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 4);
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_fetch_with_relative_range(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await; // Alice
+            let ids = |page: Page<RecurringTransaction>| -> Vec<i32> {
+                page.iter().map(|t| t.id).collect()
+            };
+
+            for range in [DateRange::LastYear, DateRange::Last12Months] {
+                let (date_from, date_to) = range.dates(Config::default().timezone);
+                let expected = RecurringTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    RecurringTransactionFetchParams {
+                        interval_ends_ge: Some(YearMonth::new(
+                            date_from.year(),
+                            date_from.month(),
+                        )?),
+                        interval_starts_le: Some(YearMonth::new(date_to.year(), date_to.month())?),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+                .await?;
+                let result = RecurringTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    RecurringTransactionFetchParams {
+                        range: Some(range),
+                        ..Default::default()
+                    },
+                    Pagination::default(),
+                )
+                .await?;
+                assert_eq!(ids(result), ids(expected));
+            }
+
+            // Explicit months take precedence over the range
+            let params = RecurringTransactionFetchParams {
+                range: Some(DateRange::ThisMonth),
+                interval_starts_le: Some(YearMonth::new(2024, 1)?),
+                interval_ends_ge: Some(YearMonth::new(2024, 12)?),
+                ..Default::default()
+            };
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
             assert_eq!(result.len(), 4);
 
             Ok(())
@@ -1078,13 +1971,20 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should have amount >= amount_from
             assert!(result.iter().all(|t| match &t.recurrence {
                 Recurrence::Yearly { year_to, .. } => year_to.is_none(),
                 Recurrence::Monthly { month_to, .. } => month_to.is_none(),
+                _ => unreachable!("Fixtures only contain monthly and yearly transactions"),
             }));
             assert_eq!(result.len(), 4);
 
@@ -1099,13 +1999,20 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should have amount >= amount_from
             assert!(result.iter().all(|t| match &t.recurrence {
                 Recurrence::Yearly { year_to, .. } => year_to.is_some(),
                 Recurrence::Monthly { month_to, .. } => month_to.is_some(),
+                _ => unreachable!("Fixtures only contain monthly and yearly transactions"),
             }));
             assert_eq!(result.len(), 7);
 
@@ -1121,8 +2028,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should have amount >= amount_from
             assert!(result.iter().all(|t| t.amount >= *amount_from));
@@ -1140,8 +2053,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should have amount <= amount_to
             assert!(result.iter().all(|t| t.amount <= *amount_to));
@@ -1161,8 +2080,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should be within the amount range
             assert!(
@@ -1186,8 +2111,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
             assert!(result.is_empty());
 
             Ok(())
@@ -1202,8 +2133,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should belong to the specified category
             assert!(result.iter().all(|t| t.category_id == category.id));
@@ -1220,6 +2157,7 @@ mod tests {
             RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams {
                     category_id: Some(UnvalidatedCategoryId::from(999999)),
                     ..Default::default()
@@ -1232,7 +2170,7 @@ mod tests {
             RecurringTransaction::fetch(
             &pool,
             &user,
-            RecurringTransactionFetchParams {
+            &Config::default(), RecurringTransactionFetchParams {
                 category_id: Some(UnvalidatedCategoryId::from(14)),
                 ..Default::default()
             },
@@ -1257,7 +2195,13 @@ mod tests {
             };
 
             // Alice trying to filter by Bob's category should fail
-            RecurringTransaction::fetch(&pool, &alice, params, Pagination::default())
+            RecurringTransaction::fetch(
+                &pool,
+                &alice,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
             .await
             .expect_err(
                 "recurring transaction fetch with category belonging to different user didn't fail",
@@ -1276,8 +2220,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should belong to the specified shop
             assert!(result.iter().all(|t| t.shop_id == Some(shop.id)));
@@ -1295,8 +2245,14 @@ mod tests {
                 ..Default::default()
             };
 
-            let result =
-                RecurringTransaction::fetch(&pool, &user, params, Pagination::default()).await?;
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await?;
 
             // All transactions should belong to the specified shop
             assert!(result.iter().all(|t| t.shop_id.is_none()));
@@ -1313,6 +2269,7 @@ mod tests {
             RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams {
                     shop_filter: Some(FilterMode::Specific),
                     shop_id: Some(UnvalidatedShopId::from(999999)),
@@ -1326,6 +2283,7 @@ mod tests {
             RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams {
                     shop_filter: Some(FilterMode::Specific),
                     shop_id: Some(UnvalidatedShopId::from(16)),
@@ -1351,11 +2309,17 @@ mod tests {
             };
 
             // Alice trying to filter by Bob's shop should fail
-            RecurringTransaction::fetch(&pool, &alice, params, Pagination::default())
-                .await
-                .expect_err(
-                    "recurring transaction fetch with shop belonging to different user didn't fail",
-                );
+            RecurringTransaction::fetch(
+                &pool,
+                &alice,
+                &Config::default(),
+                params,
+                Pagination::default(),
+            )
+            .await
+            .expect_err(
+                "recurring transaction fetch with shop belonging to different user didn't fail",
+            );
 
             Ok(())
         }
@@ -1369,6 +2333,7 @@ mod tests {
             RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams {
                     shop_filter: Some(FilterMode::Specific),
                     shop_id: None,
@@ -1383,6 +2348,7 @@ mod tests {
             RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams {
                     shop_filter: None,
                     shop_id: Some(UnvalidatedShopId::from(shop.id)),
@@ -1397,6 +2363,7 @@ mod tests {
             RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams {
                     shop_filter: Some(FilterMode::Null),
                     shop_id: Some(UnvalidatedShopId::from(shop.id)),
@@ -1538,6 +2505,7 @@ mod tests {
             let result = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 Pagination::new(Limit(5), Offset(0)),
             )
@@ -1556,6 +2524,7 @@ mod tests {
             let result1 = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 pagination1,
             )
@@ -1566,6 +2535,7 @@ mod tests {
             let result2 = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 pagination2,
             )
@@ -1593,6 +2563,7 @@ mod tests {
             let result = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 pagination,
             )
@@ -1611,6 +2582,7 @@ mod tests {
             let result = RecurringTransaction::fetch(
                 &pool,
                 &user,
+                &Config::default(),
                 RecurringTransactionFetchParams::default(),
                 pagination,
             )
@@ -1619,32 +2591,244 @@ mod tests {
             assert_eq!(result.len(), 0);
             Ok(())
         }
-    }
-
-    mod tests_get_by_id {
-        use super::*;
 
         #[sqlx::test(fixtures("base", "recurring"))]
-        fn test_get_by_id_basic(pool: PgPool) -> anyhow::Result<()> {
+        fn test_fetch_with_combined_list_filters(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
+            let entertainment = get_category_by_name(&pool, user.id, "Entertainment").await;
+            let netflix = get_shop_by_name(&pool, user.id, "Netflix").await;
 
-            // Get the first transaction from fixtures: Weekly grocery shopping
-            let result = RecurringTransaction::get_by_id(&pool, &user, 1)
-                .await?
-                .expect("Failed to get transaction by id");
-
-            assert_eq!(result.id, 1);
-            assert_eq!(
-                result.recurrence,
-                Recurrence::Monthly {
-                    month_from: YearMonth::new(2024, 1)?,
-                    month_to: Some(YearMonth::new(2024, 12)?)
-                }
+            let params = RecurringTransactionFetchParams {
+                category_ids: Some(IdList::from(vec![entertainment.id])),
+                exclude_shop_ids: Some(IdList::from(vec![netflix.id])),
+                ..Default::default()
+            };
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                params.clone(),
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 2);
+            assert!(
+                result.iter().all(|t| {
+                    t.category_id == entertainment.id && t.shop_id != Some(netflix.id)
+                })
             );
-            assert!(result.is_expense);
-            assert_eq!(result.amount, 1599);
-            assert_eq!(result.description, Some("Netflix subscription".to_string()));
-            assert_eq!(result.category_id, 4);
+
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                RecurringTransactionFetchParams {
+                    description_contains: Some("gym".to_owned()),
+                    ..params
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].description.as_deref(), Some("Gym membership"));
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_fetch_meta(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            let all = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                RecurringTransactionFetchParams::default(),
+                Pagination::default(),
+            )
+            .await?;
+            let sum = |is_expense: bool| -> i64 {
+                all.iter()
+                    .filter(|t| t.is_expense == is_expense)
+                    .map(|t| t.amount as i64)
+                    .sum()
+            };
+            let (income, expenses) = (sum(false), sum(true));
+
+            let page = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                RecurringTransactionFetchParams::default(),
+                Pagination::new(Limit(3), Offset(0)),
+            )
+            .await?;
+            assert_eq!(page.len(), 3);
+            assert_eq!(page.meta.total, 11);
+            assert_eq!(page.meta.income, income);
+            assert_eq!(page.meta.expenses, expenses);
+            assert_eq!(page.meta.net, income - expenses);
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_fetch_cursor_pagination(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            for order_key in [
+                OrderKey::Time,
+                OrderKey::Amount,
+                OrderKey::Category,
+                OrderKey::Shop,
+            ] {
+                for ordering in [Ordering::Asc, Ordering::Desc] {
+                    let expected = fetch_all_transactions_with_ordering(
+                        &pool,
+                        user.id,
+                        ordering.clone(),
+                        order_key.clone(),
+                    )
+                    .await?;
+
+                    // Walk through all pages, they must match the unpaginated list
+                    let mut ids = Vec::new();
+                    let mut cursor = None;
+                    loop {
+                        let page = RecurringTransaction::fetch(
+                            &pool,
+                            &user,
+                            &Config::default(),
+                            RecurringTransactionFetchParams {
+                                ordering: Some(ordering.clone()),
+                                order_key: Some(order_key.clone()),
+                                cursor,
+                                ..Default::default()
+                            },
+                            Pagination::new(Limit(2), Offset(0)),
+                        )
+                        .await?;
+                        assert_eq!(page.meta.total, 11);
+                        ids.extend(page.iter().map(|t| t.id));
+                        cursor = page.meta.next_cursor;
+                        if cursor.is_none() {
+                            break;
+                        }
+                    }
+
+                    let expected: Vec<i32> = expected.iter().map(|t| t.id).collect();
+                    assert_eq!(ids, expected, "{order_key:?} {ordering}");
+                }
+            }
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_fetch_multiple_order_keys(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            // Weekly comes before monthly in the enum, but after it by name
+            RecurringTransaction::update(
+                &pool,
+                &user,
+                &Config::default(),
+                2,
+                RecurringTransactionUpdateParams {
+                    recurrence: Some(Recurrence::Weekly {
+                        date_from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                        date_to: None,
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .expect("Transaction should exist");
+            let order: Order = "frequency:asc,intervalTo:desc:nullsLast,amount"
+                .parse()
+                .map_err(anyhow::Error::msg)?;
+
+            let result = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                RecurringTransactionFetchParams {
+                    order: Some(order.clone()),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(result.len(), 11);
+
+            // Weekly before monthly before yearly transactions, open-ended ones last within each frequency
+            let keys: Vec<(RecurrenceFrequency, bool)> = result
+                .iter()
+                .map(|t| {
+                    let raw = t.recurrence.to_raw().unwrap();
+                    (raw.frequency, raw.interval_to.is_none())
+                })
+                .collect();
+            assert_eq!(keys[0], (RecurrenceFrequency::Weekly, true));
+            assert!(keys.is_sorted(), "{keys:?}");
+            assert!(keys.iter().any(|(_, is_open)| *is_open));
+            assert_ne!(
+                keys.first().map(|key| &key.0),
+                keys.last().map(|key| &key.0)
+            );
+
+            // Walk through all pages, they must match the unpaginated list
+            let mut ids = Vec::new();
+            let mut cursor = None;
+            loop {
+                let page = RecurringTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    RecurringTransactionFetchParams {
+                        order: Some(order.clone()),
+                        cursor,
+                        ..Default::default()
+                    },
+                    Pagination::new(Limit(3), Offset(0)),
+                )
+                .await?;
+                ids.extend(page.iter().map(|t| t.id));
+                cursor = page.meta.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+            assert_eq!(ids, result.iter().map(|t| t.id).collect::<Vec<i32>>());
+
+            Ok(())
+        }
+    }
+
+    mod tests_get_by_id {
+        use super::*;
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_get_by_id_basic(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            // Get the first transaction from fixtures: Weekly grocery shopping
+            let result = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
+                .await?
+                .expect("Failed to get transaction by id");
+
+            assert_eq!(result.id, 1);
+            assert_eq!(
+                result.recurrence,
+                Recurrence::Monthly {
+                    month_from: YearMonth::new(2024, 1)?,
+                    month_to: Some(YearMonth::new(2024, 12)?),
+                    day_of_month: None,
+                }
+            );
+            assert!(result.is_expense);
+            assert_eq!(result.amount, 1599);
+            assert_eq!(result.description, Some("Netflix subscription".to_string()));
+            assert_eq!(result.category_id, 4);
             assert_eq!(result.category, "Entertainment"); // From fixtures
             assert_eq!(result.shop_id, Some(6));
             assert_eq!(result.shop, Some("Netflix".to_string())); // From fixtures
@@ -1659,28 +2843,32 @@ mod tests {
             let user2 = get_user_by_id(&pool, 2).await;
 
             // Transaction ID 1 belongs to user 1, try to access it as user 2
-            let result = RecurringTransaction::get_by_id(&pool, &user2, 1).await?;
+            let result =
+                RecurringTransaction::get_by_id(&pool, &user2, &Config::default(), 1).await?;
             assert!(
                 result.is_none(),
                 "Should not be able to access another user's transaction"
             );
 
             // Verify that user 1 can still access their own transaction
-            let result = RecurringTransaction::get_by_id(&pool, &user1, 1).await?;
+            let result =
+                RecurringTransaction::get_by_id(&pool, &user1, &Config::default(), 1).await?;
             assert!(
                 result.is_some(),
                 "User 1 should be able to access their own transaction"
             );
 
             // Test accessing user 2's transaction as user 1
-            let result = RecurringTransaction::get_by_id(&pool, &user1, 7).await?;
+            let result =
+                RecurringTransaction::get_by_id(&pool, &user1, &Config::default(), 7).await?;
             assert!(
                 result.is_none(),
                 "User 1 should not be able to access user 2's transaction"
             );
 
             // Verify that user 2 can access their own transaction
-            let result = RecurringTransaction::get_by_id(&pool, &user2, 7).await?;
+            let result =
+                RecurringTransaction::get_by_id(&pool, &user2, &Config::default(), 7).await?;
             assert!(
                 result.is_some(),
                 "User 2 should be able to access their own transaction"
@@ -1694,7 +2882,8 @@ mod tests {
             let user = get_user_by_id(&pool, 1).await;
 
             // Try to get a transaction with an ID that doesn't exist
-            let result = RecurringTransaction::get_by_id(&pool, &user, 99999).await?;
+            let result =
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 99999).await?;
 
             assert!(
                 result.is_none(),
@@ -1711,13 +2900,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_nothing(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams::default(),
             )
@@ -1734,7 +2924,7 @@ mod tests {
         fn test_update_recurrence_monthly(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
             // this instance should have a yearly recurrence
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 14)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 14)
                 .await?
                 .expect("Should find instance created by fixtures");
             assert!(matches!(instance.recurrence, Recurrence::Yearly { .. }));
@@ -1742,11 +2932,13 @@ mod tests {
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 14,
                 RecurringTransactionUpdateParams {
                     recurrence: Some(Recurrence::Monthly {
                         month_from: YearMonth::new(2026, 1)?,
                         month_to: None,
+                        day_of_month: None,
                     }),
                     ..Default::default()
                 },
@@ -1760,13 +2952,14 @@ mod tests {
                     recurrence: Recurrence::Monthly {
                         month_from: YearMonth::new(2026, 1)?,
                         month_to: None,
+                        day_of_month: None,
                     },
                     updated_at: updated_instance.updated_at,
                     ..instance
                 }
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 14)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 14)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1775,11 +2968,66 @@ mod tests {
             Ok(())
         }
 
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_update_recurrence_every_n_months(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let every_six_months = Recurrence::EveryNMonths {
+                months: 6,
+                month_from: YearMonth::new(2026, 1)?,
+                month_to: None,
+                day_of_month: None,
+            };
+
+            let updated_instance = RecurringTransaction::update(
+                &pool,
+                &user,
+                &Config::default(),
+                14,
+                RecurringTransactionUpdateParams {
+                    recurrence: Some(every_six_months.clone()),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .expect("Should return updated instance");
+            assert_eq!(updated_instance.recurrence, every_six_months);
+
+            // The number of months is removed with another frequency
+            let quarterly = Recurrence::Quarterly {
+                month_from: YearMonth::new(2026, 1)?,
+                month_to: Some(YearMonth::new(2026, 10)?),
+                day_of_month: None,
+            };
+            let updated_instance = RecurringTransaction::update(
+                &pool,
+                &user,
+                &Config::default(),
+                14,
+                RecurringTransactionUpdateParams {
+                    recurrence: Some(quarterly.clone()),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .expect("Should return updated instance");
+            assert_eq!(updated_instance.recurrence, quarterly);
+            assert_eq!(
+                sqlx::query_scalar!(
+                    "SELECT interval_months FROM recurring_transactions WHERE id = 14"
+                )
+                .fetch_one(&pool)
+                .await?,
+                None
+            );
+
+            Ok(())
+        }
+
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_recurrence_year_to_null(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
             // this instance should have a yearly recurrence
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
             assert!(matches!(instance.recurrence, Recurrence::Monthly { .. }));
@@ -1787,11 +3035,14 @@ mod tests {
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     recurrence: Some(Recurrence::Yearly {
                         year_from: 2026,
                         year_to: None,
+                        month: None,
+                        day_of_month: None,
                     }),
                     ..Default::default()
                 },
@@ -1805,13 +3056,15 @@ mod tests {
                     recurrence: Recurrence::Yearly {
                         year_from: 2026,
                         year_to: None,
+                        month: None,
+                        day_of_month: None,
                     },
                     updated_at: updated_instance.updated_at,
                     ..instance
                 }
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1823,13 +3076,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_is_expense(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     is_expense: Some(false),
@@ -1848,7 +3102,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1860,7 +3114,7 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_amount(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
             assert_eq!(instance.amount, 1599);
@@ -1868,6 +3122,7 @@ mod tests {
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     amount: Some(Amount(10000)),
@@ -1880,13 +3135,14 @@ mod tests {
             assert_eq!(
                 RecurringTransaction {
                     amount: 10000,
+                    initial_amount: 10000,
                     updated_at: updated_instance.updated_at,
                     ..instance
                 },
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1898,7 +3154,7 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_description_some(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
             assert_eq!(
@@ -1909,6 +3165,7 @@ mod tests {
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     description: TriState::Defined(Some(Description(
@@ -1929,7 +3186,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1941,7 +3198,7 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_description_none(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
             assert_eq!(
@@ -1952,6 +3209,7 @@ mod tests {
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     description: TriState::Defined(None),
@@ -1970,7 +3228,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -1982,13 +3240,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_valid_category_id(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     category_id: Some(UnvalidatedCategoryId::from(4)),
@@ -2008,7 +3267,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -2024,6 +3283,7 @@ mod tests {
             RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     category_id: Some(UnvalidatedCategoryId::from(999999)),
@@ -2036,6 +3296,7 @@ mod tests {
             RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     category_id: Some(UnvalidatedCategoryId::from(14)),
@@ -2051,13 +3312,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_valid_shop_id_some(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     shop_id: TriState::Defined(Some(UnvalidatedShopId::from(4))),
@@ -2077,7 +3339,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -2089,13 +3351,14 @@ mod tests {
         #[sqlx::test(fixtures("base", "recurring"))]
         fn test_update_valid_shop_id_none(pool: PgPool) -> anyhow::Result<()> {
             let user = get_user_by_id(&pool, 1).await;
-            let instance = RecurringTransaction::get_by_id(&pool, &user, 1)
+            let instance = RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                 .await?
                 .expect("Should find instance created by fixtures");
 
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     shop_id: TriState::Defined(None),
@@ -2115,7 +3378,7 @@ mod tests {
                 updated_instance
             );
             assert_eq!(
-                RecurringTransaction::get_by_id(&pool, &user, 1)
+                RecurringTransaction::get_by_id(&pool, &user, &Config::default(), 1)
                     .await?
                     .expect("Should fetch updated transaction"),
                 updated_instance
@@ -2131,6 +3394,7 @@ mod tests {
             RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     shop_id: TriState::Defined(Some(UnvalidatedShopId::from(999999))),
@@ -2143,6 +3407,7 @@ mod tests {
             RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     shop_id: TriState::Defined(Some(UnvalidatedShopId::from(16))),
@@ -2172,6 +3437,7 @@ mod tests {
             let updated_instance = RecurringTransaction::update(
                 &pool,
                 &user,
+                &Config::default(),
                 1,
                 RecurringTransactionUpdateParams {
                     amount: Some(Amount(10000)),
@@ -2201,6 +3467,7 @@ mod tests {
                 RecurringTransaction::update(
                     &pool,
                     &user,
+                    &Config::default(),
                     user_2_transaction.id,
                     RecurringTransactionUpdateParams {
                         description: TriState::Defined(Some(Description(
@@ -2242,6 +3509,7 @@ mod tests {
                 RecurringTransaction::update(
                     &pool,
                     &user,
+                    &Config::default(),
                     999999,
                     RecurringTransactionUpdateParams {
                         description: TriState::Defined(Some(Description(
@@ -2269,6 +3537,667 @@ mod tests {
         }
     }
 
+    mod tests_occurrences {
+        use super::*;
+
+        fn params(from: NaiveDate, to: NaiveDate) -> OccurrenceParams {
+            OccurrenceParams { from, to }
+        }
+
+        fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(year, month, day).unwrap()
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_occurrences(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            // Netflix subscription, monthly from January to December 2024
+            let occurrences = RecurringTransaction::occurrences(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                &params(date(2024, 11, 2), date(2025, 3, 1)),
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(
+                occurrences,
+                vec![Occurrence {
+                    date: date(2024, 12, 1),
+                    is_expense: true,
+                    amount: 1599,
+                    description: Some("Netflix subscription".to_string()),
+                }]
+            );
+
+            let bob = get_user_by_id(&pool, 2).await;
+            assert!(
+                RecurringTransaction::occurrences(
+                    &pool,
+                    &bob,
+                    &Config::default(),
+                    1,
+                    &params(date(2024, 1, 1), date(2024, 12, 31))
+                )
+                .await?
+                .is_none()
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn test_validate_params() {
+            params(date(2024, 1, 1), date(2024, 1, 1))
+                .validate()
+                .expect("Single day");
+            params(date(2024, 1, 1), date(2034, 1, 1))
+                .validate()
+                .expect("Ten years");
+            params(date(2024, 1, 2), date(2024, 1, 1))
+                .validate()
+                .expect_err("End before start");
+            params(date(2024, 1, 1), date(2034, 1, 2))
+                .validate()
+                .expect_err("More than ten years");
+        }
+    }
+
+    mod tests_amount_revisions {
+        use super::*;
+
+        fn revision(year: i32, month: u32, amount: i32) -> AmountRevisionParams {
+            AmountRevisionParams {
+                effective_from: YearMonth::new(year, month).unwrap(),
+                amount: Amount(amount),
+            }
+        }
+
+        fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(year, month, day).unwrap()
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_add_amount_revision(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            // Netflix subscription, monthly from January to December 2024 for 1599
+            let transaction = RecurringTransaction::add_amount_revision(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                revision(2024, 6, 1799),
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(transaction.initial_amount, 1599);
+            assert_eq!(transaction.amount, 1799, "Revision applies today");
+            assert_eq!(
+                transaction.amount_revisions,
+                vec![AmountRevision {
+                    effective_from: YearMonth::new(2024, 6)?,
+                    amount: 1799
+                }]
+            );
+
+            let amounts: Vec<_> = RecurringTransaction::occurrences(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                &OccurrenceParams {
+                    from: date(2024, 5, 1),
+                    to: date(2024, 7, 1),
+                },
+            )
+            .await?
+            .expect("Transaction should exist")
+            .into_iter()
+            .map(|occurrence| (occurrence.date, occurrence.amount))
+            .collect();
+            assert_eq!(
+                amounts,
+                vec![
+                    (date(2024, 5, 1), 1599),
+                    (date(2024, 6, 1), 1799),
+                    (date(2024, 7, 1), 1799)
+                ]
+            );
+
+            let page = RecurringTransaction::fetch(
+                &pool,
+                &user,
+                &Config::default(),
+                RecurringTransactionFetchParams {
+                    amount_from: Some(Amount(1700)),
+                    amount_to: Some(Amount(1800)),
+                    ..Default::default()
+                },
+                Pagination::default(),
+            )
+            .await?;
+            assert_eq!(page.items, vec![transaction.clone()]);
+            assert_eq!(page.meta.total, 1);
+            assert_eq!(page.meta.expenses, 1799);
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_update_recurrence_with_revisions(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let config = Config::default();
+            let monthly_from = |month| -> anyhow::Result<RecurringTransactionUpdateParams> {
+                Ok(RecurringTransactionUpdateParams {
+                    recurrence: Some(Recurrence::Monthly {
+                        month_from: YearMonth::new(2024, month)?,
+                        month_to: None,
+                        day_of_month: None,
+                    }),
+                    ..Default::default()
+                })
+            };
+
+            RecurringTransaction::add_amount_revision(
+                &pool,
+                &user,
+                &config,
+                1,
+                revision(2024, 6, 1799),
+            )
+            .await?
+            .expect("Transaction should exist");
+
+            for month in [6, 7] {
+                let result =
+                    RecurringTransaction::update(&pool, &user, &config, 1, monthly_from(month)?)
+                        .await;
+                assert!(
+                    matches!(
+                        result,
+                        Err(ServerError::Generic(StatusCode::BAD_REQUEST, _))
+                    ),
+                    "Starts with or after the revision"
+                );
+            }
+
+            let transaction =
+                RecurringTransaction::update(&pool, &user, &config, 1, monthly_from(5)?)
+                    .await?
+                    .expect("Transaction should exist");
+            assert_eq!(transaction.amount_revisions.len(), 1);
+
+            // Transactions of other users are not found instead
+            let other_user = get_user_by_id(&pool, 2).await;
+            let result =
+                RecurringTransaction::update(&pool, &other_user, &config, 1, monthly_from(7)?)
+                    .await?;
+            assert!(result.is_none());
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_add_amount_revision_order(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            let result = RecurringTransaction::add_amount_revision(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                revision(2024, 1, 1799),
+            )
+            .await;
+            assert!(
+                matches!(
+                    result,
+                    Err(ServerError::Generic(StatusCode::BAD_REQUEST, _))
+                ),
+                "First month of the transaction"
+            );
+
+            RecurringTransaction::add_amount_revision(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                revision(2024, 6, 1799),
+            )
+            .await?
+            .expect("Transaction should exist");
+            for month in [3, 6] {
+                let result = RecurringTransaction::add_amount_revision(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    1,
+                    revision(2024, month, 1999),
+                )
+                .await;
+                assert!(
+                    matches!(
+                        result,
+                        Err(ServerError::Generic(StatusCode::BAD_REQUEST, _))
+                    ),
+                    "Not after the last revision"
+                );
+            }
+
+            let transaction = RecurringTransaction::add_amount_revision(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                revision(2024, 9, 1999),
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(transaction.amount_revisions.len(), 2);
+            assert_eq!(transaction.amount, 1999);
+
+            let bob = get_user_by_id(&pool, 2).await;
+            assert!(
+                RecurringTransaction::add_amount_revision(
+                    &pool,
+                    &bob,
+                    &Config::default(),
+                    1,
+                    revision(2024, 12, 999)
+                )
+                .await?
+                .is_none()
+            );
+
+            Ok(())
+        }
+    }
+
+    mod tests_exceptions {
+        use super::*;
+
+        fn skip() -> OccurrenceExceptionParams {
+            OccurrenceExceptionParams {
+                skip: true,
+                amount: None,
+                description: None,
+            }
+        }
+
+        fn change(amount: Option<i32>, description: Option<&str>) -> OccurrenceExceptionParams {
+            OccurrenceExceptionParams {
+                skip: false,
+                amount: amount.map(Amount),
+                description: description.map(str::to_string),
+            }
+        }
+
+        fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(year, month, day).unwrap()
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_exceptions(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            // Netflix subscription, monthly from January to December 2024 for 1599
+            RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                YearMonth::new(2024, 6)?,
+                skip(),
+            )
+            .await?
+            .expect("Transaction should exist");
+            RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                YearMonth::new(2024, 7)?,
+                change(Some(2500), None),
+            )
+            .await?
+            .expect("Transaction should exist");
+            let transaction = RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                YearMonth::new(2024, 8)?,
+                change(None, Some("Family plan")),
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(
+                transaction.exceptions,
+                vec![
+                    OccurrenceException {
+                        month: YearMonth::new(2024, 6)?,
+                        skip: true,
+                        amount: None,
+                        description: None,
+                    },
+                    OccurrenceException {
+                        month: YearMonth::new(2024, 7)?,
+                        skip: false,
+                        amount: Some(2500),
+                        description: None,
+                    },
+                    OccurrenceException {
+                        month: YearMonth::new(2024, 8)?,
+                        skip: false,
+                        amount: None,
+                        description: Some("Family plan".to_string()),
+                    },
+                ]
+            );
+
+            let occurrences: Vec<_> = RecurringTransaction::occurrences(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                &OccurrenceParams {
+                    from: date(2024, 5, 1),
+                    to: date(2024, 9, 1),
+                },
+            )
+            .await?
+            .expect("Transaction should exist")
+            .into_iter()
+            .map(|occurrence| (occurrence.date, occurrence.amount, occurrence.description))
+            .collect();
+            let netflix = Some("Netflix subscription".to_string());
+            assert_eq!(
+                occurrences,
+                vec![
+                    (date(2024, 5, 1), 1599, netflix.clone()),
+                    (date(2024, 7, 1), 2500, netflix.clone()),
+                    (date(2024, 8, 1), 1599, Some("Family plan".to_string())),
+                    (date(2024, 9, 1), 1599, netflix),
+                ]
+            );
+
+            // Replaces the exception of the month
+            let transaction = RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                YearMonth::new(2024, 6)?,
+                change(Some(999), None),
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(transaction.exceptions.len(), 3);
+            assert_eq!(
+                transaction.exceptions[0],
+                OccurrenceException {
+                    month: YearMonth::new(2024, 6)?,
+                    skip: false,
+                    amount: Some(999),
+                    description: None,
+                }
+            );
+
+            let transaction = RecurringTransaction::remove_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                YearMonth::new(2024, 6)?,
+            )
+            .await?
+            .expect("Exception should exist");
+            assert_eq!(transaction.exceptions.len(), 2);
+            assert!(
+                RecurringTransaction::remove_exception(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    1,
+                    YearMonth::new(2024, 6)?
+                )
+                .await?
+                .is_none()
+            );
+
+            let bob = get_user_by_id(&pool, 2).await;
+            assert!(
+                RecurringTransaction::set_exception(
+                    &pool,
+                    &bob,
+                    &Config::default(),
+                    1,
+                    YearMonth::new(2024, 9)?,
+                    skip()
+                )
+                .await?
+                .is_none()
+            );
+            assert!(
+                RecurringTransaction::remove_exception(
+                    &pool,
+                    &bob,
+                    &Config::default(),
+                    1,
+                    YearMonth::new(2024, 7)?
+                )
+                .await?
+                .is_none()
+            );
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_update_removes_stale_exceptions(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let config = Config::default();
+
+            for (month, params) in [
+                (6, skip()),
+                (7, change(Some(2500), None)),
+                (8, change(None, Some("Family plan"))),
+            ] {
+                RecurringTransaction::set_exception(
+                    &pool,
+                    &user,
+                    &config,
+                    1,
+                    YearMonth::new(2024, month)?,
+                    params,
+                )
+                .await?
+                .expect("Transaction should exist");
+            }
+
+            // Other fields keep the exceptions
+            let transaction = RecurringTransaction::update(
+                &pool,
+                &user,
+                &config,
+                1,
+                RecurringTransactionUpdateParams {
+                    is_expense: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(transaction.exceptions.len(), 3);
+
+            // Only July still has an occurrence
+            let transaction = RecurringTransaction::update(
+                &pool,
+                &user,
+                &config,
+                1,
+                RecurringTransactionUpdateParams {
+                    recurrence: Some(Recurrence::Yearly {
+                        year_from: 2024,
+                        year_to: None,
+                        month: Some(7),
+                        day_of_month: Some(15),
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await?
+            .expect("Transaction should exist");
+            assert_eq!(
+                transaction.exceptions,
+                vec![OccurrenceException {
+                    month: YearMonth::new(2024, 7)?,
+                    skip: false,
+                    amount: Some(2500),
+                    description: None,
+                }]
+            );
+
+            let occurrences: Vec<_> = RecurringTransaction::occurrences(
+                &pool,
+                &user,
+                &config,
+                1,
+                &OccurrenceParams {
+                    from: date(2024, 1, 1),
+                    to: date(2025, 12, 31),
+                },
+            )
+            .await?
+            .expect("Transaction should exist")
+            .into_iter()
+            .map(|occurrence| (occurrence.date, occurrence.amount))
+            .collect();
+            assert_eq!(
+                occurrences,
+                vec![(date(2024, 7, 15), 2500), (date(2025, 7, 15), 1599)]
+            );
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base", "recurring"))]
+        fn test_invalid_exceptions(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+
+            let result = RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                1,
+                YearMonth::new(2025, 1)?,
+                skip(),
+            )
+            .await;
+            assert!(
+                matches!(
+                    result,
+                    Err(ServerError::Generic(StatusCode::BAD_REQUEST, _))
+                ),
+                "No occurrence in the month"
+            );
+
+            for params in [
+                change(None, None),
+                OccurrenceExceptionParams {
+                    amount: Some(Amount(100)),
+                    ..skip()
+                },
+            ] {
+                let result = RecurringTransaction::set_exception(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    1,
+                    YearMonth::new(2024, 6)?,
+                    params,
+                )
+                .await;
+                assert!(
+                    matches!(result, Err(ServerError::SqlxError(_))),
+                    "Violates recurring_transaction_exceptions_override_check"
+                );
+            }
+
+            Ok(())
+        }
+
+        #[sqlx::test(fixtures("base"))]
+        fn test_totals(pool: PgPool) -> anyhow::Result<()> {
+            let user = get_user_by_id(&pool, 1).await;
+            let category = get_category_by_name(&pool, user.id, "Groceries").await;
+            let today = UserSettings::get(&pool, &user)
+                .await?
+                .today(Config::default().timezone);
+            let month = YearMonth::new(today.year(), today.month())?;
+
+            let transaction = RecurringTransaction::create(
+                &pool,
+                &user,
+                RecurringTransactionCreateParams {
+                    recurrence: Recurrence::Monthly {
+                        month_from: month.clone(),
+                        month_to: None,
+                        day_of_month: None,
+                    },
+                    is_expense: true,
+                    amount: Amount(1000),
+                    description: None,
+                    category_id: UnvalidatedCategoryId::from(category.id),
+                    shop_id: None,
+                },
+            )
+            .await?
+            .expect("Failed to create recurring transaction");
+
+            let expenses = async || -> anyhow::Result<i64> {
+                Ok(RecurringTransaction::fetch(
+                    &pool,
+                    &user,
+                    &Config::default(),
+                    RecurringTransactionFetchParams::default(),
+                    Pagination::default(),
+                )
+                .await?
+                .meta
+                .expenses)
+            };
+            assert_eq!(expenses().await?, 1000);
+
+            RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                transaction.id,
+                month.clone(),
+                change(Some(1500), None),
+            )
+            .await?;
+            assert_eq!(expenses().await?, 1500);
+
+            RecurringTransaction::set_exception(
+                &pool,
+                &user,
+                &Config::default(),
+                transaction.id,
+                month,
+                skip(),
+            )
+            .await?;
+            assert_eq!(expenses().await?, 0);
+
+            Ok(())
+        }
+    }
+
     mod tests_remove {
         use super::*;
 
@@ -1,18 +1,42 @@
-use garde::Validate;
+use std::fmt;
+
+use chrono::{Datelike, Months, NaiveDate, TimeDelta};
+use garde::{Path, Report, Validate};
 use serde::{Deserialize, Serialize};
 use sqlx::Type;
+use utoipa::ToSchema;
 
 use crate::app::resources::recurring_transactions::year_month::YearMonth;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, Type)]
+/// Declared in the order of the database enum, from short to long recurrences
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Type, ToSchema)]
 #[serde(rename_all = "camelCase")]
 #[sqlx(type_name = "recurrence_frequency", rename_all = "lowercase")]
 pub enum RecurrenceFrequency {
+    Weekly,
+    Biweekly,
     Monthly,
+    Quarterly,
+    #[sqlx(rename = "every_n_months")]
+    EveryNMonths,
     Yearly,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, Validate)]
+impl fmt::Display for RecurrenceFrequency {
+    /// Same as the database value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecurrenceFrequency::Weekly => write!(f, "weekly"),
+            RecurrenceFrequency::Biweekly => write!(f, "biweekly"),
+            RecurrenceFrequency::Monthly => write!(f, "monthly"),
+            RecurrenceFrequency::Quarterly => write!(f, "quarterly"),
+            RecurrenceFrequency::EveryNMonths => write!(f, "every_n_months"),
+            RecurrenceFrequency::Yearly => write!(f, "yearly"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, Validate, ToSchema)]
 #[serde(
     rename_all = "camelCase",
     rename_all_fields = "camelCase",
@@ -20,21 +44,363 @@ pub enum RecurrenceFrequency {
     deny_unknown_fields
 )]
 pub enum Recurrence {
+    // utoipa doesn't support `rename_all_fields`
+    #[schema(rename_all = "camelCase")]
     Yearly {
         // 262143 is the max. year allowed for chrono NaiveDate
         #[garde(range(min = 1, max = 262143))]
+        #[schema(minimum = 1, maximum = 262143)]
         year_from: i32,
         #[garde(custom(validate_year_range(self)))]
         year_to: Option<i32>,
+        /// Month of the occurrences, requires `dayOfMonth`. Without both, occurrences fall on the first of January.
+        #[garde(range(min = 1, max = 12))]
+        #[schema(minimum = 1, maximum = 12)]
+        month: Option<u32>,
+        /// Day of `month`. The 29th of February falls on the 28th in other years.
+        #[garde(range(min = 1, max = 31), custom(validate_day_of_year(self)))]
+        #[schema(minimum = 1, maximum = 31)]
+        day_of_month: Option<u32>,
     },
+    #[schema(rename_all = "camelCase")]
     Monthly {
         #[garde(dive)]
         month_from: YearMonth,
         #[garde(dive, custom(validate_month_range(self)))]
         month_to: Option<YearMonth>,
+        /// Day of the occurrences, the last day of shorter months. Without it, occurrences fall on the first.
+        #[garde(range(min = 1, max = 31))]
+        #[schema(minimum = 1, maximum = 31)]
+        day_of_month: Option<u32>,
+    },
+    /// Every week on the weekday of `dateFrom`
+    #[schema(rename_all = "camelCase")]
+    Weekly {
+        /// Anchor day and first occurrence
+        #[garde(skip)]
+        date_from: NaiveDate,
+        /// Last day an occurrence may fall on
+        #[garde(custom(validate_date_range(self)))]
+        date_to: Option<NaiveDate>,
+    },
+    /// Every second week on the weekday of `dateFrom`
+    #[schema(rename_all = "camelCase")]
+    Biweekly {
+        /// Anchor day and first occurrence
+        #[garde(skip)]
+        date_from: NaiveDate,
+        /// Last day an occurrence may fall on
+        #[garde(custom(validate_date_range(self)))]
+        date_to: Option<NaiveDate>,
+    },
+    /// Every three months, starting with `monthFrom`
+    #[schema(rename_all = "camelCase")]
+    Quarterly {
+        #[garde(dive)]
+        month_from: YearMonth,
+        #[garde(dive, custom(validate_month_range(self)))]
+        month_to: Option<YearMonth>,
+        /// Like for monthly recurrences
+        #[garde(range(min = 1, max = 31))]
+        #[schema(minimum = 1, maximum = 31)]
+        day_of_month: Option<u32>,
+    },
+    /// Every `months` months, starting with `monthFrom`
+    #[schema(rename_all = "camelCase")]
+    EveryNMonths {
+        #[garde(range(min = 2, max = 120))]
+        #[schema(minimum = 2, maximum = 120)]
+        months: u32,
+        #[garde(dive)]
+        month_from: YearMonth,
+        #[garde(dive, custom(validate_month_range(self)))]
+        month_to: Option<YearMonth>,
+        /// Like for monthly recurrences
+        #[garde(range(min = 1, max = 31))]
+        #[schema(minimum = 1, maximum = 31)]
+        day_of_month: Option<u32>,
     },
 }
 
+/// Columns of a [`Recurrence`] in the `recurring_transactions` table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawRecurrence {
+    pub frequency: RecurrenceFrequency,
+    /// First day of the first month or year, or the first day of weekly recurrences
+    pub interval_from: NaiveDate,
+    pub interval_to: Option<NaiveDate>,
+    /// Months between two occurrences, only stored for [`Recurrence::EveryNMonths`]
+    pub interval_months: Option<i32>,
+    pub day_of_month: Option<i16>,
+    /// Only stored for [`Recurrence::Yearly`]
+    pub month_of_year: Option<i16>,
+}
+
+impl Recurrence {
+    pub fn frequency(&self) -> RecurrenceFrequency {
+        match self {
+            Recurrence::Weekly { .. } => RecurrenceFrequency::Weekly,
+            Recurrence::Biweekly { .. } => RecurrenceFrequency::Biweekly,
+            Recurrence::Yearly { .. } => RecurrenceFrequency::Yearly,
+            Recurrence::Monthly { .. } => RecurrenceFrequency::Monthly,
+            Recurrence::Quarterly { .. } => RecurrenceFrequency::Quarterly,
+            Recurrence::EveryNMonths { .. } => RecurrenceFrequency::EveryNMonths,
+        }
+    }
+
+    /// The columns to store. `None` if a year is out of the range of dates.
+    pub fn to_raw(&self) -> Option<RawRecurrence> {
+        let year_start = |year| NaiveDate::from_ymd_opt(year, 1, 1);
+        let (interval_from, interval_to) = match self {
+            Recurrence::Weekly { date_from, date_to }
+            | Recurrence::Biweekly { date_from, date_to } => (*date_from, *date_to),
+            Recurrence::Yearly {
+                year_from, year_to, ..
+            } => (
+                year_start(*year_from)?,
+                match year_to {
+                    Some(year_to) => Some(year_start(*year_to)?),
+                    None => None,
+                },
+            ),
+            Recurrence::Monthly {
+                month_from,
+                month_to,
+                ..
+            }
+            | Recurrence::Quarterly {
+                month_from,
+                month_to,
+                ..
+            }
+            | Recurrence::EveryNMonths {
+                month_from,
+                month_to,
+                ..
+            } => (
+                month_from.to_naive_date(),
+                month_to.as_ref().map(YearMonth::to_naive_date),
+            ),
+        };
+        let day_of_month = match self {
+            Recurrence::Weekly { .. } | Recurrence::Biweekly { .. } => None,
+            Recurrence::Yearly { day_of_month, .. }
+            | Recurrence::Monthly { day_of_month, .. }
+            | Recurrence::Quarterly { day_of_month, .. }
+            | Recurrence::EveryNMonths { day_of_month, .. } => *day_of_month,
+        };
+
+        Some(RawRecurrence {
+            frequency: self.frequency(),
+            interval_from,
+            interval_to,
+            interval_months: match self {
+                Recurrence::EveryNMonths { months, .. } => Some(*months as i32),
+                _ => None,
+            },
+            day_of_month: day_of_month.map(|day| day as i16),
+            month_of_year: match self {
+                Recurrence::Yearly { month, .. } => month.map(|month| month as i16),
+                _ => None,
+            },
+        })
+    }
+
+    /// Dates of the occurrences from `from` to `to`, both inclusive
+    pub fn occurrences(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        match self {
+            Recurrence::Weekly { date_from, date_to } => {
+                weekly_occurrences(*date_from, *date_to, 7, from, to)
+            }
+            Recurrence::Biweekly { date_from, date_to } => {
+                weekly_occurrences(*date_from, *date_to, 14, from, to)
+            }
+            Recurrence::Yearly {
+                year_from,
+                year_to,
+                month,
+                day_of_month,
+            } => {
+                let month = month.unwrap_or(1);
+                monthly_occurrences(
+                    &YearMonth {
+                        year: *year_from,
+                        month,
+                    },
+                    year_to.map(|year| YearMonth { year, month }).as_ref(),
+                    12,
+                    *day_of_month,
+                    from,
+                    to,
+                )
+            }
+            Recurrence::Monthly {
+                month_from,
+                month_to,
+                day_of_month,
+            } => monthly_occurrences(month_from, month_to.as_ref(), 1, *day_of_month, from, to),
+            Recurrence::Quarterly {
+                month_from,
+                month_to,
+                day_of_month,
+            } => monthly_occurrences(month_from, month_to.as_ref(), 3, *day_of_month, from, to),
+            Recurrence::EveryNMonths {
+                months,
+                month_from,
+                month_to,
+                day_of_month,
+            } => monthly_occurrences(
+                month_from,
+                month_to.as_ref(),
+                *months,
+                *day_of_month,
+                from,
+                to,
+            ),
+        }
+    }
+}
+
+fn missing(field: &str) -> Report {
+    let mut report = Report::new();
+    report.append(Path::new(field), garde::Error::new("missing or invalid"));
+    report
+}
+
+impl TryFrom<RawRecurrence> for Recurrence {
+    type Error = Report;
+
+    fn try_from(raw: RawRecurrence) -> Result<Self, Report> {
+        let month_from = YearMonth::new(raw.interval_from.year(), raw.interval_from.month())?;
+        let month_to = raw
+            .interval_to
+            .map(|value| YearMonth::new(value.year(), value.month()))
+            .transpose()?;
+        let day_of_month = raw.day_of_month.map(|day| day as u32);
+        Ok(match raw.frequency {
+            RecurrenceFrequency::Weekly => Recurrence::Weekly {
+                date_from: raw.interval_from,
+                date_to: raw.interval_to,
+            },
+            RecurrenceFrequency::Biweekly => Recurrence::Biweekly {
+                date_from: raw.interval_from,
+                date_to: raw.interval_to,
+            },
+            RecurrenceFrequency::Yearly => Recurrence::Yearly {
+                year_from: raw.interval_from.year(),
+                year_to: raw.interval_to.map(|value| value.year()),
+                month: raw.month_of_year.map(|month| month as u32),
+                day_of_month,
+            },
+            RecurrenceFrequency::Monthly => Recurrence::Monthly {
+                month_from,
+                month_to,
+                day_of_month,
+            },
+            RecurrenceFrequency::Quarterly => Recurrence::Quarterly {
+                month_from,
+                month_to,
+                day_of_month,
+            },
+            RecurrenceFrequency::EveryNMonths => Recurrence::EveryNMonths {
+                months: raw
+                    .interval_months
+                    .and_then(|months| u32::try_from(months).ok())
+                    .ok_or_else(|| missing("interval_months"))?,
+                month_from,
+                month_to,
+                day_of_month,
+            },
+        })
+    }
+}
+
+/// Every `step_days` days from `date_from`
+fn weekly_occurrences(
+    date_from: NaiveDate,
+    date_to: Option<NaiveDate>,
+    step_days: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NaiveDate> {
+    let end = date_to.map_or(to, |date_to| date_to.min(to));
+    // Skip the occurrences before `from`
+    let skipped = ((from - date_from).num_days().max(0) + step_days - 1) / step_days;
+    let mut dates = Vec::new();
+    let mut date = date_from.checked_add_signed(TimeDelta::days(skipped * step_days));
+    while let Some(current) = date {
+        if current > end {
+            break;
+        }
+        dates.push(current);
+        date = current.checked_add_signed(TimeDelta::days(step_days));
+    }
+    dates
+}
+
+/// Every `step` months from `month_from` on `day_of_month`, or on the first
+fn monthly_occurrences(
+    month_from: &YearMonth,
+    month_to: Option<&YearMonth>,
+    step: u32,
+    day_of_month: Option<u32>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NaiveDate> {
+    let index = |year: i32, month: u32| i64::from(year) * 12 + i64::from(month) - 1;
+    let step = i64::from(step);
+    let first = index(month_from.year, month_from.month);
+    let last = month_to.map(|month_to| index(month_to.year, month_to.month));
+    // Skip the months before the month of `from`
+    let skipped = (index(from.year(), from.month()) - first).max(0) / step * step;
+
+    let mut dates = Vec::new();
+    let mut current = first + skipped;
+    while last.is_none_or(|last| current <= last) {
+        let Some(date) = clamped_date(current, day_of_month) else {
+            break;
+        };
+        if date > to {
+            break;
+        }
+        if date >= from {
+            dates.push(date);
+        }
+        current += step;
+    }
+    dates
+}
+
+/// The day of the month with the given index since year 0, or its last day if the month is shorter
+fn clamped_date(month_index: i64, day_of_month: Option<u32>) -> Option<NaiveDate> {
+    let year = i32::try_from(month_index.div_euclid(12)).ok()?;
+    let first = NaiveDate::from_ymd_opt(year, month_index.rem_euclid(12) as u32 + 1, 1)?;
+    let last_day = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+    first.with_day(day_of_month.unwrap_or(1).min(last_day))
+}
+
+fn validate_date_range(
+    context: &Recurrence,
+) -> impl FnOnce(&Option<NaiveDate>, &()) -> garde::Result {
+    move |value, _| {
+        let Some(date_to) = value else {
+            return Ok(());
+        };
+
+        match context {
+            Recurrence::Weekly { date_from, .. } | Recurrence::Biweekly { date_from, .. } => {
+                if date_to < date_from {
+                    return Err(garde::Error::new(format!(
+                        "less than date_from={date_from}"
+                    )));
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+}
+
 fn validate_year_range(context: &Recurrence) -> impl FnOnce(&Option<i32>, &()) -> garde::Result {
     move |value, _| {
         let Some(year_to) = value else {
@@ -49,12 +415,33 @@ fn validate_year_range(context: &Recurrence) -> impl FnOnce(&Option<i32>, &()) -
                     )));
                 }
             }
-            Recurrence::Monthly { .. } => unreachable!(),
+            _ => unreachable!(),
         }
         Ok(())
     }
 }
 
+fn validate_day_of_year(context: &Recurrence) -> impl FnOnce(&Option<u32>, &()) -> garde::Result {
+    move |value, _| {
+        let Recurrence::Yearly { month, .. } = context else {
+            unreachable!()
+        };
+
+        match (month, value) {
+            (None, None) => Ok(()),
+            (Some(month), Some(day)) => {
+                // Leap years have the most days
+                if (1..=12).contains(month) && NaiveDate::from_ymd_opt(2000, *month, *day).is_none()
+                {
+                    return Err(garde::Error::new(format!("month={month} has no day {day}")));
+                }
+                Ok(())
+            }
+            _ => Err(garde::Error::new("requires month and vice versa")),
+        }
+    }
+}
+
 fn validate_month_range(
     context: &Recurrence,
 ) -> impl FnOnce(&Option<YearMonth>, &()) -> garde::Result {
@@ -64,7 +451,9 @@ fn validate_month_range(
         };
 
         match context {
-            Recurrence::Monthly { month_from, .. } => {
+            Recurrence::Monthly { month_from, .. }
+            | Recurrence::Quarterly { month_from, .. }
+            | Recurrence::EveryNMonths { month_from, .. } => {
                 if month_to.year < month_from.year
                     || (month_to.year == month_from.year && month_to.month < month_from.month)
                 {
@@ -74,21 +463,12 @@ fn validate_month_range(
                     )));
                 }
             }
-            Recurrence::Yearly { .. } => unreachable!(),
+            _ => unreachable!(),
         }
         Ok(())
     }
 }
 
-// impl From<Recurrence> for NaiveDate {
-//     fn from(value: Recurrence) -> Self {
-//         match value {
-//             Recurrence::Yearly { year_from, year_to } => NaiveDate::from_isoywd_opt(year, week, weekday),
-//             Recurrence::Monthly { month_from, month_to } => todo!(),
-//         }
-//     }
-// }
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,6 +482,8 @@ mod tests {
         Recurrence::Yearly {
             year_from: 2020,
             year_to: Some(2025),
+            month: None,
+            day_of_month: None,
         }
         .validate()
         .expect("Valid range");
@@ -109,6 +491,8 @@ mod tests {
         Recurrence::Yearly {
             year_from: 2023,
             year_to: Some(2023),
+            month: None,
+            day_of_month: None,
         }
         .validate()
         .expect("Same year");
@@ -116,6 +500,8 @@ mod tests {
         Recurrence::Yearly {
             year_from: 2020,
             year_to: None,
+            month: None,
+            day_of_month: None,
         }
         .validate()
         .expect("No end year");
@@ -126,6 +512,8 @@ mod tests {
         Recurrence::Yearly {
             year_from: 2025,
             year_to: Some(2020),
+            month: None,
+            day_of_month: None,
         }
         .validate()
         .expect_err("Invalid range");
@@ -133,6 +521,8 @@ mod tests {
         Recurrence::Yearly {
             year_from: -100,
             year_to: Some(100),
+            month: None,
+            day_of_month: None,
         }
         .validate()
         .expect_err("Negative years");
@@ -143,6 +533,7 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2020, 3),
             month_to: Some(year_month(2021, 5)),
+            day_of_month: None,
         }
         .validate()
         .expect("Valid range different years");
@@ -150,6 +541,7 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2023, 3),
             month_to: Some(year_month(2023, 8)),
+            day_of_month: None,
         }
         .validate()
         .expect("Valid range same year");
@@ -157,6 +549,7 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2023, 6),
             month_to: Some(year_month(2023, 6)),
+            day_of_month: None,
         }
         .validate()
         .expect("Same year and month");
@@ -164,6 +557,7 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2023, 1),
             month_to: None,
+            day_of_month: None,
         }
         .validate()
         .expect("No end month");
@@ -171,6 +565,7 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2023, 12),
             month_to: Some(year_month(2024, 1)),
+            day_of_month: None,
         }
         .validate()
         .expect("Year boundary transition");
@@ -181,6 +576,7 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2023, 5),
             month_to: Some(year_month(2022, 10)),
+            day_of_month: None,
         }
         .validate()
         .expect_err("Invalid range different years");
@@ -188,8 +584,266 @@ mod tests {
         Recurrence::Monthly {
             month_from: year_month(2023, 8),
             month_to: Some(year_month(2023, 3)),
+            day_of_month: None,
         }
         .validate()
         .expect_err("Invalid range same year");
     }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_weekly_recurrence() {
+        Recurrence::Weekly {
+            date_from: date(2024, 3, 14),
+            date_to: Some(date(2024, 3, 14)),
+        }
+        .validate()
+        .expect("Same day");
+
+        Recurrence::Biweekly {
+            date_from: date(2024, 3, 14),
+            date_to: None,
+        }
+        .validate()
+        .expect("No end date");
+
+        Recurrence::Biweekly {
+            date_from: date(2024, 3, 14),
+            date_to: Some(date(2024, 3, 13)),
+        }
+        .validate()
+        .expect_err("Invalid range");
+    }
+
+    #[test]
+    fn test_every_n_months_recurrence() {
+        Recurrence::Quarterly {
+            month_from: year_month(2024, 2),
+            month_to: Some(year_month(2024, 1)),
+            day_of_month: None,
+        }
+        .validate()
+        .expect_err("Invalid range");
+
+        for (months, valid) in [(1, false), (2, true), (6, true), (120, true), (121, false)] {
+            let result = Recurrence::EveryNMonths {
+                months,
+                month_from: year_month(2024, 1),
+                month_to: None,
+                day_of_month: None,
+            }
+            .validate();
+            assert_eq!(result.is_ok(), valid, "{months} months");
+        }
+
+        Recurrence::EveryNMonths {
+            months: 6,
+            month_from: year_month(2024, 7),
+            month_to: Some(year_month(2024, 6)),
+            day_of_month: None,
+        }
+        .validate()
+        .expect_err("Invalid range");
+    }
+
+    #[test]
+    fn test_raw_round_trip() -> anyhow::Result<()> {
+        for recurrence in [
+            Recurrence::Weekly {
+                date_from: date(2024, 3, 14),
+                date_to: None,
+            },
+            Recurrence::Biweekly {
+                date_from: date(2024, 3, 14),
+                date_to: Some(date(2024, 12, 31)),
+            },
+            Recurrence::Monthly {
+                month_from: year_month(2024, 3),
+                month_to: None,
+                day_of_month: Some(31),
+            },
+            Recurrence::Quarterly {
+                month_from: year_month(2024, 2),
+                month_to: Some(year_month(2025, 11)),
+                day_of_month: None,
+            },
+            Recurrence::EveryNMonths {
+                months: 6,
+                month_from: year_month(2024, 4),
+                month_to: None,
+                day_of_month: None,
+            },
+            Recurrence::Yearly {
+                year_from: 2024,
+                year_to: Some(2030),
+                month: Some(2),
+                day_of_month: Some(29),
+            },
+        ] {
+            let raw = recurrence.to_raw().expect("Dates in range");
+            assert_eq!(Recurrence::try_from(raw)?, recurrence);
+        }
+
+        Recurrence::try_from(RawRecurrence {
+            frequency: RecurrenceFrequency::EveryNMonths,
+            interval_from: date(2024, 1, 1),
+            interval_to: None,
+            interval_months: None,
+            day_of_month: None,
+            month_of_year: None,
+        })
+        .expect_err("Missing number of months");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_yearly_day_validation() {
+        let yearly = |month, day_of_month| Recurrence::Yearly {
+            year_from: 2024,
+            year_to: None,
+            month,
+            day_of_month,
+        };
+
+        yearly(Some(2), Some(29)).validate().expect("Leap day");
+        yearly(Some(12), Some(31)).validate().expect("Last day");
+        yearly(None, None).validate().expect("No day");
+        yearly(Some(4), Some(31))
+            .validate()
+            .expect_err("April has 30 days");
+        yearly(Some(2), Some(30))
+            .validate()
+            .expect_err("February has at most 29 days");
+        yearly(Some(3), None)
+            .validate()
+            .expect_err("Month without day");
+        yearly(None, Some(3))
+            .validate()
+            .expect_err("Day without month");
+        yearly(Some(13), Some(1))
+            .validate()
+            .expect_err("Invalid month");
+
+        Recurrence::Monthly {
+            month_from: year_month(2024, 1),
+            month_to: None,
+            day_of_month: Some(32),
+        }
+        .validate()
+        .expect_err("Invalid day");
+    }
+
+    #[test]
+    fn test_monthly_occurrences() {
+        let monthly = |day_of_month| Recurrence::Monthly {
+            month_from: year_month(2024, 1),
+            month_to: Some(year_month(2024, 4)),
+            day_of_month,
+        };
+
+        // Shorter months are clamped to their last day
+        assert_eq!(
+            monthly(Some(31)).occurrences(date(2020, 1, 1), date(2030, 1, 1)),
+            [
+                date(2024, 1, 31),
+                date(2024, 2, 29),
+                date(2024, 3, 31),
+                date(2024, 4, 30)
+            ]
+        );
+        assert_eq!(
+            monthly(Some(31)).occurrences(date(2024, 2, 15), date(2024, 3, 30)),
+            [date(2024, 2, 29)]
+        );
+        assert_eq!(
+            monthly(None).occurrences(date(2024, 3, 1), date(2024, 12, 31)),
+            [date(2024, 3, 1), date(2024, 4, 1)]
+        );
+        assert!(
+            monthly(Some(3))
+                .occurrences(date(2024, 3, 4), date(2024, 3, 2))
+                .is_empty()
+        );
+
+        let quarterly = Recurrence::Quarterly {
+            month_from: year_month(2023, 11),
+            month_to: None,
+            day_of_month: Some(15),
+        };
+        assert_eq!(
+            quarterly.occurrences(date(2024, 1, 1), date(2024, 12, 31)),
+            [
+                date(2024, 2, 15),
+                date(2024, 5, 15),
+                date(2024, 8, 15),
+                date(2024, 11, 15)
+            ]
+        );
+
+        let every_six_months = Recurrence::EveryNMonths {
+            months: 6,
+            month_from: year_month(2024, 4),
+            month_to: Some(year_month(2025, 4)),
+            day_of_month: None,
+        };
+        assert_eq!(
+            every_six_months.occurrences(date(2024, 10, 2), date(2026, 12, 31)),
+            [date(2025, 4, 1)]
+        );
+    }
+
+    #[test]
+    fn test_yearly_occurrences() {
+        let leap_day = Recurrence::Yearly {
+            year_from: 2023,
+            year_to: Some(2025),
+            month: Some(2),
+            day_of_month: Some(29),
+        };
+        assert_eq!(
+            leap_day.occurrences(date(2000, 1, 1), date(2100, 1, 1)),
+            [date(2023, 2, 28), date(2024, 2, 29), date(2025, 2, 28)]
+        );
+
+        let new_year = Recurrence::Yearly {
+            year_from: 2023,
+            year_to: None,
+            month: None,
+            day_of_month: None,
+        };
+        assert_eq!(
+            new_year.occurrences(date(2023, 1, 2), date(2025, 1, 1)),
+            [date(2024, 1, 1), date(2025, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_weekly_occurrences() {
+        // The 14th of March 2024 is a Thursday
+        let weekly = Recurrence::Weekly {
+            date_from: date(2024, 3, 14),
+            date_to: None,
+        };
+        assert_eq!(
+            weekly.occurrences(date(2024, 4, 1), date(2024, 4, 20)),
+            [date(2024, 4, 4), date(2024, 4, 11), date(2024, 4, 18)]
+        );
+        assert_eq!(
+            weekly.occurrences(date(2024, 3, 1), date(2024, 3, 21)),
+            [date(2024, 3, 14), date(2024, 3, 21)]
+        );
+
+        let biweekly = Recurrence::Biweekly {
+            date_from: date(2024, 3, 14),
+            date_to: Some(date(2024, 4, 20)),
+        };
+        assert_eq!(
+            biweekly.occurrences(date(2024, 3, 15), date(2024, 12, 31)),
+            [date(2024, 3, 28), date(2024, 4, 11)]
+        );
+    }
 }
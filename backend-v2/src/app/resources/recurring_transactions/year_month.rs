@@ -1,8 +1,12 @@
 use std::fmt::{self, Display};
 
-use chrono::NaiveDate;
+use chrono::{Months, NaiveDate};
 use garde::{Report, Validate};
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Visitor};
+use utoipa::{
+    PartialSchema, ToSchema,
+    openapi::{ObjectBuilder, RefOr, Schema, Type},
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Validate)]
 pub struct YearMonth {
@@ -24,6 +28,13 @@ impl YearMonth {
         NaiveDate::from_ymd_opt(self.year, self.month, 1)
             .expect("Couldn't create NaiveDate from validated YearMonth")
     }
+
+    /// Last day of the month
+    pub fn last_day(&self) -> NaiveDate {
+        (self.to_naive_date() + Months::new(1))
+            .pred_opt()
+            .expect("Couldn't create NaiveDate from validated YearMonth")
+    }
 }
 
 impl Display for YearMonth {
@@ -91,3 +102,16 @@ impl<'de> Deserialize<'de> for YearMonth {
         deserializer.deserialize_str(YearMonthVisitor)
     }
 }
+
+impl PartialSchema for YearMonth {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .pattern(Some(r"^\d{4}-(0[1-9]|1[0-2])$"))
+            .description(Some("Month in `YYYY-MM` format"))
+            .examples(["2024-01"])
+            .into()
+    }
+}
+
+impl ToSchema for YearMonth {}
@@ -0,0 +1,415 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDate, Utc};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool, types::Json};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app::{
+        api::pagination::Pagination,
+        config::Config,
+        resources::Resource,
+        transactions::{
+            Amount, Order, OrderKey, Ordering,
+            date_range::DateRange,
+            filters::{MAX_IDS, TransactionFilterParams, TransactionFilters},
+            validate_shared_order, validate_shared_order_key,
+        },
+    },
+    errors::ServerError,
+    users::User,
+};
+
+/// A named set of oneoff transaction list parameters, applied with `GET /api/transactions/oneoff?view={id}`
+#[derive(Clone, Debug, Serialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedView {
+    pub id: i32,
+    pub name: String,
+    pub user_id: i32,
+    #[schema(value_type = SavedViewParams)]
+    pub params: Json<SavedViewParams>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The saved list parameters, with the same meaning as the query parameters of the same name. Id lists are arrays
+/// instead of comma separated strings.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SavedViewParams {
+    #[garde(skip)]
+    pub is_expense: Option<bool>,
+    #[garde(skip)]
+    pub date_from: Option<NaiveDate>,
+    #[garde(skip)]
+    pub date_to: Option<NaiveDate>,
+    #[garde(skip)]
+    pub range: Option<DateRange>,
+    #[garde(dive)]
+    pub amount_from: Option<Amount>,
+    #[garde(dive)]
+    pub amount_to: Option<Amount>,
+    #[garde(length(min = 1, max = MAX_IDS), inner(inner(range(min = 1))))]
+    pub category_ids: Option<Vec<i32>>,
+    #[garde(length(min = 1, max = MAX_IDS), inner(inner(range(min = 1))))]
+    pub exclude_category_ids: Option<Vec<i32>>,
+    #[garde(length(min = 1, max = MAX_IDS), inner(inner(range(min = 1))))]
+    pub shop_ids: Option<Vec<i32>>,
+    #[garde(length(min = 1, max = MAX_IDS), inner(inner(range(min = 1))))]
+    pub exclude_shop_ids: Option<Vec<i32>>,
+    #[garde(length(min = 1))]
+    pub description_contains: Option<String>,
+    #[garde(skip)]
+    pub description_is_null: Option<bool>,
+    #[garde(custom(validate_shared_order))]
+    pub order: Option<Order>,
+    #[garde(skip)]
+    pub ordering: Option<Ordering>,
+    #[garde(custom(validate_shared_order_key))]
+    pub order_key: Option<OrderKey>,
+}
+
+impl SavedViewParams {
+    /// Check that all ids belong to the user
+    async fn validate_ids(&self, database: &PgPool, user: &User) -> Result<(), ServerError> {
+        let params = TransactionFilterParams {
+            category_ids: self.category_ids.clone().map(Into::into),
+            exclude_category_ids: self.exclude_category_ids.clone().map(Into::into),
+            shop_ids: self.shop_ids.clone().map(Into::into),
+            exclude_shop_ids: self.exclude_shop_ids.clone().map(Into::into),
+            ..Default::default()
+        };
+        TransactionFilters::resolve(database, user, params).await?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SavedViewCreate {
+    #[garde(length(graphemes, min = 1))]
+    #[schema(min_length = 1)]
+    name: String,
+    #[garde(dive)]
+    params: SavedViewParams,
+}
+
+impl SavedViewCreate {
+    #[cfg(test)]
+    pub fn new(name: &str, params: SavedViewParams) -> Self {
+        SavedViewCreate {
+            name: name.to_owned(),
+            params,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct SavedViewFetch {
+    #[garde(length(graphemes, min = 1))]
+    #[param(min_length = 1)]
+    name: Option<String>,
+}
+
+impl SavedView {
+    async fn find(database: &PgPool, user: &User, id: i32) -> Result<Option<Self>, ServerError> {
+        let view = sqlx::query_as!(
+            SavedView,
+            r#"
+            SELECT id, name, user_id, params as "params: Json<SavedViewParams>", created_at, updated_at
+            FROM saved_views
+            WHERE user_id = $1 AND id = $2
+            "#,
+            user.id,
+            id
+        )
+        .fetch_optional(database)
+        .await?;
+
+        Ok(view)
+    }
+
+    /// Parameters of a view of the user, views of other users are rejected
+    pub async fn load(
+        database: &PgPool,
+        user: &User,
+        id: i32,
+    ) -> Result<SavedViewParams, ServerError> {
+        match Self::find(database, user, id).await? {
+            Some(view) => Ok(view.params.0),
+            None => Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some("Invalid view id".to_owned()),
+            )),
+        }
+    }
+}
+
+impl Resource for SavedView {
+    type CreateParams = SavedViewCreate;
+    type FetchParams = SavedViewFetch;
+    type UpdateParams = SavedViewCreate;
+    type ReturnType = SavedView;
+    type VecReturnType = Vec<Self::ReturnType>;
+    type Error = ServerError;
+
+    async fn create(
+        database: &PgPool,
+        user: &User,
+        params: Self::CreateParams,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        params.params.validate_ids(database, user).await?;
+
+        let view = sqlx::query_as!(
+            SavedView,
+            r#"
+            INSERT INTO saved_views (user_id, name, params) VALUES ($1, $2, $3)
+            RETURNING id, name, user_id, params as "params: Json<SavedViewParams>", created_at, updated_at
+            "#,
+            user.id,
+            params.name,
+            Json(params.params) as _
+        )
+        .fetch_one(database)
+        .await?;
+
+        Ok(Some(view))
+    }
+
+    async fn fetch(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        params: Self::FetchParams,
+        Pagination { limit, offset }: Pagination,
+    ) -> Result<Self::VecReturnType, Self::Error> {
+        let views = sqlx::query_as!(
+            SavedView,
+            r#"
+            SELECT id, name, user_id, params as "params: Json<SavedViewParams>", created_at, updated_at
+            FROM saved_views
+            WHERE user_id = $1 AND ($2::text IS NULL OR name = $2)
+            ORDER BY name, id
+            LIMIT $3 OFFSET $4
+            "#,
+            user.id,
+            params.name,
+            limit.0 as i64,
+            offset.0 as i64
+        )
+        .fetch_all(database)
+        .await?;
+
+        Ok(views)
+    }
+
+    async fn get_by_id(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        id: i32,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        Self::find(database, user, id).await
+    }
+
+    async fn update(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        id: i32,
+        params: Self::UpdateParams,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        params.params.validate_ids(database, user).await?;
+
+        let view = sqlx::query_as!(
+            SavedView,
+            r#"
+            UPDATE saved_views SET name = $3, params = $4
+            WHERE user_id = $1 AND id = $2
+            RETURNING id, name, user_id, params as "params: Json<SavedViewParams>", created_at, updated_at
+            "#,
+            user.id,
+            id,
+            params.name,
+            Json(params.params) as _
+        )
+        .fetch_optional(database)
+        .await?;
+
+        Ok(view)
+    }
+
+    async fn remove(database: &PgPool, user: &User, id: i32) -> Result<u64, Self::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM saved_views WHERE user_id = $1 AND id = $2",
+            user.id,
+            id
+        )
+        .execute(database)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    fn groceries_view() -> SavedViewParams {
+        SavedViewParams {
+            date_from: NaiveDate::from_ymd_opt(2024, 1, 1),
+            category_ids: Some(vec![1]),
+            ordering: Some(Ordering::Desc),
+            ..Default::default()
+        }
+    }
+
+    #[sqlx::test(fixtures("base"))]
+    async fn test_create_and_get(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let view = SavedView::create(
+            &pool,
+            &user,
+            SavedViewCreate::new("Groceries", groceries_view()),
+        )
+        .await?
+        .expect("Failed to create view");
+        assert_eq!(view.name, "Groceries");
+        assert_eq!(view.user_id, user.id);
+        assert_eq!(view.params.category_ids, Some(vec![1]));
+
+        let params = SavedView::load(&pool, &user, view.id).await?;
+        assert_eq!(params.date_from, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert!(matches!(params.ordering, Some(Ordering::Desc)));
+        assert!(params.order_key.is_none());
+
+        // Names are unique per user
+        SavedView::create(
+            &pool,
+            &user,
+            SavedViewCreate::new("Groceries", SavedViewParams::default()),
+        )
+        .await
+        .expect_err("Duplicate view name was accepted");
+        let bob = get_user_by_id(&pool, 2).await;
+        SavedView::create(
+            &pool,
+            &bob,
+            SavedViewCreate::new("Groceries", SavedViewParams::default()),
+        )
+        .await?
+        .expect("Failed to create view with the same name for another user");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("base"))]
+    async fn test_other_users_ids_rejected(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        // Category 14 belongs to Bob
+        let params = SavedViewParams {
+            exclude_category_ids: Some(vec![14]),
+            ..Default::default()
+        };
+        SavedView::create(&pool, &user, SavedViewCreate::new("Not mine", params))
+            .await
+            .expect_err("View with category of another user was accepted");
+
+        let view = SavedView::create(&pool, &user, SavedViewCreate::new("Mine", groceries_view()))
+            .await?
+            .expect("Failed to create view");
+        let params = SavedViewParams {
+            shop_ids: Some(vec![16]),
+            ..Default::default()
+        };
+        SavedView::update(
+            &pool,
+            &user,
+            &Config::default(),
+            view.id,
+            SavedViewCreate::new("Mine", params),
+        )
+        .await
+        .expect_err("Update with shop of another user was accepted");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("base"))]
+    async fn test_user_isolation(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+        let bob = get_user_by_id(&pool, 2).await;
+
+        let view = SavedView::create(
+            &pool,
+            &user,
+            SavedViewCreate::new("Groceries", groceries_view()),
+        )
+        .await?
+        .expect("Failed to create view");
+
+        assert!(
+            SavedView::get_by_id(&pool, &bob, &Config::default(), view.id)
+                .await?
+                .is_none()
+        );
+        SavedView::load(&pool, &bob, view.id)
+            .await
+            .expect_err("View of another user was loaded");
+        let views = SavedView::fetch(
+            &pool,
+            &bob,
+            &Config::default(),
+            SavedViewFetch { name: None },
+            Pagination::default(),
+        )
+        .await?;
+        assert!(views.is_empty());
+        assert_eq!(SavedView::remove(&pool, &bob, view.id).await?, 0);
+        assert_eq!(SavedView::remove(&pool, &user, view.id).await?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_params() -> anyhow::Result<()> {
+        groceries_view().validate()?;
+
+        SavedViewParams {
+            category_ids: Some(vec![]),
+            ..Default::default()
+        }
+        .validate()
+        .expect_err("Empty id list should cause an error");
+        SavedViewParams {
+            shop_ids: Some(vec![1, 0]),
+            ..Default::default()
+        }
+        .validate()
+        .expect_err("Id 0 should cause an error");
+        serde_json::from_str::<SavedViewParams>(r#"{"order": "amount:desc,intervalTo"}"#)?
+            .validate()
+            .expect_err("Key of recurring transactions should cause an error");
+        serde_json::from_str::<SavedViewParams>(r#"{"orderKey": "Frequency"}"#)?
+            .validate()
+            .expect_err("Key of recurring transactions should cause an error");
+        serde_json::from_str::<SavedViewParams>(r#"{"limit": 10}"#)
+            .expect_err("Unknown field should cause an error");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDate, Utc};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app::{
+        api::{pagination::Pagination, tri_state_field::TriState},
+        config::Config,
+        resources::{Resource, members::UnvalidatedMemberId},
+        transactions::{Amount, Description},
+    },
+    errors::ServerError,
+    users::User,
+};
+
+/// A payment between two household members that reduces what one owes the other.
+#[derive(Clone, Debug, Serialize, FromRow, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Settlement {
+    id: i32,
+    date: NaiveDate,
+    user_id: i32,
+    from_member_id: i32,
+    from_member: String,
+    to_member_id: i32,
+    to_member: String,
+    amount: i32,
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SettlementCreateParams {
+    #[garde(skip)]
+    date: NaiveDate,
+    #[garde(dive)]
+    from_member_id: UnvalidatedMemberId,
+    #[garde(dive)]
+    to_member_id: UnvalidatedMemberId,
+    #[garde(dive)]
+    amount: Amount,
+    #[garde(dive)]
+    description: Option<Description>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Validate, IntoParams)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct SettlementFetchParams {
+    /// Only settlements paid by or to this member
+    #[garde(dive)]
+    member_id: Option<UnvalidatedMemberId>,
+    #[garde(skip)]
+    date_from: Option<NaiveDate>,
+    #[garde(skip)]
+    date_to: Option<NaiveDate>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SettlementUpdateParams {
+    #[garde(skip)]
+    date: Option<NaiveDate>,
+    #[garde(dive)]
+    amount: Option<Amount>,
+    /// `null` removes the description
+    #[serde(default)]
+    #[garde(dive)]
+    #[schema(value_type = Option<Description>)]
+    description: TriState<Description>,
+}
+
+impl Resource for Settlement {
+    type CreateParams = SettlementCreateParams;
+    type FetchParams = SettlementFetchParams;
+    type UpdateParams = SettlementUpdateParams;
+    type ReturnType = Settlement;
+    type VecReturnType = Vec<Settlement>;
+    type Error = ServerError;
+
+    async fn create(
+        database: &PgPool,
+        user: &User,
+        params: Self::CreateParams,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        if params.from_member_id == params.to_member_id {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some("A member can't settle with themselves".to_owned()),
+            ));
+        }
+
+        let result = sqlx::query_as!(
+            Settlement,
+            r#"
+            WITH insert AS (
+                INSERT INTO settlements (date, user_id, from_member_id, to_member_id, amount, description)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+            )
+            SELECT insert.*, f.name as from_member, t.name as to_member
+            FROM insert
+            INNER JOIN members f ON insert.from_member_id = f.id
+            INNER JOIN members t ON insert.to_member_id = t.id
+            "#,
+            params.date,
+            user.id,
+            params.from_member_id.validate(user, database).await?,
+            params.to_member_id.validate(user, database).await?,
+            *params.amount,
+            params.description.as_deref(),
+        )
+        .fetch_optional(database)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn fetch(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        params: Self::FetchParams,
+        pagination: Pagination,
+    ) -> Result<Self::VecReturnType, Self::Error> {
+        let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
+            r#"
+                SELECT
+                    st.*,
+                    f.name as from_member,
+                    t.name as to_member
+                FROM settlements st
+                INNER JOIN members f ON st.from_member_id = f.id
+                INNER JOIN members t ON st.to_member_id = t.id
+                WHERE st.user_id =
+        "#,
+        );
+        query_builder.push_bind(user.id);
+
+        if let Some(member_id) = params.member_id {
+            let member_id = member_id.validate(user, database).await?;
+            query_builder
+                .push(" AND (st.from_member_id = ")
+                .push_bind(member_id)
+                .push(" OR st.to_member_id = ")
+                .push_bind(member_id)
+                .push(")");
+        }
+
+        if let Some(date_from) = params.date_from {
+            query_builder.push(" AND st.date >= ").push_bind(date_from);
+        }
+
+        if let Some(date_to) = params.date_to {
+            query_builder.push(" AND st.date <= ").push_bind(date_to);
+        }
+
+        query_builder.push(" ORDER BY st.date, st.id");
+        query_builder.push(" LIMIT ").push_bind(pagination.limit.0);
+        query_builder
+            .push(" OFFSET ")
+            .push_bind(pagination.offset.0);
+
+        let result = query_builder.build_query_as().fetch_all(database).await?;
+
+        Ok(result)
+    }
+
+    async fn get_by_id(
+        database: &PgPool,
+        user: &User,
+        _config: &Config,
+        id: i32,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        let result = sqlx::query_as!(
+            Settlement,
+            r#"
+            SELECT st.*, f.name as from_member, t.name as to_member
+            FROM settlements st
+            INNER JOIN members f ON st.from_member_id = f.id
+            INNER JOIN members t ON st.to_member_id = t.id
+            WHERE st.user_id = $1 AND st.id = $2
+            "#,
+            user.id,
+            id
+        )
+        .fetch_optional(database)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn update(
+        database: &PgPool,
+        user: &User,
+        config: &Config,
+        id: i32,
+        params: Self::UpdateParams,
+    ) -> Result<Option<Self::ReturnType>, Self::Error> {
+        // Start building the dynamic query
+        let mut query_parts = Vec::new();
+        let mut bind_count = 1;
+
+        // Build SET clause dynamically based on provided parameters
+        if params.date.is_some() {
+            query_parts.push(format!("date = ${bind_count}"));
+            bind_count += 1;
+        }
+
+        if params.amount.is_some() {
+            query_parts.push(format!("amount = ${bind_count}"));
+            bind_count += 1;
+        }
+
+        if let TriState::Defined(_) = params.description {
+            query_parts.push(format!("description = ${bind_count}"));
+            bind_count += 1;
+        }
+
+        // If no fields to update, return instance without changes
+        if query_parts.is_empty() {
+            return Self::get_by_id(database, user, config, id).await;
+        }
+
+        let sql = format!(
+            "UPDATE settlements SET {} WHERE user_id = ${} AND id = ${}",
+            query_parts.join(", "),
+            bind_count,
+            bind_count + 1
+        );
+
+        // Bind parameters in the same order as they were added to query_parts
+        let mut query = sqlx::query(&sql);
+
+        if let Some(date) = params.date {
+            query = query.bind(date);
+        }
+
+        if let Some(amount) = params.amount {
+            query = query.bind(*amount);
+        }
+
+        if let TriState::Defined(field) = &params.description {
+            query = query.bind(field.as_deref());
+        }
+
+        let result = query.bind(user.id).bind(id).execute(database).await?;
+
+        if result.rows_affected() == 0 {
+            Ok(None)
+        } else {
+            Self::get_by_id(database, user, config, id).await
+        }
+    }
+
+    async fn remove(database: &PgPool, user: &User, id: i32) -> Result<u64, Self::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM settlements WHERE user_id = $1 AND id = $2",
+            user.id,
+            id
+        )
+        .execute(database)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    fn create_params(from: i32, to: i32, amount: i32) -> SettlementCreateParams {
+        SettlementCreateParams {
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            from_member_id: UnvalidatedMemberId::from(from),
+            to_member_id: UnvalidatedMemberId::from(to),
+            amount: Amount(amount),
+            description: None,
+        }
+    }
+
+    #[sqlx::test(fixtures("base", "members"))]
+    async fn test_create_settlement(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let settlement = Settlement::create(&pool, &user, create_params(2, 1, 500))
+            .await?
+            .expect("Failed to create settlement");
+
+        assert_eq!(settlement.from_member, "Carol");
+        assert_eq!(settlement.to_member, "Alice");
+        assert_eq!(settlement.amount, 500);
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("base", "members"))]
+    async fn test_create_settlement_invalid_members(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        Settlement::create(&pool, &user, create_params(1, 1, 500))
+            .await
+            .expect_err("Settlement with the same member on both sides was created");
+        Settlement::create(&pool, &user, create_params(1, 4, 500))
+            .await
+            .expect_err("Settlement with a member of a different user was created");
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures("base", "members"))]
+    async fn test_fetch_and_update_settlement(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let created = Settlement::create(&pool, &user, create_params(2, 1, 500))
+            .await?
+            .expect("Failed to create settlement");
+        Settlement::create(&pool, &user, create_params(3, 1, 700))
+            .await?
+            .expect("Failed to create settlement");
+
+        let result = Settlement::fetch(
+            &pool,
+            &user,
+            &Config::default(),
+            SettlementFetchParams {
+                member_id: Some(UnvalidatedMemberId::from(2)),
+                ..Default::default()
+            },
+            Pagination::default(),
+        )
+        .await?;
+        assert_eq!(result, vec![created.clone()]);
+
+        let updated = Settlement::update(
+            &pool,
+            &user,
+            &Config::default(),
+            created.id,
+            SettlementUpdateParams {
+                amount: Some(Amount(600)),
+                description: TriState::Defined(Some(Description("Cash".to_string()))),
+                ..Default::default()
+            },
+        )
+        .await?
+        .expect("Failed to update settlement");
+        assert_eq!(updated.amount, 600);
+        assert_eq!(updated.description.as_deref(), Some("Cash"));
+        assert_eq!(updated.date, created.date);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use async_trait::async_trait;
+use axum_login::tower_sessions::{
+    ExpiredDeletion, SessionStore,
+    cookie::time::OffsetDateTime,
+    session::{Id, Record},
+    session_store,
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Session store persisting sessions in the `sessions` table, so that sessions survive restarts and can be shared
+/// between multiple instances
+#[derive(Clone, Debug)]
+pub struct PostgresStore {
+    database: PgPool,
+}
+
+fn backend_error(err: sqlx::Error) -> session_store::Error {
+    session_store::Error::Backend(err.to_string())
+}
+
+fn to_chrono(date: OffsetDateTime) -> session_store::Result<DateTime<Utc>> {
+    DateTime::from_timestamp_micros((date.unix_timestamp_nanos() / 1000) as i64)
+        .ok_or_else(|| session_store::Error::Encode("expiry date out of range".to_owned()))
+}
+
+fn from_chrono(date: DateTime<Utc>) -> session_store::Result<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp_nanos(i128::from(date.timestamp_micros()) * 1000)
+        .map_err(|err| session_store::Error::Decode(err.to_string()))
+}
+
+impl PostgresStore {
+    pub fn new(database: PgPool) -> Self {
+        Self { database }
+    }
+}
+
+#[async_trait]
+impl SessionStore for PostgresStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let data = serde_json::to_value(&record.data)
+            .map_err(|err| session_store::Error::Encode(err.to_string()))?;
+        let expiry_date = to_chrono(record.expiry_date)?;
+
+        // Generate new ids until there is no collision with an existing session
+        loop {
+            let result = sqlx::query!(
+                "INSERT INTO sessions (id, data, expiry_date) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                record.id.to_string(),
+                data,
+                expiry_date
+            )
+            .execute(&self.database)
+            .await
+            .map_err(backend_error)?;
+
+            if result.rows_affected() == 1 {
+                return Ok(());
+            }
+            record.id = Id::default();
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data = serde_json::to_value(&record.data)
+            .map_err(|err| session_store::Error::Encode(err.to_string()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, data, expiry_date) VALUES ($1, $2, $3)
+            ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data, expiry_date = EXCLUDED.expiry_date
+            "#,
+            record.id.to_string(),
+            data,
+            to_chrono(record.expiry_date)?
+        )
+        .execute(&self.database)
+        .await
+        .map_err(backend_error)?;
+
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let row = sqlx::query!(
+            "SELECT data, expiry_date FROM sessions WHERE id = $1 AND expiry_date > now()",
+            session_id.to_string()
+        )
+        .fetch_optional(&self.database)
+        .await
+        .map_err(backend_error)?;
+
+        let Some(row) = row else { return Ok(None) };
+
+        Ok(Some(Record {
+            id: *session_id,
+            data: serde_json::from_value(row.data)
+                .map_err(|err| session_store::Error::Decode(err.to_string()))?,
+            expiry_date: from_chrono(row.expiry_date)?,
+        }))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        sqlx::query!("DELETE FROM sessions WHERE id = $1", session_id.to_string())
+            .execute(&self.database)
+            .await
+            .map_err(backend_error)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for PostgresStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        sqlx::query!("DELETE FROM sessions WHERE expiry_date <= now()")
+            .execute(&self.database)
+            .await
+            .map_err(backend_error)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_login::tower_sessions::cookie::time::Duration;
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(expiry_date: OffsetDateTime) -> Record {
+        Record {
+            id: Id::default(),
+            data: [("key".to_string(), json!("value"))].into(),
+            expiry_date,
+        }
+    }
+
+    async fn count_sessions(pool: &PgPool) -> i64 {
+        sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!" FROM sessions"#)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to count sessions")
+    }
+
+    #[sqlx::test]
+    async fn test_create_load_delete(pool: PgPool) -> anyhow::Result<()> {
+        let store = PostgresStore::new(pool);
+
+        let mut record = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        store.create(&mut record).await?;
+
+        let loaded = store.load(&record.id).await?.expect("Session not found");
+        assert_eq!(loaded.data, record.data);
+        assert_eq!(
+            loaded.expiry_date.unix_timestamp(),
+            record.expiry_date.unix_timestamp()
+        );
+
+        record.data.insert("other".to_string(), json!(1));
+        store.save(&record).await?;
+        let loaded = store.load(&record.id).await?.expect("Session not found");
+        assert_eq!(loaded.data, record.data);
+
+        store.delete(&record.id).await?;
+        assert!(store.load(&record.id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_create_avoids_id_collision(pool: PgPool) -> anyhow::Result<()> {
+        let store = PostgresStore::new(pool);
+
+        let mut first = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        store.create(&mut first).await?;
+
+        let mut second = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        second.id = first.id;
+        store.create(&mut second).await?;
+
+        assert_ne!(first.id, second.id);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_expired_sessions(pool: PgPool) -> anyhow::Result<()> {
+        let store = PostgresStore::new(pool.clone());
+
+        let mut expired = record(OffsetDateTime::now_utc() - Duration::minutes(1));
+        store.create(&mut expired).await?;
+        let mut active = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        store.create(&mut active).await?;
+
+        assert!(store.load(&expired.id).await?.is_none());
+
+        store.delete_expired().await?;
+        assert_eq!(count_sessions(&pool).await, 1);
+        assert!(store.load(&active.id).await?.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use axum::{Json, Router, extract::State, response::IntoResponse, routing::get};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use garde::Validate;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use serde_json::json;
+use sqlx::PgPool;
+use utoipa::{
+    PartialSchema, ToSchema,
+    openapi::{ObjectBuilder, RefOr, Schema, Type},
+};
+
+use crate::{
+    app::{
+        AppState,
+        api::{AuthUser, ValidJson, tri_state_field::TriState},
+        transactions::{OrderKey, Ordering, date_range::today, validate_shared_order_key},
+    },
+    errors::ServerError,
+    users::User,
+};
+
+/// IANA timezone like `Europe/Berlin`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timezone(pub Tz);
+
+impl Serialize for Timezone {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.0.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value
+            .parse()
+            .map(Timezone)
+            .map_err(|_| de::Error::custom(format!("unknown timezone '{value}'")))
+    }
+}
+
+impl PartialSchema for Timezone {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("IANA timezone name"))
+            .examples(["Europe/Berlin"])
+            .into()
+    }
+}
+
+impl ToSchema for Timezone {}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema,
+)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "weekday", rename_all = "lowercase")]
+pub enum Weekday {
+    #[default]
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+/// How amounts are labeled, e.g. `€`, `EUR` or `euros`
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema,
+)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "currency_display", rename_all = "lowercase")]
+pub enum CurrencyDisplay {
+    #[default]
+    Symbol,
+    Code,
+    Name,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema,
+)]
+#[sqlx(type_name = "date_format")]
+pub enum DateFormat {
+    #[default]
+    #[serde(rename = "YYYY-MM-DD")]
+    #[sqlx(rename = "YYYY-MM-DD")]
+    YearMonthDay,
+    #[serde(rename = "DD.MM.YYYY")]
+    #[sqlx(rename = "DD.MM.YYYY")]
+    DayMonthYearDotted,
+    #[serde(rename = "DD/MM/YYYY")]
+    #[sqlx(rename = "DD/MM/YYYY")]
+    DayMonthYearSlashed,
+    #[serde(rename = "MM/DD/YYYY")]
+    #[sqlx(rename = "MM/DD/YYYY")]
+    MonthDayYear,
+}
+
+/// Preferences of a user. Users that never changed them get the defaults.
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSettings {
+    /// `null` uses the timezone of the server configuration
+    pub timezone: Option<Timezone>,
+    pub first_day_of_week: Weekday,
+    pub currency_display: CurrencyDisplay,
+    pub date_format: DateFormat,
+    /// Default `ordering` of transaction lists
+    pub ordering: Ordering,
+    /// Default `orderKey` of transaction lists
+    pub order_key: OrderKey,
+}
+
+struct RawUserSettings {
+    timezone: Option<String>,
+    first_day_of_week: Weekday,
+    currency_display: CurrencyDisplay,
+    date_format: DateFormat,
+    ordering: Ordering,
+    order_key: OrderKey,
+}
+
+impl From<RawUserSettings> for UserSettings {
+    fn from(raw: RawUserSettings) -> Self {
+        UserSettings {
+            // Timezones are validated on update, but may be removed from the timezone database by an update
+            timezone: raw.timezone.and_then(|name| match name.parse() {
+                Ok(timezone) => Some(Timezone(timezone)),
+                Err(_) => {
+                    log::warn!("Ignoring unknown timezone '{name}'");
+                    None
+                }
+            }),
+            first_day_of_week: raw.first_day_of_week,
+            currency_display: raw.currency_display,
+            date_format: raw.date_format,
+            ordering: raw.ordering,
+            order_key: raw.order_key,
+        }
+    }
+}
+
+/// Omitted fields keep their current value
+#[derive(Clone, Debug, Default, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UserSettingsUpdate {
+    /// `null` falls back to the timezone of the server configuration
+    #[serde(default)]
+    #[garde(skip)]
+    #[schema(value_type = Option<Timezone>)]
+    pub timezone: TriState<Timezone>,
+    #[garde(skip)]
+    pub first_day_of_week: Option<Weekday>,
+    #[garde(skip)]
+    pub currency_display: Option<CurrencyDisplay>,
+    #[garde(skip)]
+    pub date_format: Option<DateFormat>,
+    #[garde(skip)]
+    pub ordering: Option<Ordering>,
+    #[garde(custom(validate_shared_order_key))]
+    pub order_key: Option<OrderKey>,
+}
+
+impl UserSettings {
+    pub async fn get(database: &PgPool, user: &User) -> Result<Self, sqlx::Error> {
+        let raw = sqlx::query_as!(
+            RawUserSettings,
+            r#"
+            SELECT
+                timezone,
+                first_day_of_week as "first_day_of_week: Weekday",
+                currency_display as "currency_display: CurrencyDisplay",
+                date_format as "date_format: DateFormat",
+                ordering as "ordering: Ordering",
+                order_key as "order_key: OrderKey"
+            FROM user_settings
+            WHERE user_id = $1
+            "#,
+            user.id
+        )
+        .fetch_optional(database)
+        .await?;
+
+        Ok(raw.map(UserSettings::from).unwrap_or_default())
+    }
+
+    pub async fn update(
+        database: &PgPool,
+        user: &User,
+        params: UserSettingsUpdate,
+    ) -> Result<Self, sqlx::Error> {
+        let current = Self::get(database, user).await?;
+        let settings = UserSettings {
+            timezone: match params.timezone {
+                TriState::Undefined => current.timezone,
+                TriState::Defined(timezone) => timezone,
+            },
+            first_day_of_week: params
+                .first_day_of_week
+                .unwrap_or(current.first_day_of_week),
+            currency_display: params.currency_display.unwrap_or(current.currency_display),
+            date_format: params.date_format.unwrap_or(current.date_format),
+            ordering: params.ordering.unwrap_or(current.ordering),
+            order_key: params.order_key.unwrap_or(current.order_key),
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_settings (user_id, timezone, first_day_of_week, currency_display, date_format, ordering, order_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (user_id) DO UPDATE SET
+                timezone = EXCLUDED.timezone,
+                first_day_of_week = EXCLUDED.first_day_of_week,
+                currency_display = EXCLUDED.currency_display,
+                date_format = EXCLUDED.date_format,
+                ordering = EXCLUDED.ordering,
+                order_key = EXCLUDED.order_key
+            "#,
+            user.id,
+            settings.timezone.map(|timezone| timezone.0.name()),
+            settings.first_day_of_week as _,
+            settings.currency_display as _,
+            settings.date_format as _,
+            settings.ordering.clone() as _,
+            settings.order_key.clone() as _
+        )
+        .execute(database)
+        .await?;
+
+        Ok(settings)
+    }
+
+    /// The timezone of the user, or `default` of the server configuration
+    pub fn timezone(&self, default: Tz) -> Tz {
+        self.timezone.map_or(default, |timezone| timezone.0)
+    }
+
+    /// The current date in the timezone of the user
+    pub fn today(&self, default_timezone: Tz) -> NaiveDate {
+        today(self.timezone(default_timezone))
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(get_settings).patch(update_settings))
+}
+
+async fn get_settings(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+) -> Result<impl IntoResponse, ServerError> {
+    let settings = UserSettings::get(&state.database, &user).await?;
+    Ok(Json(json!({ "status": "success", "data": settings })))
+}
+
+async fn update_settings(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    ValidJson(params): ValidJson<UserSettingsUpdate>,
+) -> Result<impl IntoResponse, ServerError> {
+    let settings = UserSettings::update(&state.database, &user, params).await?;
+    Ok(Json(json!({ "status": "success", "data": settings })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base")))]
+    async fn test_defaults_and_update(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        let settings = UserSettings::get(&pool, &user).await?;
+        assert!(settings.timezone.is_none());
+        assert_eq!(settings.timezone(Tz::Europe__Berlin), Tz::Europe__Berlin);
+        assert_eq!(settings.date_format, DateFormat::YearMonthDay);
+
+        let update: UserSettingsUpdate = serde_json::from_str(
+            r#"{"timezone": "Pacific/Auckland", "dateFormat": "DD.MM.YYYY", "ordering": "Desc"}"#,
+        )?;
+        UserSettings::update(&pool, &user, update).await?;
+        let settings = UserSettings::get(&pool, &user).await?;
+        assert_eq!(settings.timezone(Tz::UTC), Tz::Pacific__Auckland);
+        assert_eq!(settings.date_format, DateFormat::DayMonthYearDotted);
+        assert!(matches!(settings.ordering, Ordering::Desc));
+        assert_eq!(settings.first_day_of_week, Weekday::Monday);
+
+        // Omitted fields are kept, `null` resets the timezone
+        let update: UserSettingsUpdate =
+            serde_json::from_str(r#"{"timezone": null, "firstDayOfWeek": "sunday"}"#)?;
+        let settings = UserSettings::update(&pool, &user, update).await?;
+        assert!(settings.timezone.is_none());
+        assert_eq!(settings.first_day_of_week, Weekday::Sunday);
+        assert_eq!(settings.date_format, DateFormat::DayMonthYearDotted);
+
+        // Other users keep the defaults
+        let bob = get_user_by_id(&pool, 2).await;
+        assert_eq!(
+            UserSettings::get(&pool, &bob).await?.first_day_of_week,
+            Weekday::Monday
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_update() {
+        serde_json::from_str::<UserSettingsUpdate>(r#"{"timezone": "Mars/Olympus_Mons"}"#)
+            .expect_err("Unknown timezone should cause an error");
+        serde_json::from_str::<UserSettingsUpdate>(r#"{"dateFormat": "YYYY/MM/DD"}"#)
+            .expect_err("Unknown date format should cause an error");
+        serde_json::from_str::<UserSettingsUpdate>(r#"{"locale": "de"}"#)
+            .expect_err("Unknown field should cause an error");
+    }
+}
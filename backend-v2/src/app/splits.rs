@@ -0,0 +1,787 @@
+use std::collections::{BTreeMap, HashSet};
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgPool, Type};
+use utoipa::ToSchema;
+
+use crate::{
+    app::{
+        AppState,
+        api::{AuthUser, ValidJson},
+        resources::members::UnvalidatedMemberId,
+    },
+    errors::ServerError,
+    users::User,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, Type, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[sqlx(type_name = "split_mode", rename_all = "lowercase")]
+pub enum SplitMode {
+    /// The amount is divided evenly, shares carry no value
+    Equal,
+    /// Share values are percentages that add up to 100
+    Percentage,
+    /// Share values are amounts in cents that add up to the transaction amount
+    Exact,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ShareParams {
+    #[garde(dive)]
+    member_id: UnvalidatedMemberId,
+    /// Percentage of at most 100 or amount in cents, depending on the mode
+    #[garde(range(min = 0))]
+    #[schema(minimum = 0)]
+    value: Option<i32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SplitParams {
+    #[garde(skip)]
+    mode: SplitMode,
+    #[garde(dive)]
+    paid_by_member_id: UnvalidatedMemberId,
+    #[garde(length(min = 1), dive, custom(validate_share_values(&self.mode)))]
+    #[schema(min_items = 1)]
+    shares: Vec<ShareParams>,
+}
+
+fn validate_share_values(mode: &SplitMode) -> impl FnOnce(&Vec<ShareParams>, &()) -> garde::Result {
+    move |shares, _| {
+        let mut members = HashSet::new();
+        if !shares.iter().all(|share| members.insert(share.member_id)) {
+            return Err(garde::Error::new("members must be unique"));
+        }
+
+        match mode {
+            SplitMode::Equal => {
+                if shares.iter().any(|share| share.value.is_some()) {
+                    return Err(garde::Error::new("values are not allowed for equal splits"));
+                }
+            }
+            SplitMode::Percentage | SplitMode::Exact => {
+                if shares.iter().any(|share| share.value.is_none()) {
+                    return Err(garde::Error::new("every share requires a value"));
+                }
+            }
+        }
+
+        if *mode == SplitMode::Percentage {
+            if shares.iter().any(|share| share.value > Some(100)) {
+                return Err(garde::Error::new("percentages must be at most 100"));
+            }
+            if sum_values(shares) != 100 {
+                return Err(garde::Error::new("percentages must add up to 100"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sum of the share values, in 64 bits so that large values can't overflow
+fn sum_values(shares: &[ShareParams]) -> i64 {
+    shares
+        .iter()
+        .filter_map(|share| share.value)
+        .map(i64::from)
+        .sum()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Share {
+    member_id: i32,
+    member: String,
+    value: Option<i32>,
+    amount: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Split {
+    oneoff_transaction_id: i32,
+    mode: SplitMode,
+    paid_by_member_id: i32,
+    paid_by_member: String,
+    shares: Vec<Share>,
+}
+
+#[derive(FromRow)]
+struct RawSplit {
+    oneoff_transaction_id: i32,
+    mode: SplitMode,
+    paid_by_member_id: i32,
+    paid_by_member: String,
+    is_expense: bool,
+    amount: i32,
+}
+
+#[derive(FromRow)]
+struct RawShare {
+    oneoff_transaction_id: i32,
+    member_id: i32,
+    member: String,
+    value: Option<i32>,
+}
+
+/// Resolve share values into amounts in cents. Shares must be sorted by member id, leftover cents from rounding
+/// go to the first members so that the amounts always add up to the transaction amount.
+fn resolve_amounts(mode: SplitMode, amount: i32, values: &[Option<i32>]) -> Vec<i32> {
+    let amount = i64::from(amount);
+    let mut amounts: Vec<i64> = match mode {
+        SplitMode::Equal => vec![amount / values.len() as i64; values.len()],
+        SplitMode::Percentage => values
+            .iter()
+            .map(|value| amount * i64::from(value.unwrap_or_default()) / 100)
+            .collect(),
+        // Exact shares are taken as recorded, the transaction amount can't change while they exist
+        SplitMode::Exact => {
+            return values
+                .iter()
+                .map(|value| value.unwrap_or_default())
+                .collect();
+        }
+    };
+
+    let remainder = amount - amounts.iter().sum::<i64>();
+    for value in amounts.iter_mut().take(remainder as usize) {
+        *value += 1;
+    }
+
+    amounts.into_iter().map(|value| value as i32).collect()
+}
+
+impl Split {
+    fn from_raw(split: RawSplit, shares: Vec<RawShare>) -> Self {
+        let amounts = resolve_amounts(
+            split.mode,
+            split.amount,
+            &shares.iter().map(|share| share.value).collect::<Vec<_>>(),
+        );
+
+        Split {
+            oneoff_transaction_id: split.oneoff_transaction_id,
+            mode: split.mode,
+            paid_by_member_id: split.paid_by_member_id,
+            paid_by_member: split.paid_by_member,
+            shares: shares
+                .into_iter()
+                .zip(amounts)
+                .map(|(share, amount)| Share {
+                    member_id: share.member_id,
+                    member: share.member,
+                    value: share.value,
+                    amount,
+                })
+                .collect(),
+        }
+    }
+
+    pub async fn get(
+        database: &PgPool,
+        user: &User,
+        transaction_id: i32,
+    ) -> Result<Option<Split>, ServerError> {
+        let split = sqlx::query_as!(
+            RawSplit,
+            r#"
+            SELECT ts.oneoff_transaction_id, ts.mode as "mode: SplitMode", ts.paid_by_member_id, m.name as paid_by_member, ot.is_expense, ot.amount
+            FROM transaction_splits ts
+            INNER JOIN oneoff_transactions ot ON ts.oneoff_transaction_id = ot.id
+            INNER JOIN members m ON ts.paid_by_member_id = m.id
+            WHERE ot.user_id = $1 AND ot.id = $2
+            "#,
+            user.id,
+            transaction_id
+        )
+        .fetch_optional(database)
+        .await?;
+
+        let Some(split) = split else {
+            return Ok(None);
+        };
+
+        let shares = sqlx::query_as!(
+            RawShare,
+            r#"
+            SELECT tss.oneoff_transaction_id, tss.member_id, m.name as member, tss.value
+            FROM transaction_split_shares tss
+            INNER JOIN members m ON tss.member_id = m.id
+            WHERE tss.oneoff_transaction_id = $1
+            ORDER BY tss.member_id
+            "#,
+            transaction_id
+        )
+        .fetch_all(database)
+        .await?;
+
+        Ok(Some(Split::from_raw(split, shares)))
+    }
+
+    /// Create or replace the split of a oneoff transaction. Returns None if the transaction doesn't exist.
+    pub async fn put(
+        database: &PgPool,
+        user: &User,
+        transaction_id: i32,
+        params: SplitParams,
+    ) -> Result<Option<Split>, ServerError> {
+        let amount = sqlx::query_scalar!(
+            "SELECT amount FROM oneoff_transactions WHERE user_id = $1 AND id = $2",
+            user.id,
+            transaction_id
+        )
+        .fetch_optional(database)
+        .await?;
+
+        let Some(amount) = amount else {
+            return Ok(None);
+        };
+
+        if params.mode == SplitMode::Exact && sum_values(&params.shares) != i64::from(amount) {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some("Exact shares must add up to the transaction amount".to_owned()),
+            ));
+        }
+
+        let paid_by_member_id = params.paid_by_member_id.validate(user, database).await?;
+        let mut member_ids = Vec::with_capacity(params.shares.len());
+        let mut values = Vec::with_capacity(params.shares.len());
+        for share in params.shares {
+            member_ids.push(share.member_id.validate(user, database).await?);
+            values.push(share.value);
+        }
+
+        let mut transaction = database.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transaction_splits (oneoff_transaction_id, paid_by_member_id, mode)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (oneoff_transaction_id) DO UPDATE SET paid_by_member_id = $2, mode = $3
+            "#,
+            transaction_id,
+            paid_by_member_id,
+            params.mode as _
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM transaction_split_shares WHERE oneoff_transaction_id = $1",
+            transaction_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO transaction_split_shares (oneoff_transaction_id, member_id, value)
+            SELECT $1, * FROM UNNEST($2::integer[], $3::integer[])
+            "#,
+            transaction_id,
+            &member_ids,
+            &values as &[Option<i32>]
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Self::get(database, user, transaction_id).await
+    }
+
+    pub async fn remove(
+        database: &PgPool,
+        user: &User,
+        transaction_id: i32,
+    ) -> Result<u64, ServerError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM transaction_splits ts
+            USING oneoff_transactions ot
+            WHERE ts.oneoff_transaction_id = ot.id AND ot.user_id = $1 AND ot.id = $2
+            "#,
+            user.id,
+            transaction_id
+        )
+        .execute(database)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberBalance {
+    member_id: i32,
+    member: String,
+    /// Positive if the member is owed money, negative if they owe money
+    balance: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Debt {
+    from_member_id: i32,
+    from_member: String,
+    to_member_id: i32,
+    to_member: String,
+    amount: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Balances {
+    balances: Vec<MemberBalance>,
+    debts: Vec<Debt>,
+}
+
+/// Reduce balances to a short list of payments that settle everyone up, by repeatedly matching the largest debtor
+/// with the largest creditor.
+fn settle_up(balances: &[MemberBalance]) -> Vec<Debt> {
+    let mut debtors: Vec<(&MemberBalance, i64)> = balances
+        .iter()
+        .filter(|b| b.balance < 0)
+        .map(|b| (b, -b.balance))
+        .collect();
+    let mut creditors: Vec<(&MemberBalance, i64)> = balances
+        .iter()
+        .filter(|b| b.balance > 0)
+        .map(|b| (b, b.balance))
+        .collect();
+    // Sort by amount descending and member id ascending for a deterministic result
+    debtors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.member_id.cmp(&b.0.member_id)));
+    creditors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.member_id.cmp(&b.0.member_id)));
+
+    let mut debts = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < debtors.len() && j < creditors.len() {
+        let amount = debtors[i].1.min(creditors[j].1);
+        debts.push(Debt {
+            from_member_id: debtors[i].0.member_id,
+            from_member: debtors[i].0.member.clone(),
+            to_member_id: creditors[j].0.member_id,
+            to_member: creditors[j].0.member.clone(),
+            amount,
+        });
+
+        debtors[i].1 -= amount;
+        creditors[j].1 -= amount;
+        if debtors[i].1 == 0 {
+            i += 1;
+        }
+        if creditors[j].1 == 0 {
+            j += 1;
+        }
+    }
+
+    debts
+}
+
+impl Balances {
+    pub async fn compute(database: &PgPool, user: &User) -> Result<Balances, ServerError> {
+        let members = sqlx::query!(
+            "SELECT id, name FROM members WHERE user_id = $1 ORDER BY name, id",
+            user.id
+        )
+        .fetch_all(database)
+        .await?;
+
+        let splits = sqlx::query_as!(
+            RawSplit,
+            r#"
+            SELECT ts.oneoff_transaction_id, ts.mode as "mode: SplitMode", ts.paid_by_member_id, m.name as paid_by_member, ot.is_expense, ot.amount
+            FROM transaction_splits ts
+            INNER JOIN oneoff_transactions ot ON ts.oneoff_transaction_id = ot.id
+            INNER JOIN members m ON ts.paid_by_member_id = m.id
+            WHERE ot.user_id = $1
+            ORDER BY ts.oneoff_transaction_id
+            "#,
+            user.id
+        )
+        .fetch_all(database)
+        .await?;
+
+        let mut shares: BTreeMap<i32, Vec<RawShare>> = BTreeMap::new();
+        for share in sqlx::query_as!(
+            RawShare,
+            r#"
+            SELECT tss.oneoff_transaction_id, tss.member_id, m.name as member, tss.value
+            FROM transaction_split_shares tss
+            INNER JOIN oneoff_transactions ot ON tss.oneoff_transaction_id = ot.id
+            INNER JOIN members m ON tss.member_id = m.id
+            WHERE ot.user_id = $1
+            ORDER BY tss.oneoff_transaction_id, tss.member_id
+            "#,
+            user.id
+        )
+        .fetch_all(database)
+        .await?
+        {
+            shares
+                .entry(share.oneoff_transaction_id)
+                .or_default()
+                .push(share);
+        }
+
+        let mut totals: BTreeMap<i32, i64> = BTreeMap::new();
+        for raw in splits {
+            // For an expense, the payer advanced the money for everyone. For an income, the payer received money
+            // that is partly owed to the other members.
+            let sign = if raw.is_expense { 1 } else { -1 };
+            let transaction_shares = shares
+                .remove(&raw.oneoff_transaction_id)
+                .unwrap_or_default();
+            let split = Split::from_raw(raw, transaction_shares);
+
+            for share in &split.shares {
+                let amount = sign * i64::from(share.amount);
+                *totals.entry(split.paid_by_member_id).or_default() += amount;
+                *totals.entry(share.member_id).or_default() -= amount;
+            }
+        }
+
+        let settlements = sqlx::query!(
+            "SELECT from_member_id, to_member_id, amount FROM settlements WHERE user_id = $1",
+            user.id
+        )
+        .fetch_all(database)
+        .await?;
+
+        for settlement in settlements {
+            *totals.entry(settlement.from_member_id).or_default() += i64::from(settlement.amount);
+            *totals.entry(settlement.to_member_id).or_default() -= i64::from(settlement.amount);
+        }
+
+        let balances: Vec<MemberBalance> = members
+            .into_iter()
+            .map(|member| MemberBalance {
+                balance: totals.get(&member.id).copied().unwrap_or_default(),
+                member_id: member.id,
+                member: member.name,
+            })
+            .collect();
+        let debts = settle_up(&balances);
+
+        Ok(Balances { balances, debts })
+    }
+}
+
+pub fn transaction_router() -> Router<AppState> {
+    Router::new().route(
+        "/{id}/split",
+        get(get_split).put(put_split).delete(remove_split),
+    )
+}
+
+pub fn balances_router() -> Router<AppState> {
+    Router::new().route("/", get(get_balances))
+}
+
+pub async fn get_split(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(split) = Split::get(&state.database, &user, id as i32).await? else {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    };
+
+    Ok(Json(json!({ "status": "success", "data": split })))
+}
+
+pub async fn put_split(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<u32>,
+    ValidJson(params): ValidJson<SplitParams>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Some(split) = Split::put(&state.database, &user, id as i32, params).await? else {
+        return Err(ServerError::Generic(StatusCode::NOT_FOUND, None));
+    };
+
+    Ok(Json(json!({ "status": "success", "data": split })))
+}
+
+pub async fn remove_split(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, ServerError> {
+    match Split::remove(&state.database, &user, id as i32).await? {
+        0 => Err(ServerError::Generic(StatusCode::NOT_FOUND, None)),
+        _ => Ok(Json(json!({ "status": "success" }))),
+    }
+}
+
+pub async fn get_balances(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+) -> Result<impl IntoResponse, ServerError> {
+    let balances = Balances::compute(&state.database, &user).await?;
+
+    Ok(Json(json!({ "status": "success", "data": balances })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_user_by_id(pool: &PgPool, id: i32) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to get user")
+    }
+
+    fn split_params(mode: SplitMode, paid_by: i32, shares: &[(i32, Option<i32>)]) -> SplitParams {
+        SplitParams {
+            mode,
+            paid_by_member_id: UnvalidatedMemberId::from(paid_by),
+            shares: shares
+                .iter()
+                .map(|(member_id, value)| ShareParams {
+                    member_id: UnvalidatedMemberId::from(*member_id),
+                    value: *value,
+                })
+                .collect(),
+        }
+    }
+
+    fn balance(member_id: i32, balance: i64) -> MemberBalance {
+        MemberBalance {
+            member_id,
+            member: format!("member {member_id}"),
+            balance,
+        }
+    }
+
+    #[test]
+    fn test_resolve_amounts() {
+        assert_eq!(
+            resolve_amounts(SplitMode::Equal, 1000, &[None, None, None]),
+            vec![334, 333, 333]
+        );
+        assert_eq!(
+            resolve_amounts(SplitMode::Percentage, 999, &[Some(50), Some(25), Some(25)]),
+            vec![500, 250, 249]
+        );
+        assert_eq!(
+            resolve_amounts(SplitMode::Exact, 1000, &[Some(100), Some(900)]),
+            vec![100, 900]
+        );
+    }
+
+    #[test]
+    fn test_validate_split_params() {
+        split_params(SplitMode::Equal, 1, &[(1, None), (2, None)])
+            .validate()
+            .expect("Valid equal split");
+        split_params(SplitMode::Percentage, 1, &[(1, Some(40)), (2, Some(60))])
+            .validate()
+            .expect("Valid percentage split");
+
+        split_params(SplitMode::Equal, 1, &[])
+            .validate()
+            .expect_err("Split without shares");
+        split_params(SplitMode::Equal, 1, &[(1, None), (1, None)])
+            .validate()
+            .expect_err("Duplicate members");
+        split_params(SplitMode::Equal, 1, &[(1, Some(50)), (2, None)])
+            .validate()
+            .expect_err("Value in equal split");
+        split_params(SplitMode::Exact, 1, &[(1, Some(50)), (2, None)])
+            .validate()
+            .expect_err("Missing value in exact split");
+        split_params(SplitMode::Percentage, 1, &[(1, Some(40)), (2, Some(40))])
+            .validate()
+            .expect_err("Percentages not adding up to 100");
+        split_params(
+            SplitMode::Percentage,
+            1,
+            &[(1, Some(i32::MAX)), (2, Some(i32::MAX)), (3, Some(102))],
+        )
+        .validate()
+        .expect_err("Percentages adding up to 100 after an overflow");
+    }
+
+    #[test]
+    fn test_settle_up() {
+        let debts = settle_up(&[
+            balance(1, 700),
+            balance(2, -500),
+            balance(3, -200),
+            balance(4, 0),
+        ]);
+
+        assert_eq!(
+            debts
+                .iter()
+                .map(|d| (d.from_member_id, d.to_member_id, d.amount))
+                .collect::<Vec<_>>(),
+            vec![(2, 1, 500), (3, 1, 200)]
+        );
+
+        let debts = settle_up(&[balance(1, 300), balance(2, 200), balance(3, -500)]);
+        assert_eq!(
+            debts
+                .iter()
+                .map(|d| (d.from_member_id, d.to_member_id, d.amount))
+                .collect::<Vec<_>>(),
+            vec![(3, 1, 300), (3, 2, 200)]
+        );
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base", "oneoff", "members")))]
+    async fn test_put_and_get_split(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        // Transaction 1 has an amount of 8542
+        let split = Split::put(
+            &pool,
+            &user,
+            1,
+            split_params(SplitMode::Equal, 1, &[(3, None), (1, None)]),
+        )
+        .await?
+        .expect("Failed to split transaction");
+
+        assert_eq!(split.paid_by_member, "Alice");
+        assert_eq!(
+            split
+                .shares
+                .iter()
+                .map(|s| (s.member_id, s.amount))
+                .collect::<Vec<_>>(),
+            vec![(1, 4271), (3, 4271)]
+        );
+
+        // Replacing the split removes the previous shares
+        let split = Split::put(
+            &pool,
+            &user,
+            1,
+            split_params(SplitMode::Exact, 2, &[(2, Some(8542))]),
+        )
+        .await?
+        .expect("Failed to split transaction");
+        assert_eq!(split.shares.len(), 1);
+        assert_eq!(Split::get(&pool, &user, 1).await?, Some(split));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base", "oneoff", "members")))]
+    async fn test_put_split_invalid(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        Split::put(
+            &pool,
+            &user,
+            1,
+            split_params(SplitMode::Exact, 1, &[(1, Some(100)), (2, Some(100))]),
+        )
+        .await
+        .expect_err("Exact shares not adding up to the amount were accepted");
+
+        Split::put(
+            &pool,
+            &user,
+            1,
+            split_params(
+                SplitMode::Exact,
+                1,
+                &[(1, Some(i32::MAX)), (2, Some(i32::MAX)), (3, Some(8544))],
+            ),
+        )
+        .await
+        .expect_err("Exact shares adding up to the amount after an overflow were accepted");
+
+        Split::put(
+            &pool,
+            &user,
+            1,
+            split_params(SplitMode::Equal, 1, &[(1, None), (4, None)]),
+        )
+        .await
+        .expect_err("Member of a different user was accepted");
+
+        // Transaction 20 belongs to Bob
+        let result = Split::put(
+            &pool,
+            &user,
+            20,
+            split_params(SplitMode::Equal, 1, &[(1, None)]),
+        )
+        .await?;
+        assert!(result.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "resources/fixtures", scripts("base", "oneoff", "members")))]
+    async fn test_balances(pool: PgPool) -> anyhow::Result<()> {
+        let user = get_user_by_id(&pool, 1).await;
+
+        // Alice paid 12750 for lunch, shared equally with Carol and Dave
+        Split::put(
+            &pool,
+            &user,
+            2,
+            split_params(SplitMode::Equal, 1, &[(1, None), (2, None), (3, None)]),
+        )
+        .await?;
+        // Carol paid 4532 for gas, Alice owes 25%
+        Split::put(
+            &pool,
+            &user,
+            3,
+            split_params(SplitMode::Percentage, 2, &[(1, Some(25)), (2, Some(75))]),
+        )
+        .await?;
+
+        let balances = Balances::compute(&pool, &user).await?;
+        assert_eq!(
+            balances
+                .balances
+                .iter()
+                .map(|b| (b.member_id, b.balance))
+                .collect::<Vec<_>>(),
+            vec![(1, 8500 - 1133), (2, 1133 - 4250), (3, -4250)]
+        );
+
+        // Dave pays his share
+        sqlx::query!(
+            "INSERT INTO settlements (date, user_id, from_member_id, to_member_id, amount) VALUES ('2024-02-01', 1, 3, 1, 4250)"
+        )
+        .execute(&pool)
+        .await?;
+
+        let balances = Balances::compute(&pool, &user).await?;
+        assert_eq!(
+            balances
+                .debts
+                .iter()
+                .map(|d| (d.from_member_id, d.to_member_id, d.amount))
+                .collect::<Vec<_>>(),
+            vec![(2, 1, 3117)]
+        );
+
+        Ok(())
+    }
+}
@@ -1,23 +1,30 @@
 use std::{
+    borrow::Cow,
     fmt::{self},
     ops::Deref,
+    str::FromStr,
 };
 
 use axum::http::StatusCode;
 use garde::Validate;
-use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use utoipa::{
+    PartialSchema, ToSchema,
+    openapi::{KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type},
+};
 
 use crate::{
-    app::resources::{
-        Resource,
-        categories_shops::{Category, Shop},
-    },
+    app::api::pagination::{Cursor, Limit, PageMeta, SortValue},
     errors::ServerError,
     users::User,
 };
 
-#[derive(Clone, Debug, Default, Deserialize)]
+pub mod date_range;
+pub mod filters;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "list_ordering")]
 pub enum Ordering {
     #[default]
     Asc,
@@ -33,13 +40,456 @@ impl fmt::Display for Ordering {
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+/// `Frequency` and `IntervalTo` only apply to recurring transactions
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "list_order_key")]
 pub enum OrderKey {
     #[default]
     Time,
     Amount,
     Category,
     Shop,
+    Description,
+    CreatedAt,
+    UpdatedAt,
+    Frequency,
+    IntervalTo,
+}
+
+impl OrderKey {
+    const ALL: [OrderKey; 9] = [
+        OrderKey::Time,
+        OrderKey::Amount,
+        OrderKey::Category,
+        OrderKey::Shop,
+        OrderKey::Description,
+        OrderKey::CreatedAt,
+        OrderKey::UpdatedAt,
+        OrderKey::Frequency,
+        OrderKey::IntervalTo,
+    ];
+
+    /// Name of the key in an [`Order`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrderKey::Time => "time",
+            OrderKey::Amount => "amount",
+            OrderKey::Category => "category",
+            OrderKey::Shop => "shop",
+            OrderKey::Description => "description",
+            OrderKey::CreatedAt => "createdAt",
+            OrderKey::UpdatedAt => "updatedAt",
+            OrderKey::Frequency => "frequency",
+            OrderKey::IntervalTo => "intervalTo",
+        }
+    }
+
+    pub fn is_recurring_only(&self) -> bool {
+        matches!(self, OrderKey::Frequency | OrderKey::IntervalTo)
+    }
+}
+
+/// Where rows whose sort key is NULL are placed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+/// A key of an [`Order`] with its own direction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortKey {
+    pub key: OrderKey,
+    pub ordering: Ordering,
+    pub nulls: Nulls,
+}
+
+impl SortKey {
+    /// NULLs are sorted last in ascending and first in descending order, like Postgres does by default
+    pub fn new(key: OrderKey, ordering: Ordering) -> Self {
+        let nulls = match ordering {
+            Ordering::Asc => Nulls::Last,
+            Ordering::Desc => Nulls::First,
+        };
+        SortKey {
+            key,
+            ordering,
+            nulls,
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ordering = match self.ordering {
+            Ordering::Asc => "asc",
+            Ordering::Desc => "desc",
+        };
+        let nulls = match self.nulls {
+            Nulls::First => "nullsFirst",
+            Nulls::Last => "nullsLast",
+        };
+        write!(f, "{}:{ordering}:{nulls}", self.key.name())
+    }
+}
+
+/// Maximum number of keys in an [`Order`]
+pub const MAX_SORT_KEYS: usize = 5;
+
+/// Comma separated sort keys of a list like `category:asc,amount:desc`. Each key can be followed by `asc` or `desc`,
+/// which defaults to `asc`, and by `nullsFirst` or `nullsLast`. Names are case insensitive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Order(Vec<SortKey>);
+
+impl Order {
+    pub fn new(key: OrderKey, ordering: Ordering) -> Self {
+        Order(vec![SortKey::new(key, ordering)])
+    }
+
+    pub fn keys(&self) -> &[SortKey] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(SortKey::to_string).collect();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+/// Lowercase without underscores, so `createdAt`, `created_at` and `CREATEDAT` are the same
+fn normalize_name(name: &str) -> String {
+    name.trim().replace('_', "").to_ascii_lowercase()
+}
+
+fn parse_sort_key(value: &str) -> Result<SortKey, String> {
+    let mut parts = value.split(':');
+    let name = parts.next().unwrap_or_default();
+    let key = OrderKey::ALL
+        .into_iter()
+        .find(|key| normalize_name(key.name()) == normalize_name(name))
+        .ok_or_else(|| format!("unknown order key '{}'", name.trim()))?;
+
+    let mut ordering = None;
+    let mut nulls = None;
+    for modifier in parts {
+        let (ordering_value, nulls_value) = match normalize_name(modifier).as_str() {
+            "asc" => (Some(Ordering::Asc), None),
+            "desc" => (Some(Ordering::Desc), None),
+            "nullsfirst" => (None, Some(Nulls::First)),
+            "nullslast" => (None, Some(Nulls::Last)),
+            _ => {
+                return Err(format!(
+                    "unknown modifier '{}' of '{}'",
+                    modifier.trim(),
+                    name.trim()
+                ));
+            }
+        };
+        if (ordering_value.is_some() && ordering.is_some())
+            || (nulls_value.is_some() && nulls.is_some())
+        {
+            return Err(format!("conflicting modifiers of '{}'", name.trim()));
+        }
+        ordering = ordering.or(ordering_value);
+        nulls = nulls.or(nulls_value);
+    }
+
+    let sort_key = SortKey::new(key, ordering.unwrap_or_default());
+    Ok(SortKey {
+        nulls: nulls.unwrap_or(sort_key.nulls),
+        ..sort_key
+    })
+}
+
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let keys = value
+            .split(',')
+            .map(parse_sort_key)
+            .collect::<Result<Vec<SortKey>, _>>()?;
+        if keys.len() > MAX_SORT_KEYS {
+            return Err(format!("at most {MAX_SORT_KEYS} order keys are allowed"));
+        }
+        for (index, sort_key) in keys.iter().enumerate() {
+            if keys[..index].iter().any(|other| other.key == sort_key.key) {
+                return Err(format!("duplicate order key '{}'", sort_key.key.name()));
+            }
+        }
+        Ok(Order(keys))
+    }
+}
+
+impl Serialize for Order {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Order {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl PartialSchema for Order {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some(
+                "Comma separated sort keys, each optionally followed by `:asc` or `:desc` and `:nullsFirst` or \
+                `:nullsLast`",
+            ))
+            .examples(["category:asc,amount:desc"])
+            .into()
+    }
+}
+
+impl ToSchema for Order {}
+
+/// Reject keys that only apply to recurring transactions, for orderings that are also used for oneoff transactions
+pub fn validate_shared_order_key(value: &Option<OrderKey>, _context: &()) -> garde::Result {
+    match value {
+        Some(key) if key.is_recurring_only() => Err(garde::Error::new(format!(
+            "`{}` only applies to recurring transactions",
+            key.name()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Like [`validate_shared_order_key`], for all keys of an [`Order`]
+pub fn validate_shared_order(value: &Option<Order>, context: &()) -> garde::Result {
+    value
+        .iter()
+        .flat_map(Order::keys)
+        .try_for_each(|sort_key| validate_shared_order_key(&Some(sort_key.key.clone()), context))
+}
+
+/// Column of a list that an [`OrderKey`] sorts by
+pub struct SortColumn {
+    expression: &'static str,
+    /// Replaces NULLs in comparisons, `None` if the column is never NULL
+    null_value: Option<&'static str>,
+}
+
+impl SortColumn {
+    pub const fn new(expression: &'static str) -> Self {
+        SortColumn {
+            expression,
+            null_value: None,
+        }
+    }
+
+    /// NULLs are sorted by an additional `{expression} IS NULL` key, followed by the column with NULLs replaced by
+    /// `null_value`. Sort values of a nullable column are therefore a [`SortValue::Bool`] followed by the value.
+    pub const fn nullable(expression: &'static str, null_value: &'static str) -> Self {
+        SortColumn {
+            expression,
+            null_value: Some(null_value),
+        }
+    }
+}
+
+/// Keyset pagination over a list ordered by sort key expressions, followed by the id as tiebreak in the direction of
+/// the first key. A page continues after the last row of the previous page.
+pub struct Keyset {
+    order: Order,
+    /// Expressions to sort by with their direction, they are never NULL
+    sort_keys: Vec<(String, Ordering)>,
+    id_column: &'static str,
+}
+
+impl Keyset {
+    /// `sort_columns` returns the columns of a key, or `None` if the list can't be sorted by it
+    pub fn new(
+        order: Order,
+        sort_columns: impl Fn(&OrderKey) -> Option<Vec<SortColumn>>,
+        id_column: &'static str,
+    ) -> Result<Self, ServerError> {
+        let mut sort_keys = Vec::new();
+        for sort_key in order.keys() {
+            let Some(columns) = sort_columns(&sort_key.key) else {
+                return Err(ServerError::Generic(
+                    StatusCode::BAD_REQUEST,
+                    Some(format!(
+                        "This list can't be ordered by `{}`",
+                        sort_key.key.name()
+                    )),
+                ));
+            };
+            for column in columns {
+                match column.null_value {
+                    Some(null_value) => {
+                        let nulls = match sort_key.nulls {
+                            Nulls::First => Ordering::Desc,
+                            Nulls::Last => Ordering::Asc,
+                        };
+                        sort_keys.push((format!("{} IS NULL", column.expression), nulls));
+                        sort_keys.push((
+                            format!("COALESCE({}, {null_value})", column.expression),
+                            sort_key.ordering.clone(),
+                        ));
+                    }
+                    None => {
+                        sort_keys.push((column.expression.to_owned(), sort_key.ordering.clone()))
+                    }
+                }
+            }
+        }
+
+        Ok(Keyset {
+            order,
+            sort_keys,
+            id_column,
+        })
+    }
+
+    fn id_ordering(&self) -> Ordering {
+        self.order.keys()[0].ordering.clone()
+    }
+
+    /// Cursor to continue after a row. `sort_values` returns the values of the [`SortColumn`]s of a key for the row.
+    pub fn cursor(&self, sort_values: impl Fn(&OrderKey) -> Vec<SortValue>, id: i32) -> Cursor {
+        let values = self
+            .order
+            .keys()
+            .iter()
+            .flat_map(|sort_key| sort_values(&sort_key.key))
+            .collect();
+        Cursor::new(self.order.to_string(), values, id)
+    }
+
+    /// Only include rows after the row of the cursor. Pages without rows have no next cursor, so a `limit` of 0 is
+    /// rejected.
+    pub fn push_condition(
+        &self,
+        query_builder: &mut QueryBuilder<Postgres>,
+        cursor: &Cursor,
+        limit: Limit,
+    ) -> Result<(), ServerError> {
+        if limit.0 == 0 {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some("`limit` must be at least 1 together with `cursor`".to_owned()),
+            ));
+        }
+        if cursor.order() != self.order.to_string() || cursor.values().len() != self.sort_keys.len()
+        {
+            return Err(ServerError::Generic(
+                StatusCode::BAD_REQUEST,
+                Some("`cursor` was created for another ordering".to_owned()),
+            ));
+        }
+
+        let id_ordering = self.id_ordering();
+        let keys: Vec<(&str, &Ordering, SortValue)> = self
+            .sort_keys
+            .iter()
+            .zip(cursor.values())
+            .map(|((expression, ordering), value)| (expression.as_str(), ordering, value.clone()))
+            .chain([(self.id_column, &id_ordering, SortValue::Int(cursor.id()))])
+            .collect();
+
+        // A row comes after the cursor if it is equal in the first keys and after it in the next one
+        query_builder.push(" AND (");
+        for (index, (expression, ordering, value)) in keys.iter().enumerate() {
+            if index > 0 {
+                query_builder.push(" OR ");
+            }
+            query_builder.push("(");
+            for (expression, _, value) in &keys[..index] {
+                query_builder.push(format!("{expression} = "));
+                push_sort_value(query_builder, value.clone());
+                query_builder.push(" AND ");
+            }
+            let comparison = match ordering {
+                Ordering::Asc => ">",
+                Ordering::Desc => "<",
+            };
+            query_builder.push(format!("{expression} {comparison} "));
+            push_sort_value(query_builder, value.clone());
+            query_builder.push(")");
+        }
+        query_builder.push(")");
+
+        Ok(())
+    }
+
+    pub fn push_order_by(&self, query_builder: &mut QueryBuilder<Postgres>) {
+        let keys: Vec<String> = self
+            .sort_keys
+            .iter()
+            .map(|(expression, ordering)| format!("{expression} {ordering}"))
+            .chain([format!("{} {}", self.id_column, self.id_ordering())])
+            .collect();
+        query_builder.push(format!(" ORDER BY {}", keys.join(", ")));
+    }
+}
+
+fn push_sort_value(query_builder: &mut QueryBuilder<Postgres>, value: SortValue) {
+    match value {
+        SortValue::Bool(value) => query_builder.push_bind(value),
+        SortValue::Int(value) => query_builder.push_bind(value),
+        SortValue::Date(value) => query_builder.push_bind(value),
+        SortValue::Timestamp(value) => query_builder.push_bind(value),
+        SortValue::Text(value) => query_builder.push_bind(value),
+        SortValue::Frequency(value) => query_builder.push_bind(value),
+    };
+}
+
+/// Count and amount sums of all transactions matching the filters of a list request
+#[derive(Debug, FromRow)]
+pub struct Totals {
+    total: i64,
+    income: i64,
+    expenses: i64,
+}
+
+impl Totals {
+    /// Query of the totals, the filters of the list have to be pushed afterwards. `from` must select the transactions
+    /// as `alias`.
+    pub fn query<'a>(from: &str, alias: &str) -> QueryBuilder<'a, Postgres> {
+        Self::query_with_amount(from, alias, "amount")
+    }
+
+    /// Like [`Totals::query`], but sums the column named by `amount` instead of the `amount` column of `alias`
+    pub fn query_with_amount<'a>(
+        from: &str,
+        alias: &str,
+        amount: &str,
+    ) -> QueryBuilder<'a, Postgres> {
+        QueryBuilder::new(format!(
+            r#"
+            SELECT
+                COUNT(*) AS total,
+                COALESCE(SUM({alias}.{amount}) FILTER (WHERE NOT {alias}.is_expense), 0)::BIGINT AS income,
+                COALESCE(SUM({alias}.{amount}) FILTER (WHERE {alias}.is_expense), 0)::BIGINT AS expenses
+            {from}
+            "#
+        ))
+    }
+
+    pub fn into_meta(self, next_cursor: Option<Cursor>) -> PageMeta {
+        PageMeta {
+            total: self.total,
+            income: self.income,
+            expenses: self.expenses,
+            net: self.income - self.expenses,
+            next_cursor,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Validate)]
@@ -54,13 +504,41 @@ impl UnvalidatedCategoryId {
     }
 
     pub async fn validate(self, user: &User, database: &PgPool) -> Result<i32, ServerError> {
-        match Category::get_by_id(database, user, self.0).await? {
-            Some(_) => Ok(self.0),
-            None => Err(ServerError::Generic(
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM categories WHERE user_id = $1 AND id = $2) AS "exists!""#,
+            user.id,
+            self.0
+        )
+        .fetch_one(database)
+        .await?;
+        if !exists {
+            return Err(ServerError::Generic(
                 StatusCode::BAD_REQUEST,
                 Some("Invalid category id".to_owned()),
-            )),
+            ));
         }
+        Ok(self.0)
+    }
+}
+
+/// Schema of an id that still needs to be checked to belong to the user
+pub fn id_schema() -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::Integer)
+        .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int32)))
+        .minimum(Some(1))
+        .into()
+}
+
+impl PartialSchema for UnvalidatedCategoryId {
+    fn schema() -> RefOr<Schema> {
+        id_schema()
+    }
+}
+
+impl ToSchema for UnvalidatedCategoryId {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("CategoryId")
     }
 }
 
@@ -76,13 +554,32 @@ impl UnvalidatedShopId {
     }
 
     pub async fn validate(self, user: &User, database: &PgPool) -> Result<i32, ServerError> {
-        match Shop::get_by_id(database, user, self.0).await? {
-            Some(_) => Ok(self.0),
-            None => Err(ServerError::Generic(
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM shops WHERE user_id = $1 AND id = $2) AS "exists!""#,
+            user.id,
+            self.0
+        )
+        .fetch_one(database)
+        .await?;
+        if !exists {
+            return Err(ServerError::Generic(
                 StatusCode::BAD_REQUEST,
                 Some("Invalid shop id".to_owned()),
-            )),
+            ));
         }
+        Ok(self.0)
+    }
+}
+
+impl PartialSchema for UnvalidatedShopId {
+    fn schema() -> RefOr<Schema> {
+        id_schema()
+    }
+}
+
+impl ToSchema for UnvalidatedShopId {
+    fn name() -> Cow<'static, str> {
+        Cow::Borrowed("ShopId")
     }
 }
 
@@ -99,6 +596,17 @@ impl Deref for Description {
     }
 }
 
+impl PartialSchema for Description {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .min_length(Some(1))
+            .into()
+    }
+}
+
+impl ToSchema for Description {}
+
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, Validate)]
 #[serde(transparent)]
 #[garde(transparent)]
@@ -111,3 +619,102 @@ impl Deref for Amount {
         &self.0
     }
 }
+
+impl PartialSchema for Amount {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::Integer)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int32)))
+            .minimum(Some(1))
+            .description(Some("Amount in cents"))
+            .into()
+    }
+}
+
+impl ToSchema for Amount {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort_key(key: OrderKey, ordering: Ordering, nulls: Nulls) -> SortKey {
+        SortKey {
+            key,
+            ordering,
+            nulls,
+        }
+    }
+
+    #[test]
+    fn test_parse_order() -> anyhow::Result<()> {
+        let order: Order = "category:asc,amount:desc"
+            .parse()
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            order.keys(),
+            [
+                sort_key(OrderKey::Category, Ordering::Asc, Nulls::Last),
+                sort_key(OrderKey::Amount, Ordering::Desc, Nulls::First),
+            ]
+        );
+        assert_eq!(
+            order.to_string(),
+            "category:asc:nullsLast,amount:desc:nullsFirst"
+        );
+
+        // Names are case insensitive and may be written in snake case, the direction defaults to ascending
+        let order: Order = "Created_At, SHOP:nullsfirst:DESC ,intervalto:nulls_last"
+            .parse()
+            .map_err(anyhow::Error::msg)?;
+        assert_eq!(
+            order.keys(),
+            [
+                sort_key(OrderKey::CreatedAt, Ordering::Asc, Nulls::Last),
+                sort_key(OrderKey::Shop, Ordering::Desc, Nulls::First),
+                sort_key(OrderKey::IntervalTo, Ordering::Asc, Nulls::Last),
+            ]
+        );
+        assert_eq!(order.to_string().parse::<Order>(), Ok(order));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_order_errors() {
+        for value in [
+            "",
+            "price",
+            "amount:up",
+            "amount:asc:desc",
+            "amount:nullsFirst:nullsLast",
+            "amount,category:desc,amount:desc",
+            "time,amount,category,shop,description,createdAt",
+        ] {
+            value
+                .parse::<Order>()
+                .expect_err(&format!("'{value}' should be rejected"));
+        }
+    }
+
+    #[test]
+    fn test_validate_shared_order() -> anyhow::Result<()> {
+        assert!(
+            validate_shared_order(
+                &Some("time,amount:desc".parse().map_err(anyhow::Error::msg)?),
+                &()
+            )
+            .is_ok()
+        );
+        assert!(
+            validate_shared_order(
+                &Some("time,frequency".parse().map_err(anyhow::Error::msg)?),
+                &()
+            )
+            .is_err()
+        );
+        assert!(validate_shared_order_key(&Some(OrderKey::IntervalTo), &()).is_err());
+        assert!(validate_shared_order_key(&None, &()).is_ok());
+
+        Ok(())
+    }
+}
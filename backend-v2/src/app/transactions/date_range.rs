@@ -0,0 +1,135 @@
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The current date in the timezone
+pub fn today(timezone: Tz) -> NaiveDate {
+    Utc::now().with_timezone(&timezone).date_naive()
+}
+
+/// A date range relative to the current date. Unlike absolute dates, it moves along with time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DateRange {
+    /// The current calendar month
+    ThisMonth,
+    /// The previous calendar month
+    LastMonth,
+    /// From the first of January up to today
+    Ytd,
+    /// The twelve months up to today, e.g. `2024-03-16` to `2025-03-15`
+    #[serde(rename = "last_12_months")]
+    Last12Months,
+    /// The previous calendar year
+    LastYear,
+}
+
+impl DateRange {
+    /// First and last day of the range, both inclusive
+    pub fn resolve(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let month_start = today.with_day(1).expect("Every month has a first day");
+        let year_start = month_start.with_month(1).expect("Every year has a January");
+        match self {
+            DateRange::ThisMonth => (month_start, month_end(month_start)),
+            DateRange::LastMonth => {
+                let last_month = month_start - Months::new(1);
+                (last_month, month_end(last_month))
+            }
+            DateRange::Ytd => (year_start, today),
+            DateRange::Last12Months => (
+                (today - Months::new(12))
+                    .succ_opt()
+                    .expect("Date is out of range"),
+                today,
+            ),
+            DateRange::LastYear => (
+                year_start - Months::new(12),
+                year_start.pred_opt().expect("Date is out of range"),
+            ),
+        }
+    }
+
+    /// First and last day of the range relative to [`today`] in the timezone
+    pub fn dates(self, timezone: Tz) -> (NaiveDate, NaiveDate) {
+        self.resolve(today(timezone))
+    }
+}
+
+fn month_end(month_start: NaiveDate) -> NaiveDate {
+    (month_start + Months::new(1))
+        .pred_opt()
+        .expect("Date is out of range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let today = date(2025, 3, 15);
+        assert_eq!(
+            DateRange::ThisMonth.resolve(today),
+            (date(2025, 3, 1), date(2025, 3, 31))
+        );
+        assert_eq!(
+            DateRange::LastMonth.resolve(today),
+            (date(2025, 2, 1), date(2025, 2, 28))
+        );
+        assert_eq!(
+            DateRange::Ytd.resolve(today),
+            (date(2025, 1, 1), date(2025, 3, 15))
+        );
+        assert_eq!(
+            DateRange::Last12Months.resolve(today),
+            (date(2024, 3, 16), date(2025, 3, 15))
+        );
+        assert_eq!(
+            DateRange::LastYear.resolve(today),
+            (date(2024, 1, 1), date(2024, 12, 31))
+        );
+    }
+
+    #[test]
+    fn test_resolve_boundaries() {
+        // The previous month of January is in the previous year
+        assert_eq!(
+            DateRange::LastMonth.resolve(date(2025, 1, 1)),
+            (date(2024, 12, 1), date(2024, 12, 31))
+        );
+        assert_eq!(
+            DateRange::ThisMonth.resolve(date(2024, 2, 10)),
+            (date(2024, 2, 1), date(2024, 2, 29))
+        );
+        // There is no 29th of February in the previous year
+        assert_eq!(
+            DateRange::Last12Months.resolve(date(2024, 2, 29)),
+            (date(2023, 3, 1), date(2024, 2, 29))
+        );
+        assert_eq!(
+            DateRange::Ytd.resolve(date(2025, 1, 1)),
+            (date(2025, 1, 1), date(2025, 1, 1))
+        );
+    }
+
+    #[test]
+    fn test_deserialize() -> anyhow::Result<()> {
+        assert_eq!(
+            serde_json::from_str::<DateRange>(r#""last_12_months""#)?,
+            DateRange::Last12Months
+        );
+        assert_eq!(
+            serde_json::from_str::<DateRange>(r#""ytd""#)?,
+            DateRange::Ytd
+        );
+        serde_json::from_str::<DateRange>(r#""next_month""#)
+            .expect_err("Unknown range should cause an error");
+
+        Ok(())
+    }
+}
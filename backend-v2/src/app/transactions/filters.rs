@@ -0,0 +1,320 @@
+use garde::Validate;
+use serde::{Deserialize, Deserializer, de};
+use sqlx::{Encode, PgPool, Postgres, QueryBuilder, Type};
+
+use crate::{
+    app::{
+        api::column_filter::{ColumnFilter, FilterMode},
+        transactions::{Amount, UnvalidatedCategoryId, UnvalidatedShopId},
+    },
+    errors::ServerError,
+    users::User,
+};
+
+/// Maximum number of ids in an [`IdList`]
+pub const MAX_IDS: usize = 100;
+
+/// Comma separated ids in a query, e.g. `categoryIds=1,2,3`
+#[derive(Clone, Debug, PartialEq, Eq, Validate)]
+#[garde(transparent)]
+pub struct IdList(#[garde(length(min = 1, max = MAX_IDS), inner(range(min = 1)))] Vec<i32>);
+
+impl From<Vec<i32>> for IdList {
+    fn from(ids: Vec<i32>) -> Self {
+        Self(ids)
+    }
+}
+
+impl<'de> Deserialize<'de> for IdList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value
+            .split(',')
+            .map(|id| id.trim().parse())
+            .collect::<Result<Vec<i32>, _>>()
+            .map(IdList)
+            .map_err(|_| de::Error::custom("expected comma separated ids"))
+    }
+}
+
+/// Filter query parameters that both transaction resources accept
+#[derive(Clone, Debug, Default)]
+pub struct TransactionFilterParams {
+    pub is_expense: Option<bool>,
+    pub amount_from: Option<Amount>,
+    pub amount_to: Option<Amount>,
+    pub category_id: Option<UnvalidatedCategoryId>,
+    pub category_ids: Option<IdList>,
+    pub exclude_category_ids: Option<IdList>,
+    pub shop_filter: Option<FilterMode>,
+    pub shop_id: Option<UnvalidatedShopId>,
+    pub shop_ids: Option<IdList>,
+    pub exclude_shop_ids: Option<IdList>,
+    pub description_contains: Option<String>,
+    pub description_is_null: Option<bool>,
+}
+
+/// [`TransactionFilterParams`] with all ids checked to belong to the user
+pub struct TransactionFilters {
+    user_id: i32,
+    is_expense: Option<bool>,
+    amount_from: Option<i32>,
+    amount_to: Option<i32>,
+    category_id: Option<i32>,
+    category_ids: Option<Vec<i32>>,
+    exclude_category_ids: Option<Vec<i32>>,
+    shop: ColumnFilter<i32>,
+    shop_ids: Option<Vec<i32>>,
+    exclude_shop_ids: Option<Vec<i32>>,
+    description_contains: Option<String>,
+    description_is_null: Option<bool>,
+}
+
+impl TransactionFilters {
+    pub async fn resolve(
+        database: &PgPool,
+        user: &User,
+        params: TransactionFilterParams,
+    ) -> Result<Self, ServerError> {
+        let category_id = match params.category_id {
+            Some(category_id) => Some(category_id.validate(user, database).await?),
+            None => None,
+        };
+        let shop = match ColumnFilter::resolve(params.shop_filter, params.shop_id, "shop")? {
+            ColumnFilter::Any => ColumnFilter::Any,
+            ColumnFilter::Null => ColumnFilter::Null,
+            ColumnFilter::Specific(shop_id) => {
+                ColumnFilter::Specific(shop_id.validate(user, database).await?)
+            }
+        };
+
+        Ok(TransactionFilters {
+            user_id: user.id,
+            is_expense: params.is_expense,
+            amount_from: params.amount_from.map(|amount| *amount),
+            amount_to: params.amount_to.map(|amount| *amount),
+            category_id,
+            category_ids: validate_category_ids(database, user, params.category_ids).await?,
+            exclude_category_ids: validate_category_ids(
+                database,
+                user,
+                params.exclude_category_ids,
+            )
+            .await?,
+            shop,
+            shop_ids: validate_shop_ids(database, user, params.shop_ids).await?,
+            exclude_shop_ids: validate_shop_ids(database, user, params.exclude_shop_ids).await?,
+            description_contains: params.description_contains,
+            description_is_null: params.description_is_null,
+        })
+    }
+
+    /// Start the `WHERE` clause of a query with these filters. `alias` is the alias of the transaction table, further
+    /// conditions can be added to the returned [`Conditions`].
+    pub fn push<'b, 'args>(
+        &self,
+        query_builder: &'b mut QueryBuilder<'args, Postgres>,
+        alias: &'static str,
+    ) -> Conditions<'b, 'args> {
+        let mut conditions = Conditions::new(query_builder, alias, self.user_id);
+        conditions
+            .eq("is_expense", self.is_expense)
+            .ge("amount", self.amount_from)
+            .le("amount", self.amount_to)
+            .eq("category_id", self.category_id)
+            .any("category_id", self.category_ids.clone())
+            .none("category_id", self.exclude_category_ids.clone())
+            .column_filter("shop_id", &self.shop)
+            .any("shop_id", self.shop_ids.clone())
+            .none("shop_id", self.exclude_shop_ids.clone())
+            .contains("description", self.description_contains.as_deref())
+            .is_null("description", self.description_is_null);
+        conditions
+    }
+}
+
+async fn validate_category_ids(
+    database: &PgPool,
+    user: &User,
+    ids: Option<IdList>,
+) -> Result<Option<Vec<i32>>, ServerError> {
+    let Some(IdList(ids)) = ids else {
+        return Ok(None);
+    };
+    for id in &ids {
+        UnvalidatedCategoryId(*id).validate(user, database).await?;
+    }
+    Ok(Some(ids))
+}
+
+async fn validate_shop_ids(
+    database: &PgPool,
+    user: &User,
+    ids: Option<IdList>,
+) -> Result<Option<Vec<i32>>, ServerError> {
+    let Some(IdList(ids)) = ids else {
+        return Ok(None);
+    };
+    for id in &ids {
+        UnvalidatedShopId(*id).validate(user, database).await?;
+    }
+    Ok(Some(ids))
+}
+
+/// Builds the `WHERE` clause of a query on a table with a `user_id` column. Conditions are combined with `AND` and
+/// skipped if their value is `None`.
+pub struct Conditions<'b, 'args> {
+    query_builder: &'b mut QueryBuilder<'args, Postgres>,
+    alias: &'static str,
+}
+
+impl<'b, 'args> Conditions<'b, 'args> {
+    /// Only include rows of the user
+    pub fn new(
+        query_builder: &'b mut QueryBuilder<'args, Postgres>,
+        alias: &'static str,
+        user_id: i32,
+    ) -> Self {
+        query_builder
+            .push(format!(" WHERE {alias}.user_id = "))
+            .push_bind(user_id);
+        Conditions {
+            query_builder,
+            alias,
+        }
+    }
+
+    fn compare<T>(&mut self, column: &str, operator: &str, value: Option<T>) -> &mut Self
+    where
+        T: 'args + Encode<'args, Postgres> + Type<Postgres>,
+    {
+        if let Some(value) = value {
+            self.query_builder
+                .push(format!(" AND {}.{column} {operator} ", self.alias))
+                .push_bind(value);
+        }
+        self
+    }
+
+    pub fn eq<T>(&mut self, column: &str, value: Option<T>) -> &mut Self
+    where
+        T: 'args + Encode<'args, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "=", value)
+    }
+
+    pub fn ge<T>(&mut self, column: &str, value: Option<T>) -> &mut Self
+    where
+        T: 'args + Encode<'args, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, ">=", value)
+    }
+
+    pub fn le<T>(&mut self, column: &str, value: Option<T>) -> &mut Self
+    where
+        T: 'args + Encode<'args, Postgres> + Type<Postgres>,
+    {
+        self.compare(column, "<=", value)
+    }
+
+    /// Like [`Conditions::ge`], but rows where the column is `NULL` match as well
+    pub fn ge_or_null<T>(&mut self, column: &str, value: Option<T>) -> &mut Self
+    where
+        T: 'args + Encode<'args, Postgres> + Type<Postgres>,
+    {
+        if let Some(value) = value {
+            let column = format!("{}.{column}", self.alias);
+            self.query_builder
+                .push(format!(" AND ({column} >= "))
+                .push_bind(value)
+                .push(format!(" OR {column} IS NULL)"));
+        }
+        self
+    }
+
+    /// `Some(true)` only matches rows where the column is `NULL`, `Some(false)` only other rows
+    pub fn is_null(&mut self, column: &str, is_null: Option<bool>) -> &mut Self {
+        match is_null {
+            Some(true) => self
+                .query_builder
+                .push(format!(" AND {}.{column} IS NULL", self.alias)),
+            Some(false) => self
+                .query_builder
+                .push(format!(" AND {}.{column} IS NOT NULL", self.alias)),
+            None => self.query_builder,
+        };
+        self
+    }
+
+    pub fn column_filter(&mut self, column: &str, filter: &ColumnFilter<i32>) -> &mut Self {
+        match filter {
+            ColumnFilter::Any => self,
+            ColumnFilter::Null => self.is_null(column, Some(true)),
+            ColumnFilter::Specific(id) => self.eq(column, Some(*id)),
+        }
+    }
+
+    /// The column must be one of the ids
+    pub fn any(&mut self, column: &str, ids: Option<Vec<i32>>) -> &mut Self {
+        if let Some(ids) = ids {
+            self.query_builder
+                .push(format!(" AND {}.{column} = ANY(", self.alias))
+                .push_bind(ids)
+                .push(")");
+        }
+        self
+    }
+
+    /// The column must not be one of the ids, `NULL` matches
+    pub fn none(&mut self, column: &str, ids: Option<Vec<i32>>) -> &mut Self {
+        if let Some(ids) = ids {
+            self.query_builder
+                .push(format!(" AND NOT COALESCE({}.{column} = ANY(", self.alias))
+                .push_bind(ids)
+                .push("), FALSE)");
+        }
+        self
+    }
+
+    /// The column must contain the text, ignoring case
+    pub fn contains(&mut self, column: &str, text: Option<&str>) -> &mut Self {
+        if let Some(text) = text {
+            // `%`, `_` and the escape character itself are wildcards of ILIKE
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            self.query_builder
+                .push(format!(" AND {}.{column} ILIKE ", self.alias))
+                .push_bind(format!("%{escaped}%"));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_list() -> anyhow::Result<()> {
+        let ids: IdList = serde_json::from_str(r#""1, 2,3""#)?;
+        assert_eq!(ids, IdList::from(vec![1, 2, 3]));
+        ids.validate()?;
+
+        serde_json::from_str::<IdList>(r#""1,,2""#).expect_err("Empty id should cause an error");
+        serde_json::from_str::<IdList>(r#""1,a""#)
+            .expect_err("Non-numeric id should cause an error");
+        IdList::from(vec![1, 0])
+            .validate()
+            .expect_err("Id 0 should cause an error");
+        IdList::from((1..=MAX_IDS as i32 + 1).collect::<Vec<i32>>())
+            .validate()
+            .expect_err("Too many ids should cause an error");
+
+        Ok(())
+    }
+}
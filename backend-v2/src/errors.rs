@@ -1,10 +1,19 @@
 use axum::{
+    Json,
+    body::to_bytes,
     extract::rejection::{JsonRejection, QueryRejection},
-    http::StatusCode,
+    http::{
+        StatusCode,
+        header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER},
+    },
     response::{IntoResponse, Response},
 };
 use axum_login::tower_sessions::session;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::error::{DatabaseError, ErrorKind};
 use thiserror::Error;
+use utoipa::ToSchema;
 
 use crate::users::{self, Backend};
 
@@ -27,32 +36,313 @@ pub enum ServerError {
     #[error(transparent)]
     SqlxError(#[from] sqlx::Error),
 
+    /// The client has to wait for the given number of seconds before retrying
+    #[error("Too many requests, retry after {0} seconds")]
+    TooManyRequests(u64),
+
     /// The optional message will be sent to the user.
     #[error("{:?}", .0.canonical_reason())]
     Generic(StatusCode, Option<String>),
 }
 
+/// Error of a single request field, `path` uses the field names of the JSON request, e.g. `splits[0].memberId`
+#[derive(Debug, Serialize, PartialEq, Eq, ToSchema)]
+pub struct FieldError {
+    pub path: String,
+    pub message: String,
+}
+
+/// Convert a garde path of Rust field names to the camelCase names used in requests
+fn camel_case_path(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    let mut uppercase_next = false;
+    for char in path.chars() {
+        match char {
+            '_' => uppercase_next = true,
+            _ if uppercase_next => {
+                result.extend(char.to_uppercase());
+                uppercase_next = false;
+            }
+            _ => result.push(char),
+        }
+    }
+    result
+}
+
+fn field_errors(report: &garde::Report) -> Vec<FieldError> {
+    report
+        .iter()
+        .map(|(path, error)| FieldError {
+            path: camel_case_path(&path.to_string()),
+            message: error.message().to_owned(),
+        })
+        .collect()
+}
+
+/// Machine-readable error code derived from the status, e.g. `not_found`
+fn status_code_name(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .map(|reason| reason.to_lowercase().replace([' ', '-'], "_"))
+        .unwrap_or_else(|| "error".to_owned())
+}
+
+fn error_response(
+    status: StatusCode,
+    code: &str,
+    message: Option<String>,
+    fields: Vec<FieldError>,
+) -> Response {
+    let message = message
+        .or_else(|| status.canonical_reason().map(str::to_owned))
+        .unwrap_or_default();
+    (
+        status,
+        Json(json!({
+            "status": "error",
+            "code": code,
+            "message": message,
+            "fields": fields
+        })),
+    )
+        .into_response()
+}
+
+/// Messages for violations of known constraints, by constraint or unique index name
+const CONSTRAINT_MESSAGES: &[(&str, &str)] = &[
+    ("users_username_key", "Username is already taken"),
+    (
+        "categories_name_user_id_idx",
+        "A category with this name already exists",
+    ),
+    (
+        "shops_name_user_id_idx",
+        "A shop with this name already exists",
+    ),
+    (
+        "members_name_user_id_idx",
+        "A member with this name already exists",
+    ),
+    (
+        "saved_views_name_user_id_idx",
+        "A view with this name already exists",
+    ),
+    (
+        "recurring_transactions_interval_check",
+        "The end of the recurrence (`recurrence.dateTo`, `monthTo` or `yearTo`) must not be before its start",
+    ),
+    (
+        "recurring_transactions_interval_from_check",
+        "Only weekly and biweekly recurrences start on a `recurrence.dateFrom`, all others with a `monthFrom` or `yearFrom`",
+    ),
+    (
+        "recurring_transactions_interval_to_check",
+        "Only weekly and biweekly recurrences end on a `recurrence.dateTo`, all others with a `monthTo` or `yearTo`",
+    ),
+    (
+        "recurring_transactions_interval_from_month_check",
+        "Yearly recurrences must start with `recurrence.yearFrom`",
+    ),
+    (
+        "recurring_transactions_interval_to_month_check",
+        "Yearly recurrences must end with `recurrence.yearTo`",
+    ),
+    (
+        "recurring_transactions_interval_months_check",
+        "`recurrence.months` must be set for and only for every-N-months recurrences",
+    ),
+    (
+        "recurring_transactions_interval_months_range_check",
+        "`recurrence.months` must be between 2 and 120",
+    ),
+    (
+        "recurring_transactions_day_of_month_range_check",
+        "`recurrence.dayOfMonth` must be between 1 and 31",
+    ),
+    (
+        "recurring_transactions_month_of_year_range_check",
+        "`recurrence.month` must be between 1 and 12",
+    ),
+    (
+        "recurring_transactions_day_of_month_check",
+        "Weekly and biweekly recurrences can't have a `recurrence.dayOfMonth`",
+    ),
+    (
+        "recurring_transactions_month_of_year_check",
+        "`recurrence.month` must be set together with `dayOfMonth`, and only for yearly recurrences",
+    ),
+    (
+        "recurring_transaction_amounts_effective_from_check",
+        "`effectiveFrom` must be the first day of a month",
+    ),
+    (
+        "recurring_transaction_amounts_amount_check",
+        "`amount` must be positive",
+    ),
+    (
+        "recurring_transaction_amounts_effective_from_idx",
+        "The amount of this month has already been revised",
+    ),
+    (
+        "recurring_transaction_exceptions_month_check",
+        "The month of an exception must be its first day",
+    ),
+    (
+        "recurring_transaction_exceptions_amount_check",
+        "`amount` must be positive",
+    ),
+    (
+        "recurring_transaction_exceptions_override_check",
+        "Skipped occurrences can't set `amount` or `description`, all others must set at least one of them",
+    ),
+    (
+        "settlements_members_check",
+        "A settlement needs two different members",
+    ),
+    ("settlements_amount_check", "`amount` must be positive"),
+    (
+        "transaction_split_shares_value_check",
+        "Share values must not be negative",
+    ),
+];
+
+/// Map constraint violations to client errors, as they are caused by the request rather than by the server. Return
+/// None for all other database errors.
+fn constraint_violation(err: &dyn DatabaseError) -> Option<Response> {
+    let (status, code, fallback) = match err.kind() {
+        ErrorKind::UniqueViolation => (
+            StatusCode::CONFLICT,
+            "unique_violation",
+            "An entry with these values already exists",
+        ),
+        // Deleting or updating a row that other rows still reference fails with a message like
+        // `update or delete on table "members" violates foreign key constraint ...`
+        ErrorKind::ForeignKeyViolation if err.message().starts_with("update or delete") => (
+            StatusCode::BAD_REQUEST,
+            "foreign_key_violation",
+            "This entry is still referenced by other entries",
+        ),
+        ErrorKind::ForeignKeyViolation => (
+            StatusCode::BAD_REQUEST,
+            "foreign_key_violation",
+            "A referenced entry does not exist",
+        ),
+        ErrorKind::CheckViolation => (
+            StatusCode::BAD_REQUEST,
+            "check_violation",
+            "The values are not allowed",
+        ),
+        ErrorKind::NotNullViolation => (
+            StatusCode::BAD_REQUEST,
+            "not_null_violation",
+            "A required value is missing",
+        ),
+        _ => return None,
+    };
+
+    let message = err
+        .constraint()
+        .and_then(|constraint| {
+            CONSTRAINT_MESSAGES
+                .iter()
+                .find(|(name, _)| *name == constraint)
+        })
+        .map_or(fallback, |(_, message)| message);
+
+    Some(error_response(
+        status,
+        code,
+        Some(message.to_owned()),
+        vec![],
+    ))
+}
+
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
         match self {
-            ServerError::GardeValidationError(_) => {
-                let message = format!("Input validation error: {self}");
-                (StatusCode::BAD_REQUEST, message).into_response()
-            }
-            ServerError::AxumJsonRejection(_) | ServerError::AxumQueryRejection(_) => {
-                let message = format!("Json format error: {self}");
-                (StatusCode::BAD_REQUEST, message).into_response()
-            }
+            ServerError::GardeValidationError(report) => error_response(
+                StatusCode::BAD_REQUEST,
+                "validation_error",
+                Some("Input validation error".to_owned()),
+                field_errors(&report),
+            ),
+            ServerError::AxumJsonRejection(rejection) => error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_json",
+                Some(rejection.body_text()),
+                vec![],
+            ),
+            ServerError::AxumQueryRejection(rejection) => error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_query",
+                Some(rejection.body_text()),
+                vec![],
+            ),
             ServerError::AxumAuthError(_) | ServerError::SqlxError(_) => {
+                if let ServerError::SqlxError(sqlx::Error::Database(err)) = &self
+                    && let Some(response) = constraint_violation(err.as_ref())
+                {
+                    return response;
+                }
+
                 log::error!("{self:?}");
-                (StatusCode::INTERNAL_SERVER_ERROR).into_response()
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal_error",
+                    None,
+                    vec![],
+                )
+            }
+            ServerError::TooManyRequests(retry_after) => {
+                let mut response = error_response(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "too_many_requests",
+                    Some(self.to_string()),
+                    vec![],
+                );
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, retry_after.into());
+                response
+            }
+            ServerError::Generic(status, message) => {
+                error_response(status, &status_code_name(status), message, vec![])
             }
-            ServerError::Generic(status, Some(message)) => (status, message).into_response(),
-            ServerError::Generic(status, None) => status.into_response(),
         }
     }
 }
 
+/// Largest plain text error body that is copied into the JSON message
+const MAX_FALLBACK_BODY: usize = 4096;
+
+/// Convert error responses that weren't created from a `ServerError` to the same JSON format, e.g. rejections of
+/// the `Path` extractor, `login_required!` or unknown methods
+pub async fn json_error_fallback(response: Response) -> Response {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let message = to_bytes(body, MAX_FALLBACK_BODY)
+        .await
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes.to_vec()).ok())
+        .filter(|text| !text.is_empty());
+
+    let mut response = error_response(status, &status_code_name(status), message, vec![]);
+    for (name, value) in parts.headers.iter() {
+        if name != CONTENT_TYPE && name != CONTENT_LENGTH {
+            response.headers_mut().append(name, value.clone());
+        }
+    }
+    response
+}
+
 // `users::Error` is a subset of errors inside `ServerError::AxumAuthError`, so map it accordingly
 impl From<users::Error> for ServerError {
     fn from(value: users::Error) -> Self {
@@ -72,3 +362,113 @@ impl From<StatusCode> for ServerError {
         ServerError::Generic(value, None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use garde::Validate;
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::users::PasswordChange;
+
+    async fn body_json(response: Response) -> anyhow::Result<Value> {
+        Ok(serde_json::from_slice(
+            &response.into_body().collect().await?.to_bytes(),
+        )?)
+    }
+
+    #[test]
+    fn test_camel_case_path() {
+        assert_eq!(camel_case_path("new_password"), "newPassword");
+        assert_eq!(camel_case_path("splits[0].member_id"), "splits[0].memberId");
+        assert_eq!(camel_case_path(""), "");
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_fields() -> anyhow::Result<()> {
+        let report = PasswordChange {
+            current_password: "password".into(),
+            new_password: "short".into(),
+        }
+        .validate()
+        .expect_err("Short password should be rejected");
+
+        let response = ServerError::from(report).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = body_json(response).await?;
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["code"], "validation_error");
+        assert_eq!(body["fields"][0]["path"], "newPassword");
+        assert!(body["fields"][0]["message"].is_string());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generic_error() -> anyhow::Result<()> {
+        let response = ServerError::Generic(StatusCode::NOT_FOUND, None).into_response();
+        let body = body_json(response).await?;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "Not Found");
+        assert_eq!(body["fields"], Value::Array(vec![]));
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(path = "app/resources/fixtures", scripts("base")))]
+    async fn test_constraint_violations(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        // Rename a category to the name of another category of the same user
+        let err =
+            sqlx::query!("UPDATE categories SET name = 'Groceries' WHERE name = 'Restaurants'")
+                .execute(&pool)
+                .await
+                .expect_err("Duplicate name was accepted");
+        let response = ServerError::from(err).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = body_json(response).await?;
+        assert_eq!(body["code"], "unique_violation");
+        assert_eq!(body["message"], "A category with this name already exists");
+
+        let err = sqlx::query!(
+            r#"
+            INSERT INTO recurring_transactions
+                (user_id, category_id, is_expense, amount, frequency, interval_from, interval_to)
+            VALUES (1, 1, true, 100, 'monthly', '2026-02-01', '2026-01-01')
+            "#
+        )
+        .execute(&pool)
+        .await
+        .expect_err("Invalid interval was accepted");
+        let response = ServerError::from(err).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await?;
+        assert_eq!(body["code"], "check_violation");
+        assert_eq!(
+            body["message"],
+            "The end of the recurrence (`recurrence.dateTo`, `monthTo` or `yearTo`) must not be before its start"
+        );
+
+        // Other database errors stay internal errors
+        let response = ServerError::from(sqlx::Error::RowNotFound).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_error_fallback() -> anyhow::Result<()> {
+        let plain = (StatusCode::BAD_REQUEST, "Invalid URL").into_response();
+        let response = json_error_fallback(plain).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await?;
+        assert_eq!(body["code"], "bad_request");
+        assert_eq!(body["message"], "Invalid URL");
+
+        let success = json_error_fallback("ok".into_response()).await;
+        assert_eq!(success.into_body().collect().await?.to_bytes(), "ok");
+
+        Ok(())
+    }
+}
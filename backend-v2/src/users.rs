@@ -1,5 +1,5 @@
 use argon2::{
-    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
     password_hash::{SaltString, rand_core::OsRng},
 };
 use async_trait::async_trait;
@@ -7,24 +7,58 @@ use axum_login::{AuthUser, AuthnBackend, UserId};
 use chrono::{DateTime, Utc};
 use garde::Validate;
 use serde::Deserialize;
-use sqlx::{Pool, Postgres};
+use sqlx::{PgExecutor, Pool, Postgres};
 use tokio::task;
+use utoipa::ToSchema;
 
 // Validate minimum password length of 8 only for newly registered accounts
-#[derive(Debug, Clone, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct RegisterCredentials {
     #[garde(length(graphemes, min = 1))]
     pub username: String,
     #[garde(length(graphemes, min = 8))]
     pub password: String,
+    /// Required if registration is invite-only
+    #[garde(inner(length(min = 1)))]
+    pub invite_code: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct LoginCredentials {
     #[garde(length(graphemes, min = 1))]
     pub username: String,
     #[garde(length(graphemes, min = 1))]
     pub password: String,
+    /// Issue a long-lived session instead of the default one
+    #[serde(default)]
+    #[garde(skip)]
+    pub remember_me: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PasswordChange {
+    #[garde(length(graphemes, min = 1))]
+    pub current_password: String,
+    #[garde(length(graphemes, min = 8))]
+    pub new_password: String,
+}
+
+/// Password set by an administrator
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PasswordReset {
+    #[garde(length(graphemes, min = 8))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AccountDeletion {
+    #[garde(length(graphemes, min = 1))]
+    pub password: String,
 }
 
 #[derive(Clone)]
@@ -34,6 +68,8 @@ pub struct User {
     pub hash: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub is_admin: bool,
+    pub disabled: bool,
 }
 
 // Do not reveal password hash
@@ -45,6 +81,8 @@ impl std::fmt::Debug for User {
             .field("hash", &"[redacted]")
             .field("createdAt", &self.created_at)
             .field("updatedAt", &self.updated_at)
+            .field("isAdmin", &self.is_admin)
+            .field("disabled", &self.disabled)
             .finish()
     }
 }
@@ -76,34 +114,180 @@ pub enum Error {
 #[derive(Clone, Debug)]
 pub struct Backend {
     pub database: Pool<Postgres>,
+    /// Argon2 parameters for new hashes
+    pub hash_params: Params,
 }
 
 impl Backend {
+    /// Create a backend that hashes with the defaults of the argon2 crate
     pub fn new(database: Pool<Postgres>) -> Self {
-        Self { database }
+        Self {
+            database,
+            hash_params: Params::default(),
+        }
+    }
+
+    pub fn with_hash_params(mut self, hash_params: Params) -> Self {
+        self.hash_params = hash_params;
+        self
     }
 }
 
+fn hash_blocking(secret: &str, params: &Params) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+        .hash_password(secret.as_bytes(), &salt)?;
+    Ok(hash.serialize().to_string())
+}
+
+/// Hash a password or any other secret with Argon2id and the given parameters. Hashing is blocking and potentially
+/// slow, so we'll do so via `spawn_blocking`
+pub async fn hash_secret(secret: String, params: &Params) -> Result<String, Error> {
+    let params = params.clone();
+    task::spawn_blocking(move || hash_blocking(&secret, &params)).await?
+}
+
+/// Hash several secrets like [`hash_secret`] within a single blocking task
+pub async fn hash_secrets(secrets: Vec<String>, params: &Params) -> Result<Vec<String>, Error> {
+    let params = params.clone();
+    task::spawn_blocking(move || {
+        secrets
+            .iter()
+            .map(|secret| hash_blocking(secret, &params))
+            .collect()
+    })
+    .await?
+}
+
+/// Check whether a hash uses an older algorithm or weaker parameters than the given ones. Hashes with stronger
+/// parameters are kept, so lowering the configuration never weakens existing hashes.
+pub fn needs_rehash(hash: &str, current: &Params) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&hash) else {
+        return true;
+    };
+
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(Version::V0x13.into())
+        || params.m_cost() < current.m_cost()
+        || params.t_cost() < current.t_cost()
+        || params.p_cost() < current.p_cost()
+}
+
+/// Check a secret against an Argon2 hash. Verifying is blocking and potentially slow, so we'll do so via
+/// `spawn_blocking`
+pub async fn verify_secret(hash: String, secret: String) -> Result<bool, Error> {
+    let valid = task::spawn_blocking(move || {
+        PasswordHash::new(&hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(secret.as_bytes(), &hash)
+                .is_ok()
+        })
+    })
+    .await?;
+
+    Ok(valid)
+}
+
+/// Grant admin rights to the given users, e.g. to bootstrap the first administrator. Return the usernames that don't
+/// exist.
+pub async fn promote_admins(
+    database: &Pool<Postgres>,
+    usernames: &[String],
+) -> Result<Vec<String>, Error> {
+    let promoted = sqlx::query_scalar!(
+        "UPDATE users SET is_admin = true WHERE username = ANY($1) RETURNING username",
+        usernames
+    )
+    .fetch_all(database)
+    .await?;
+
+    Ok(usernames
+        .iter()
+        .filter(|username| !promoted.contains(username))
+        .cloned()
+        .collect())
+}
+
+/// Insert a new user, or return None if the username is already taken
+pub async fn insert_user<'e>(
+    executor: impl PgExecutor<'e>,
+    credentials: RegisterCredentials,
+    hash_params: &Params,
+) -> Result<Option<User>, Error> {
+    let hash = hash_secret(credentials.password, hash_params).await?;
+
+    let user = sqlx::query_as!(
+        User,
+        "INSERT INTO users (username, hash) VALUES ($1, $2) ON CONFLICT (username) DO NOTHING RETURNING *",
+        credentials.username,
+        hash
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(user)
+}
+
+pub async fn verify_password(user: &User, password: String) -> Result<bool, Error> {
+    verify_secret(user.hash.clone(), password).await
+}
+
 impl Backend {
     /// Create and return a new user with the given credentials, or return None if the username is already taken
     pub async fn create_user(
         &self,
         credentials: RegisterCredentials,
     ) -> Result<Option<User>, Error> {
-        let salt = SaltString::generate(&mut OsRng);
-        let hash = Argon2::default().hash_password(credentials.password.as_bytes(), &salt)?;
+        insert_user(&self.database, credentials, &self.hash_params).await
+    }
+
+    /// Set a new password and return the updated user, or return None if the current password is wrong.
+    /// Changing the hash invalidates all sessions of the user, as it is used as `session_auth_hash`.
+    pub async fn change_password(
+        &self,
+        user: &User,
+        params: PasswordChange,
+    ) -> Result<Option<User>, Error> {
+        if !verify_password(user, params.current_password).await? {
+            return Ok(None);
+        }
 
+        self.set_password(user.id, &params.new_password).await
+    }
+
+    /// Overwrite the password of a user without verifying the current one. Return None if the user doesn't exist.
+    pub async fn set_password(&self, user_id: i32, password: &str) -> Result<Option<User>, Error> {
+        let hash = hash_secret(password.to_owned(), &self.hash_params).await?;
         let user = sqlx::query_as!(
             User,
-            "INSERT INTO users (username, hash) VALUES ($1, $2) ON CONFLICT (username) DO NOTHING RETURNING *",
-            credentials.username.into(),
-            hash.serialize().to_string()
+            "UPDATE users SET hash = $2 WHERE id = $1 RETURNING *",
+            user_id,
+            hash
         )
         .fetch_optional(&self.database)
         .await?;
 
         Ok(user)
     }
+
+    /// Delete a user together with all rows owned by them. Return false if the password is wrong.
+    pub async fn delete_user(&self, user: &User, params: AccountDeletion) -> Result<bool, Error> {
+        if !verify_password(user, params.password).await? {
+            return Ok(false);
+        }
+
+        // All user-owned tables reference the user with `ON DELETE CASCADE`
+        let mut transaction = self.database.begin().await?;
+        sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(true)
+    }
 }
 
 #[async_trait]
@@ -118,7 +302,7 @@ impl AuthnBackend for Backend {
     ) -> Result<Option<Self::User>, Self::Error> {
         let user = sqlx::query_as!(
             User,
-            "SELECT * FROM users WHERE username = $1",
+            "SELECT * FROM users WHERE username = $1 AND NOT disabled",
             credentials.username
         )
         .fetch_optional(&self.database)
@@ -126,26 +310,37 @@ impl AuthnBackend for Backend {
 
         let Some(user) = user else { return Ok(None) };
 
-        // Verifying the password is blocking and potentially slow, so we'll do so via
-        // `spawn_blocking`
-        return task::spawn_blocking(move || {
-            if PasswordHash::new(&user.hash).is_ok_and(|hash| {
-                Argon2::default()
-                    .verify_password(credentials.password.as_bytes(), &hash)
-                    .is_ok()
-            }) {
-                Ok(Some(user))
-            } else {
-                Ok(None)
-            }
-        })
-        .await?;
+        if !verify_password(&user, credentials.password.clone()).await? {
+            return Ok(None);
+        }
+
+        // The password is only available in plain text during login, so this is the only chance to upgrade the hash.
+        // The new hash ends the other sessions of the user, as it is used as `session_auth_hash`.
+        if needs_rehash(&user.hash, &self.hash_params) {
+            let hash = hash_secret(credentials.password, &self.hash_params).await?;
+            let user = sqlx::query_as!(
+                User,
+                "UPDATE users SET hash = $2 WHERE id = $1 RETURNING *",
+                user.id,
+                hash
+            )
+            .fetch_one(&self.database)
+            .await?;
+            return Ok(Some(user));
+        }
+
+        Ok(Some(user))
     }
 
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
-        let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
-            .fetch_optional(&self.database)
-            .await?;
+        // Disabled users are logged out of all sessions
+        let user = sqlx::query_as!(
+            User,
+            "SELECT * FROM users WHERE id = $1 AND NOT disabled",
+            user_id
+        )
+        .fetch_optional(&self.database)
+        .await?;
 
         Ok(user)
     }
@@ -164,6 +359,7 @@ mod tests {
             RegisterCredentials {
                 username: "user".into(),
                 password: "longenough".into(),
+                invite_code: None,
             }
             .validate()
             .is_ok()
@@ -173,6 +369,7 @@ mod tests {
             RegisterCredentials {
                 username: "".into(),
                 password: "longenough".into(),
+                invite_code: None,
             }
             .validate()
             .is_err()
@@ -182,6 +379,7 @@ mod tests {
             RegisterCredentials {
                 username: "user".into(),
                 password: "short".into(),
+                invite_code: None,
             }
             .validate()
             .is_err()
@@ -194,6 +392,7 @@ mod tests {
             LoginCredentials {
                 username: "user".into(),
                 password: "any".into(),
+                remember_me: false,
             }
             .validate()
             .is_ok()
@@ -203,6 +402,7 @@ mod tests {
             LoginCredentials {
                 username: "".into(),
                 password: "any".into(),
+                remember_me: false,
             }
             .validate()
             .is_err()
@@ -212,6 +412,7 @@ mod tests {
             LoginCredentials {
                 username: "user".into(),
                 password: "".into(),
+                remember_me: false,
             }
             .validate()
             .is_err()
@@ -226,6 +427,8 @@ mod tests {
             hash: "secret_hash".to_string(),
             created_at: DateTime::UNIX_EPOCH,
             updated_at: DateTime::UNIX_EPOCH,
+            is_admin: false,
+            disabled: false,
         };
 
         assert!(!format!("{user:?}").contains("secret_hash"));
@@ -238,6 +441,7 @@ mod tests {
         let creds = RegisterCredentials {
             username: "testuser".to_string(),
             password: "strongpassword".to_string(),
+            invite_code: None,
         };
 
         let result = backend.create_user(creds.clone()).await?;
@@ -259,6 +463,7 @@ mod tests {
         let creds = RegisterCredentials {
             username: "auth_success".to_string(),
             password: "securepassword".to_string(),
+            invite_code: None,
         };
 
         backend
@@ -270,6 +475,7 @@ mod tests {
             .authenticate(LoginCredentials {
                 username: creds.username,
                 password: creds.password,
+                remember_me: false,
             })
             .await?;
 
@@ -284,6 +490,7 @@ mod tests {
         let creds = RegisterCredentials {
             username: "auth_wrong_password".to_string(),
             password: "rightpassword".to_string(),
+            invite_code: None,
         };
 
         backend.create_user(creds.clone()).await?;
@@ -291,6 +498,7 @@ mod tests {
         let login = LoginCredentials {
             username: creds.username,
             password: "wrongpassword".to_string(),
+            remember_me: false,
         };
 
         let user = backend.authenticate(login).await?;
@@ -309,6 +517,7 @@ mod tests {
         let login = LoginCredentials {
             username: "ghostuser".to_string(),
             password: "whatever".to_string(),
+            remember_me: false,
         };
 
         let user = backend.authenticate(login).await?;
@@ -320,6 +529,118 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn test_authenticate_disabled_user(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let backend = Backend::new(pool.clone());
+
+        let user = backend
+            .create_user(RegisterCredentials {
+                username: "disabled".to_string(),
+                password: "password".to_string(),
+                invite_code: None,
+            })
+            .await?
+            .expect("User was not created");
+
+        sqlx::query!("UPDATE users SET disabled = true WHERE id = $1", user.id)
+            .execute(&pool)
+            .await?;
+
+        let login = LoginCredentials {
+            username: "disabled".to_string(),
+            password: "password".to_string(),
+            remember_me: false,
+        };
+
+        assert!(
+            backend.authenticate(login).await?.is_none(),
+            "Expected authentication to fail for disabled user"
+        );
+        assert!(backend.get_user(&user.id).await?.is_none());
+
+        Ok(())
+    }
+
+    fn weak_hash(algorithm: Algorithm, password: &str) -> anyhow::Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2 = Argon2::new(algorithm, Version::V0x13, Params::new(64, 1, 1, None)?);
+        Ok(argon2
+            .hash_password(password.as_bytes(), &salt)?
+            .serialize()
+            .to_string())
+    }
+
+    #[tokio::test]
+    async fn test_needs_rehash() -> anyhow::Result<()> {
+        let params = Params::default();
+        assert!(!needs_rehash(
+            &hash_secret("password".to_string(), &params).await?,
+            &params
+        ));
+        assert!(needs_rehash(
+            &weak_hash(Algorithm::Argon2id, "password")?,
+            &params
+        ));
+        assert!(needs_rehash(
+            &weak_hash(Algorithm::Argon2i, "password")?,
+            &params
+        ));
+
+        let strong = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(Params::DEFAULT_M_COST * 2, 3, 1, None)?,
+        );
+        let hash = strong
+            .hash_password(b"password", &SaltString::generate(&mut OsRng))?
+            .serialize()
+            .to_string();
+        assert!(!needs_rehash(&hash, &params));
+
+        // Stronger configured parameters require a rehash of the default ones
+        let stronger = Params::new(Params::DEFAULT_M_COST * 2, 3, 1, None)?;
+        assert!(needs_rehash(
+            &hash_secret("password".to_string(), &params).await?,
+            &stronger
+        ));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_authenticate_upgrades_weak_hash(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let backend = Backend::new(pool.clone());
+
+        let user = backend
+            .create_user(RegisterCredentials {
+                username: "legacy".to_string(),
+                password: "password".to_string(),
+                invite_code: None,
+            })
+            .await?
+            .expect("User was not created");
+        let weak = weak_hash(Algorithm::Argon2i, "password")?;
+        sqlx::query!("UPDATE users SET hash = $2 WHERE id = $1", user.id, weak)
+            .execute(&pool)
+            .await?;
+
+        let user = backend
+            .authenticate(LoginCredentials {
+                username: "legacy".to_string(),
+                password: "password".to_string(),
+                remember_me: false,
+            })
+            .await?
+            .expect("Expected authentication to succeed");
+        assert_ne!(user.hash, weak);
+        assert!(!needs_rehash(&user.hash, &backend.hash_params));
+
+        // The upgraded hash still verifies
+        assert!(verify_password(&user, "password".to_string()).await?);
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn test_get_user_success(pool: Pool<Postgres>) -> anyhow::Result<()> {
         let backend = Backend::new(pool);
@@ -327,6 +648,7 @@ mod tests {
         let creds = RegisterCredentials {
             username: "get_user_test".to_string(),
             password: "validpassword".to_string(),
+            invite_code: None,
         };
 
         let user = backend
@@ -354,4 +676,110 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn test_change_password(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let backend = Backend::new(pool);
+
+        let user = backend
+            .create_user(RegisterCredentials {
+                username: "change_password".to_string(),
+                password: "oldpassword".to_string(),
+                invite_code: None,
+            })
+            .await?
+            .expect("Failed to create user");
+
+        let rejected = backend
+            .change_password(
+                &user,
+                PasswordChange {
+                    current_password: "wrongpassword".to_string(),
+                    new_password: "newpassword".to_string(),
+                },
+            )
+            .await?;
+        assert!(rejected.is_none(), "Password changed with wrong password");
+
+        let updated = backend
+            .change_password(
+                &user,
+                PasswordChange {
+                    current_password: "oldpassword".to_string(),
+                    new_password: "newpassword".to_string(),
+                },
+            )
+            .await?
+            .expect("Failed to change password");
+        assert_ne!(updated.session_auth_hash(), user.session_auth_hash());
+
+        let login = |password: &str| LoginCredentials {
+            username: "change_password".to_string(),
+            password: password.to_string(),
+            remember_me: false,
+        };
+        assert!(backend.authenticate(login("oldpassword")).await?.is_none());
+        assert!(backend.authenticate(login("newpassword")).await?.is_some());
+
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(
+        path = "app/resources/fixtures",
+        scripts("base", "oneoff", "recurring", "members")
+    ))]
+    async fn test_delete_user(pool: Pool<Postgres>) -> anyhow::Result<()> {
+        let backend = Backend::new(pool.clone());
+        let user = backend.get_user(&1).await?.expect("User not found");
+
+        let deleted = backend
+            .delete_user(
+                &user,
+                AccountDeletion {
+                    password: "wrongpassword".to_string(),
+                },
+            )
+            .await?;
+        assert!(!deleted, "User deleted with wrong password");
+
+        // Fixture users have no valid hash, so set a known password first
+        let user = sqlx::query_as!(
+            User,
+            "UPDATE users SET hash = $2 WHERE id = $1 RETURNING *",
+            user.id,
+            hash_secret("password".to_string(), &backend.hash_params).await?
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        let deleted = backend
+            .delete_user(
+                &user,
+                AccountDeletion {
+                    password: "password".to_string(),
+                },
+            )
+            .await?;
+        assert!(deleted);
+        assert!(backend.get_user(&1).await?.is_none());
+
+        let remaining = sqlx::query_scalar!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM categories WHERE user_id = 1)
+                + (SELECT COUNT(*) FROM shops WHERE user_id = 1)
+                + (SELECT COUNT(*) FROM oneoff_transactions WHERE user_id = 1)
+                + (SELECT COUNT(*) FROM recurring_transactions WHERE user_id = 1)
+                + (SELECT COUNT(*) FROM members WHERE user_id = 1) as "count!"
+            "#
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(remaining, 0);
+
+        // Other users are not affected
+        assert!(backend.get_user(&2).await?.is_some());
+
+        Ok(())
+    }
 }